@@ -0,0 +1,182 @@
+use crate::core::{
+    all_stream_stats, all_user_stats, audit_log, overlay_events, remove_responder, save_responder,
+    stored_responder, stored_responders, stream_stats_csv, user_stats_csv, AuditLog, AutoResponder,
+    Change, ResponderError, StatsError, Storage,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    error::Error,
+    thread::{self, JoinHandle},
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Starts a thread serving the viewer statistics over HTTP:
+/// `/users`, `/users/<name>` and `/streams` as JSON, `/users.csv` and `/streams.csv` as CSV.
+/// Stream overlays poll `/overlay/events?since=<milliseconds>` for the events of cheers.
+/// `/audit` lists the changes moderators and the API made to commands, repeating messages,
+/// rewards and auto responders.
+/// `/responders` lists the auto responders added in chat or over the API, `PUT /responders`
+/// adds or replaces one given as JSON and `DELETE /responders/<name>` removes one.
+/// Changes need the token in an `Authorization: Bearer <token>` header, without a token
+/// nothing can be changed.
+pub fn start_stats_api(
+    address: &str,
+    token: Option<&str>,
+    storage: Storage,
+) -> Result<JoinHandle<()>, Box<dyn Error + Send + Sync>> {
+    let server = Server::http(address)?;
+    println!("Serving statistics on http://{}", address);
+    let token = token.map(str::to_owned);
+    Ok(thread::spawn(move || {
+        let mut audit = AuditLog::new(storage.clone());
+        for mut request in server.incoming_requests() {
+            let response = match request.method() {
+                Method::Put | Method::Delete if !is_authorized(&request, token.as_deref()) => {
+                    unauthorized()
+                }
+                Method::Put | Method::Delete => handle_change(&mut request, &storage, &mut audit),
+                _ => handle_request(&request, &storage),
+            };
+            if let Err(err) = request.respond(response) {
+                println!("Could not send statistics response: {:?}", err);
+            }
+        }
+    }))
+}
+
+fn content(body: String, content_type: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", content_type).expect("Valid header");
+    Response::from_string(body).with_header(header)
+}
+
+fn json<T: Serialize>(value: &T) -> Result<Response<std::io::Cursor<Vec<u8>>>, StatsError> {
+    let body = serde_json::to_string(value).unwrap_or_default();
+    Ok(content(body, "application/json"))
+}
+
+fn not_found() -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string("Not found").with_status_code(404)
+}
+
+fn bad_request(message: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(message).with_status_code(400)
+}
+
+fn unauthorized() -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string("Unauthorized").with_status_code(401)
+}
+
+fn is_authorized(request: &Request, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => return false,
+    };
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        == Some(token)
+}
+
+// Changes are recorded in the audit log as made by the user "api"
+fn handle_change(
+    request: &mut Request,
+    storage: &Storage,
+    audit: &mut AuditLog,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let path = request.url().to_owned();
+    let action = format!("{} {}", request.method(), path);
+    let old = |name: &str| {
+        stored_responder(storage, name).unwrap_or_else(|err| {
+            println!("Could not load auto responder: {:?}", err);
+            None
+        })
+    };
+    let result = match (request.method(), path.as_str()) {
+        (Method::Put, "/responders") => {
+            let mut body = String::new();
+            if let Err(err) = request.as_reader().read_to_string(&mut body) {
+                return bad_request(format!("Could not read the request [{}]", err));
+            }
+            let responder: AutoResponder = match serde_json::from_str(&body) {
+                Ok(responder) => responder,
+                Err(err) => return bad_request(format!("Invalid auto responder [{}]", err)),
+            };
+            let change = Change::Responder {
+                name: responder.name.to_lowercase(),
+                old: old(&responder.name),
+                new: Some(responder.to_owned()),
+            };
+            save_responder(storage, responder).map(|_| change)
+        }
+        (Method::Delete, path) => match path.strip_prefix("/responders/") {
+            Some(name) => {
+                let change = Change::Responder {
+                    name: name.to_lowercase(),
+                    old: old(name),
+                    new: None,
+                };
+                remove_responder(storage, name)
+                    .map(|_| change)
+                    .map_err(ResponderError::from)
+            }
+            None => return not_found(),
+        },
+        _ => return not_found(),
+    };
+    match result {
+        Ok(change) => {
+            audit.record("api", &action, vec![change], None, Utc::now());
+            Response::from_string("Ok")
+        }
+        Err(err @ ResponderError::Regex(_)) => bad_request(err.to_string()),
+        Err(err) => {
+            println!("Could not change auto responders: {:?}", err);
+            Response::from_string("Internal server error").with_status_code(500)
+        }
+    }
+}
+
+fn handle_request(request: &Request, storage: &Storage) -> Response<std::io::Cursor<Vec<u8>>> {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let response = match path {
+        "/users" => all_user_stats(storage).and_then(|stats| json(&stats)),
+        "/users.csv" => user_stats_csv(storage).map(|csv| content(csv, "text/csv")),
+        "/streams" => all_stream_stats(storage).and_then(|stats| json(&stats)),
+        "/streams.csv" => stream_stats_csv(storage).map(|csv| content(csv, "text/csv")),
+        "/responders" => stored_responders(storage)
+            .map_err(StatsError::from)
+            .and_then(|responders| json(&responders)),
+        "/audit" => audit_log(storage)
+            .map_err(StatsError::from)
+            .and_then(|entries| json(&entries)),
+        "/overlay/events" => {
+            let since = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("since="))
+                .and_then(|since| since.parse().ok())
+                .unwrap_or_default();
+            overlay_events(storage, since)
+                .map_err(StatsError::from)
+                .and_then(|events| json(&events))
+        }
+        _ => match path.strip_prefix("/users/") {
+            Some(user_name) => all_user_stats(storage).and_then(|stats| {
+                match stats
+                    .iter()
+                    .find(|stats| stats.user.eq_ignore_ascii_case(user_name))
+                {
+                    Some(stats) => json(stats),
+                    None => Ok(not_found()),
+                }
+            }),
+            None => Ok(not_found()),
+        },
+    };
+    response.unwrap_or_else(|err| {
+        println!("Could not serve statistics: {:?}", err);
+        Response::from_string("Internal server error").with_status_code(500)
+    })
+}
@@ -1,54 +1,121 @@
-use dotenv::dotenv;
-use std::env::{self, VarError};
-use thiserror::Error;
-
-#[derive(Debug)]
-pub struct AppConfig {
-    channel_name: String,
-    bot_user_name: String,
-    twitch_client_id: String,
-    twitch_client_secret: String,
-}
-
-#[derive(Debug, Error)]
-pub enum AppConfigError {
-    #[error("Environment variable error [{}]", .0)]
-    EnvironmentVar(#[from] VarError),
-}
-
-impl AppConfig {
-    pub fn new() -> Result<AppConfig, AppConfigError> {
-        dotenv().ok();
-        Ok(AppConfig {
-            channel_name: env::var("TWITCH_CHANNEL")
-                .unwrap_or_else(|_| "captaincallback".to_string()),
-            bot_user_name: env::var("TWITCH_CHAT_USER")?,
-            twitch_client_id: env::var("TWITCH_AUTH_CLIENT_ID")?,
-            twitch_client_secret: env::var("TWITCH_AUTH_CLIENT_SECRET")?,
-        })
-    }
-
-    /// Get a reference to the config's channel name.
-    /// this value is provided by the TWITCH_CHANNEL environment variable
-    pub fn channel_name(&self) -> &str {
-        self.channel_name.as_ref()
-    }
-
-    /// Get a reference to the config's bot user name.
-    /// this value is provided by the TWITCH_CHAR_USER environment variable
-    pub fn bot_user_name(&self) -> &str {
-        self.bot_user_name.as_ref()
-    }
-
-    /// Get a reference to the config's twitch client id.
-    /// this value is provided by the TWITCH_AUTH_CLIENT_ID environment variable
-    pub fn twitch_client_id(&self) -> &str {
-        self.twitch_client_id.as_ref()
-    }
-
-    /// Get a reference to the config's twitch client secret.
-    /// this value is provided by the TWITCH_AUTH_CLIENT_SECRET environment variable
-    pub fn twitch_client_secret(&self) -> &str {
-        self.twitch_client_secret.as_ref()
-    }
-}
+use dotenv::dotenv;
+use std::env::{self, VarError};
+use thiserror::Error;
+
+/// The files and directories of the bot. Unlike the `AppConfig`, they do not need
+/// the twitch credentials, which offline commands like `retrain-classifier` do without.
+#[derive(Debug)]
+pub struct PathConfig {
+    bot_config_file: String,
+    bot_storage_path: String,
+    bot_log_directory: String,
+}
+
+#[derive(Debug)]
+pub struct AppConfig {
+    channel_name: String,
+    bot_user_name: String,
+    twitch_client_id: String,
+    twitch_client_secret: String,
+    twitch_helix_url: String,
+    twitch_eventsub_url: String,
+    bot_api_token: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum AppConfigError {
+    #[error("Environment variable error [{}]", .0)]
+    EnvironmentVar(#[from] VarError),
+}
+
+impl PathConfig {
+    pub fn from_env() -> PathConfig {
+        dotenv().ok();
+        PathConfig {
+            bot_config_file: env::var("BOT_CONFIG_FILE")
+                .unwrap_or_else(|_| "./bot_config.json".to_string()),
+            bot_storage_path: env::var("BOT_STORAGE_PATH")
+                .unwrap_or_else(|_| "./bot_store".to_string()),
+            bot_log_directory: env::var("BOT_LOG_DIR")
+                .unwrap_or_else(|_| "./chat_logs".to_string()),
+        }
+    }
+
+    /// Get a reference to the config's bot config file path.
+    /// this value is provided by the BOT_CONFIG_FILE environment variable
+    pub fn bot_config_file(&self) -> &str {
+        self.bot_config_file.as_ref()
+    }
+
+    /// Get a reference to the config's bot storage path.
+    /// this value is provided by the BOT_STORAGE_PATH environment variable
+    pub fn bot_storage_path(&self) -> &str {
+        self.bot_storage_path.as_ref()
+    }
+
+    /// Get a reference to the config's chat log directory.
+    /// this value is provided by the BOT_LOG_DIR environment variable
+    pub fn bot_log_directory(&self) -> &str {
+        self.bot_log_directory.as_ref()
+    }
+}
+
+impl AppConfig {
+    pub fn new() -> Result<AppConfig, AppConfigError> {
+        dotenv().ok();
+        Ok(AppConfig {
+            channel_name: env::var("TWITCH_CHANNEL")
+                .unwrap_or_else(|_| "captaincallback".to_string()),
+            bot_user_name: env::var("TWITCH_CHAT_USER")?,
+            twitch_client_id: env::var("TWITCH_AUTH_CLIENT_ID")?,
+            twitch_client_secret: env::var("TWITCH_AUTH_CLIENT_SECRET")?,
+            twitch_helix_url: env::var("TWITCH_HELIX_URL")
+                .unwrap_or_else(|_| "https://api.twitch.tv/helix".to_string()),
+            twitch_eventsub_url: env::var("TWITCH_EVENTSUB_URL")
+                .unwrap_or_else(|_| "wss://eventsub.wss.twitch.tv/ws".to_string()),
+            bot_api_token: env::var("BOT_API_TOKEN").ok(),
+        })
+    }
+
+    /// Get a reference to the config's channel name.
+    /// this value is provided by the TWITCH_CHANNEL environment variable
+    pub fn channel_name(&self) -> &str {
+        self.channel_name.as_ref()
+    }
+
+    /// Get a reference to the config's bot user name.
+    /// this value is provided by the TWITCH_CHAR_USER environment variable
+    pub fn bot_user_name(&self) -> &str {
+        self.bot_user_name.as_ref()
+    }
+
+    /// Get a reference to the config's twitch client id.
+    /// this value is provided by the TWITCH_AUTH_CLIENT_ID environment variable
+    pub fn twitch_client_id(&self) -> &str {
+        self.twitch_client_id.as_ref()
+    }
+
+    /// Get a reference to the config's twitch client secret.
+    /// this value is provided by the TWITCH_AUTH_CLIENT_SECRET environment variable
+    pub fn twitch_client_secret(&self) -> &str {
+        self.twitch_client_secret.as_ref()
+    }
+
+    /// Get a reference to the config's twitch helix API base URL.
+    /// this value is provided by the TWITCH_HELIX_URL environment variable
+    pub fn twitch_helix_url(&self) -> &str {
+        self.twitch_helix_url.as_ref()
+    }
+
+    /// Get a reference to the config's twitch EventSub WebSocket URL.
+    /// this value is provided by the TWITCH_EVENTSUB_URL environment variable
+    pub fn twitch_eventsub_url(&self) -> &str {
+        self.twitch_eventsub_url.as_ref()
+    }
+
+    /// Get a reference to the config's token for changes over the bot's HTTP API.
+    /// this value is provided by the BOT_API_TOKEN environment variable
+    pub fn bot_api_token(&self) -> Option<&str> {
+        self.bot_api_token.as_deref()
+    }
+}
@@ -0,0 +1,230 @@
+use crate::connect::ChatBotEvent;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+// https://dev.twitch.tv/docs/eventsub/websocket-reference
+#[derive(Debug, Deserialize)]
+struct RawMessage {
+    metadata: Metadata,
+    payload: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    message_id: String,
+    message_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionPayload {
+    session: Session,
+}
+
+#[derive(Debug, Deserialize)]
+struct Session {
+    id: String,
+    keepalive_timeout_seconds: Option<u64>,
+    reconnect_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationPayload {
+    subscription: Subscription,
+    #[serde(default)]
+    event: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    #[serde(rename = "type")]
+    subscription_type: String,
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowEvent {
+    user_login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RedemptionEvent {
+    id: String,
+    user_login: String,
+    #[serde(default)]
+    user_input: String,
+    reward: Reward,
+}
+
+#[derive(Debug, Deserialize)]
+struct Reward {
+    id: String,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HypeTrainEvent {
+    level: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamOnlineEvent {
+    started_at: DateTime<Utc>,
+}
+
+// twitch closes sessions without a subscription after 10 seconds, and expects a message
+// at least every 10 seconds unless the welcome says otherwise
+pub(super) const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq)]
+pub(super) enum EventSubMessage {
+    Welcome {
+        session_id: String,
+        keepalive_timeout: Duration,
+    },
+    Keepalive,
+    // twitch moves the session to another server
+    Reconnect {
+        url: String,
+    },
+    // None for events the bot does not handle
+    Notification(Option<ChatBotEvent>),
+    // a subscription ended, e.g. because the user revoked the token
+    Revocation {
+        subscription_type: String,
+        status: String,
+    },
+    Unknown(String),
+}
+
+fn session(payload: Value) -> Result<Session, serde_json::Error> {
+    Ok(serde_json::from_value::<SessionPayload>(payload)?.session)
+}
+
+fn notification(
+    subscription_type: &str,
+    event: Value,
+) -> Result<Option<ChatBotEvent>, serde_json::Error> {
+    use serde_json::from_value;
+    Ok(Some(match subscription_type {
+        "channel.follow" => ChatBotEvent::Follow {
+            user_name: from_value::<FollowEvent>(event)?.user_login,
+        },
+        "channel.channel_points_custom_reward_redemption.add" => {
+            let event: RedemptionEvent = from_value(event)?;
+            ChatBotEvent::Redemption {
+                id: event.id,
+                reward_id: event.reward.id,
+                reward_title: event.reward.title,
+                user_name: event.user_login,
+                input: event.user_input,
+            }
+        }
+        "channel.hype_train.begin" => ChatBotEvent::HypeTrainStarted {
+            level: from_value::<HypeTrainEvent>(event)?.level,
+        },
+        "channel.hype_train.end" => ChatBotEvent::HypeTrainEnded {
+            level: from_value::<HypeTrainEvent>(event)?.level,
+        },
+        "stream.online" => ChatBotEvent::StreamOnline {
+            started_at: from_value::<StreamOnlineEvent>(event)?.started_at,
+        },
+        "stream.offline" => ChatBotEvent::StreamOffline,
+        _ => return Ok(None),
+    }))
+}
+
+/// Parses a message of the EventSub WebSocket, returns its id and content
+pub(super) fn parse(text: &str) -> Result<(String, EventSubMessage), serde_json::Error> {
+    let message: RawMessage = serde_json::from_str(text)?;
+    let content = match message.metadata.message_type.as_str() {
+        "session_welcome" => {
+            let session = session(message.payload)?;
+            EventSubMessage::Welcome {
+                session_id: session.id,
+                keepalive_timeout: session
+                    .keepalive_timeout_seconds
+                    .map(Duration::from_secs)
+                    .unwrap_or(DEFAULT_KEEPALIVE_TIMEOUT),
+            }
+        }
+        "session_keepalive" => EventSubMessage::Keepalive,
+        "session_reconnect" => EventSubMessage::Reconnect {
+            url: session(message.payload)?.reconnect_url.unwrap_or_default(),
+        },
+        "notification" => {
+            let payload: NotificationPayload = serde_json::from_value(message.payload)?;
+            EventSubMessage::Notification(notification(
+                &payload.subscription.subscription_type,
+                payload.event,
+            )?)
+        }
+        "revocation" => {
+            let payload: NotificationPayload = serde_json::from_value(message.payload)?;
+            EventSubMessage::Revocation {
+                subscription_type: payload.subscription.subscription_type,
+                status: payload.subscription.status,
+            }
+        }
+        message_type => EventSubMessage::Unknown(message_type.to_owned()),
+    };
+    Ok((message.metadata.message_id, content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_session_messages() {
+        let welcome = r#"{"metadata": {"message_id": "96a3f3b5", "message_type": "session_welcome", "message_timestamp": "2023-07-19T14:56:51.634234626Z"}, "payload": {"session": {"id": "AQoQILE98gtqShGmLD7AM6yJThAB", "status": "connected", "connected_at": "2023-07-19T14:56:51.616329898Z", "keepalive_timeout_seconds": 30, "reconnect_url": null}}}"#;
+        assert_eq!(
+            parse(welcome).unwrap(),
+            (
+                "96a3f3b5".to_owned(),
+                EventSubMessage::Welcome {
+                    session_id: "AQoQILE98gtqShGmLD7AM6yJThAB".to_owned(),
+                    keepalive_timeout: Duration::from_secs(30),
+                }
+            )
+        );
+        let reconnect = r#"{"metadata": {"message_id": "84c1e79a", "message_type": "session_reconnect", "message_timestamp": "2022-11-18T09:10:11.634234626Z"}, "payload": {"session": {"id": "AQoQexAWVYKSTIu4ec_2VAxyuhAB", "status": "reconnecting", "keepalive_timeout_seconds": null, "reconnect_url": "wss://eventsub.wss.twitch.tv?...", "connected_at": "2022-11-16T10:11:12.634234626Z"}}}"#;
+        assert_eq!(
+            parse(reconnect).unwrap().1,
+            EventSubMessage::Reconnect {
+                url: "wss://eventsub.wss.twitch.tv?...".to_owned()
+            }
+        );
+        let keepalive = r#"{"metadata": {"message_id": "84c1e79a", "message_type": "session_keepalive", "message_timestamp": "2023-07-19T10:11:12.634234626Z"}, "payload": {}}"#;
+        assert_eq!(parse(keepalive).unwrap().1, EventSubMessage::Keepalive);
+    }
+
+    #[test]
+    fn parsing_notifications() {
+        let redemption = r#"{"metadata": {"message_id": "befa7b53", "message_type": "notification", "message_timestamp": "2022-11-16T10:11:12.464757833Z", "subscription_type": "channel.channel_points_custom_reward_redemption.add", "subscription_version": "1"}, "payload": {"subscription": {"id": "f1c2a387", "status": "enabled", "type": "channel.channel_points_custom_reward_redemption.add", "version": "1", "cost": 0, "condition": {"broadcaster_user_id": "1337"}, "transport": {"method": "websocket", "session_id": "AQoQexAWVYKSTIu4ec_2VAxyuhAB"}, "created_at": "2022-11-16T10:11:12.464757833Z"}, "event": {"id": "17fa2df1", "broadcaster_user_id": "1337", "broadcaster_user_login": "cool_user", "broadcaster_user_name": "Cool_User", "user_id": "9001", "user_login": "cooler_user", "user_name": "Cooler_User", "user_input": "pogchamp", "status": "unfulfilled", "reward": {"id": "92af127c", "title": "title", "cost": 100, "prompt": "reward prompt"}, "redeemed_at": "2020-07-15T17:16:03.17106713Z"}}}"#;
+        assert_eq!(
+            parse(redemption).unwrap().1,
+            EventSubMessage::Notification(Some(ChatBotEvent::Redemption {
+                id: "17fa2df1".to_owned(),
+                reward_id: "92af127c".to_owned(),
+                reward_title: "title".to_owned(),
+                user_name: "cooler_user".to_owned(),
+                input: "pogchamp".to_owned(),
+            }))
+        );
+        let offline = r#"{"metadata": {"message_id": "d4b2", "message_type": "notification", "message_timestamp": "2022-11-16T10:11:12Z"}, "payload": {"subscription": {"id": "f1c2a387", "status": "enabled", "type": "stream.offline", "version": "1"}, "event": {"broadcaster_user_id": "1337", "broadcaster_user_login": "cool_user", "broadcaster_user_name": "Cool_User"}}}"#;
+        assert_eq!(
+            parse(offline).unwrap().1,
+            EventSubMessage::Notification(Some(ChatBotEvent::StreamOffline))
+        );
+        let revocation = r#"{"metadata": {"message_id": "84c1e79a", "message_type": "revocation", "message_timestamp": "2022-11-16T10:11:12.464757833Z", "subscription_type": "channel.follow", "subscription_version": "2"}, "payload": {"subscription": {"id": "f1c2a387", "status": "authorization_revoked", "type": "channel.follow", "version": "2", "cost": 0, "condition": {"broadcaster_user_id": "1337"}, "transport": {"method": "websocket", "session_id": "AQoQexAWVYKSTIu4ec_2VAxyuhAB"}, "created_at": "2022-11-16T10:11:12.464757833Z"}}}"#;
+        assert_eq!(
+            parse(revocation).unwrap().1,
+            EventSubMessage::Revocation {
+                subscription_type: "channel.follow".to_owned(),
+                status: "authorization_revoked".to_owned(),
+            }
+        );
+    }
+}
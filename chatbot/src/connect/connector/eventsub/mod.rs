@@ -0,0 +1,416 @@
+mod message;
+
+use super::helix::HelixClient;
+use crate::connect::{error::ConnectorError, ChatBotEvent};
+use message::{parse, EventSubMessage, DEFAULT_KEEPALIVE_TIMEOUT};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::mpsc::Sender,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use tokio::runtime::Handle;
+use websocket::{
+    stream::sync::NetworkStream, sync::Client, ClientBuilder, OwnedMessage, WebSocketError,
+};
+
+// twitch may send a message more than once, these many recent message ids are remembered
+const RECENT_MESSAGE_COUNT: usize = 100;
+// time a message may be late before the connection is considered dead
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(2);
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+type WebSocket = Client<Box<dyn NetworkStream + Send>>;
+
+// Creates the subscriptions of a new session through Helix
+#[derive(Clone)]
+struct Subscriber {
+    helix: HelixClient,
+    runtime: Handle,
+    channel_name: String,
+    bot_user_name: String,
+}
+
+// https://dev.twitch.tv/docs/eventsub/eventsub-subscription-types
+fn subscriptions(
+    broadcaster_id: &str,
+    moderator_id: &str,
+) -> Vec<(&'static str, &'static str, Value)> {
+    let broadcaster = json!({ "broadcaster_user_id": broadcaster_id });
+    vec![
+        (
+            "channel.follow",
+            "2",
+            json!({"broadcaster_user_id": broadcaster_id, "moderator_user_id": moderator_id}),
+        ),
+        (
+            "channel.channel_points_custom_reward_redemption.add",
+            "1",
+            broadcaster.clone(),
+        ),
+        ("channel.hype_train.begin", "1", broadcaster.clone()),
+        ("channel.hype_train.end", "1", broadcaster.clone()),
+        ("stream.online", "1", broadcaster.clone()),
+        ("stream.offline", "1", broadcaster),
+    ]
+}
+
+impl Subscriber {
+    async fn create_subscriptions(&self, session_id: &str) -> Result<(), ConnectorError> {
+        let users = self
+            .helix
+            .get_users(&[&self.channel_name, &self.bot_user_name])
+            .await?;
+        let user_id = |login: &str| {
+            users
+                .iter()
+                .find(|user| user.login.eq_ignore_ascii_case(login))
+                .map(|user| user.id.to_owned())
+                .ok_or_else(|| {
+                    ConnectorError::EventSubFailed(format!("There is no twitch user {}", login))
+                })
+        };
+        let broadcaster_id = user_id(&self.channel_name)?;
+        let moderator_id = user_id(&self.bot_user_name)?;
+        for (subscription_type, version, condition) in subscriptions(&broadcaster_id, &moderator_id)
+        {
+            // a missing scope only costs the events of this subscription
+            if let Err(err) = self
+                .helix
+                .create_eventsub_subscription(subscription_type, version, &condition, session_id)
+                .await
+            {
+                println!("Could not subscribe to {}: {}", subscription_type, err);
+            }
+        }
+        Ok(())
+    }
+
+    // twitch closes the session unless the subscriptions are created right after the welcome,
+    // so this must not wait for anything else
+    fn subscribe(&self, session_id: String) {
+        let subscriber = self.clone();
+        self.runtime.spawn(async move {
+            if let Err(err) = subscriber.create_subscriptions(&session_id).await {
+                println!("Could not create EventSub subscriptions: {}", err);
+            }
+        });
+    }
+}
+
+enum SessionEnd {
+    Reconnect(String),
+    // the bot stopped listening to events
+    Stopped,
+}
+
+struct Connection {
+    socket: WebSocket,
+}
+
+impl Connection {
+    fn open(url: &str) -> Result<Self, ConnectorError> {
+        let socket = ClientBuilder::new(url)
+            .map_err(|err| ConnectorError::EventSubFailed(format!("Invalid URL {}: {}", url, err)))?
+            .connect(None)?;
+        let connection = Self { socket };
+        connection.set_keepalive_timeout(DEFAULT_KEEPALIVE_TIMEOUT)?;
+        Ok(connection)
+    }
+
+    fn set_keepalive_timeout(&self, keepalive_timeout: Duration) -> Result<(), ConnectorError> {
+        self.socket
+            .stream_ref()
+            .as_tcp()
+            .set_read_timeout(Some(keepalive_timeout + KEEPALIVE_GRACE))
+            .map_err(|err| ConnectorError::EventSubFailed(err.to_string()))
+    }
+
+    // The next text message, answering pings on the way
+    fn receive(&mut self) -> Result<String, ConnectorError> {
+        loop {
+            match self.socket.recv_message() {
+                Ok(OwnedMessage::Text(text)) => return Ok(text),
+                Ok(OwnedMessage::Ping(data)) => {
+                    self.socket.send_message(&OwnedMessage::Pong(data))?
+                }
+                Ok(OwnedMessage::Close(close)) => {
+                    return Err(ConnectorError::EventSubFailed(format!(
+                        "Twitch closed the connection: {:?}",
+                        close
+                    )))
+                }
+                Ok(_) | Err(WebSocketError::NoDataAvailable) => continue,
+                Err(WebSocketError::IoError(err))
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Err(ConnectorError::EventSubFailed(
+                        "No keepalive within the keepalive timeout".to_owned(),
+                    ))
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Waits for the welcome message, which is the first message of a session
+    fn welcome(&mut self) -> Result<String, ConnectorError> {
+        match parse(&self.receive()?)? {
+            (
+                _,
+                EventSubMessage::Welcome {
+                    session_id,
+                    keepalive_timeout,
+                },
+            ) => {
+                self.set_keepalive_timeout(keepalive_timeout)?;
+                Ok(session_id)
+            }
+            (_, message) => Err(ConnectorError::EventSubFailed(format!(
+                "Expected a welcome message but received {:?}",
+                message
+            ))),
+        }
+    }
+
+    fn listen(
+        &mut self,
+        events: &Sender<ChatBotEvent>,
+        recent_messages: &mut VecDeque<String>,
+    ) -> Result<SessionEnd, ConnectorError> {
+        loop {
+            let text = self.receive()?;
+            let (message_id, message) = match parse(&text) {
+                Ok(message) => message,
+                Err(err) => {
+                    println!("Could not parse EventSub message {}: {}", text, err);
+                    continue;
+                }
+            };
+            if recent_messages.contains(&message_id) {
+                continue;
+            }
+            if recent_messages.len() == RECENT_MESSAGE_COUNT {
+                recent_messages.pop_front();
+            }
+            recent_messages.push_back(message_id);
+            match message {
+                EventSubMessage::Notification(Some(event)) => {
+                    if events.send(event).is_err() {
+                        return Ok(SessionEnd::Stopped);
+                    }
+                }
+                EventSubMessage::Reconnect { url } => return Ok(SessionEnd::Reconnect(url)),
+                EventSubMessage::Revocation {
+                    subscription_type,
+                    status,
+                } => println!(
+                    "EventSub subscription {} was revoked: {}",
+                    subscription_type, status
+                ),
+                EventSubMessage::Notification(None)
+                | EventSubMessage::Keepalive
+                | EventSubMessage::Welcome { .. }
+                | EventSubMessage::Unknown(_) => {}
+            }
+        }
+    }
+}
+
+// Keeps a session open for as long as the bot listens, a lost connection is replaced with
+// a new session
+fn run(url: &str, subscriber: &Subscriber, events: &Sender<ChatBotEvent>) {
+    let mut recent_messages = VecDeque::with_capacity(RECENT_MESSAGE_COUNT);
+    let mut retry_delay = MIN_RETRY_DELAY;
+    let mut reconnect: Option<(String, Connection)> = None;
+    loop {
+        let connection = match reconnect.take() {
+            // the subscriptions move along with the session, the old connection has to stay
+            // open until the new one is welcomed
+            Some((reconnect_url, old_connection)) => {
+                Connection::open(&reconnect_url).and_then(|mut connection| {
+                    connection.welcome()?;
+                    drop(old_connection);
+                    Ok(connection)
+                })
+            }
+            None => Connection::open(url).and_then(|mut connection| {
+                subscriber.subscribe(connection.welcome()?);
+                Ok(connection)
+            }),
+        };
+        let session_end = connection.and_then(|mut connection| {
+            retry_delay = MIN_RETRY_DELAY;
+            connection
+                .listen(events, &mut recent_messages)
+                .map(|session_end| (session_end, connection))
+        });
+        match session_end {
+            Ok((SessionEnd::Reconnect(reconnect_url), connection)) => {
+                reconnect = Some((reconnect_url, connection))
+            }
+            Ok((SessionEnd::Stopped, _)) => break,
+            Err(err) => {
+                println!(
+                    "EventSub connection lost, reconnecting in {:?}: {}",
+                    retry_delay, err
+                );
+                thread::sleep(retry_delay);
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Receives the channel's follows, channel point redemptions, hype trains and stream status
+/// from Twitch EventSub and sends them to the bot
+pub struct EventSubConnector {
+    _handle: JoinHandle<()>,
+}
+
+impl EventSubConnector {
+    /// Connects to the EventSub WebSocket at the URL, must be called within the tokio runtime
+    pub fn new(
+        url: &str,
+        helix: HelixClient,
+        channel_name: &str,
+        bot_user_name: &str,
+        chatbot_event_sender: Sender<ChatBotEvent>,
+    ) -> Self {
+        let url = url.to_owned();
+        let subscriber = Subscriber {
+            helix,
+            runtime: Handle::current(),
+            channel_name: channel_name.to_owned(),
+            bot_user_name: bot_user_name.to_owned(),
+        };
+        let handle = thread::spawn(move || run(&url, &subscriber, &chatbot_event_sender));
+        Self { _handle: handle }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::connector::helix::mock::{mock_helix, ok};
+    use std::{net::TcpStream, sync::mpsc};
+    use websocket::sync::Server;
+
+    const USERS: &str = r#"{"data": [{"id": "1337", "login": "channel", "display_name": "Channel", "created_at": "2016-12-14T20:32:28Z"}, {"id": "9001", "login": "bot", "display_name": "Bot", "created_at": "2019-05-01T10:00:00Z"}]}"#;
+    const SUBSCRIPTION: &str = r#"{"data": [{"id": "f1c2a387", "status": "enabled", "type": "channel.follow", "version": "2"}]}"#;
+
+    // A stand-in for the EventSub server, accepting connections on a local port
+    struct StandIn {
+        server: Server<websocket::server::NoTlsAcceptor>,
+    }
+
+    impl StandIn {
+        fn new() -> Self {
+            Self {
+                server: Server::bind("127.0.0.1:0").unwrap(),
+            }
+        }
+
+        fn url(&self) -> String {
+            format!("ws://{}", self.server.local_addr().unwrap())
+        }
+
+        fn accept(&mut self) -> Client<TcpStream> {
+            self.server.accept().ok().unwrap().accept().ok().unwrap()
+        }
+    }
+
+    fn send(client: &mut Client<TcpStream>, text: String) {
+        client.send_message(&OwnedMessage::Text(text)).unwrap();
+    }
+
+    fn welcome(session_id: &str, keepalive_timeout_seconds: u64) -> String {
+        json!({
+            "metadata": {"message_id": format!("welcome-{}", session_id), "message_type": "session_welcome"},
+            "payload": {"session": {"id": session_id, "status": "connected", "keepalive_timeout_seconds": keepalive_timeout_seconds, "reconnect_url": null}},
+        })
+        .to_string()
+    }
+
+    fn follow(message_id: &str, user_login: &str) -> String {
+        json!({
+            "metadata": {"message_id": message_id, "message_type": "notification"},
+            "payload": {
+                "subscription": {"id": "f1c2a387", "status": "enabled", "type": "channel.follow", "version": "2"},
+                "event": {"user_id": "1234", "user_login": user_login, "user_name": user_login, "followed_at": "2023-07-15T18:16:11.17106713Z"},
+            },
+        })
+        .to_string()
+    }
+
+    fn followed(user_name: &str) -> ChatBotEvent {
+        ChatBotEvent::Follow {
+            user_name: user_name.to_owned(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn subscribing_and_receiving_notifications() {
+        let mut stand_in = StandIn::new();
+        let mut responses = vec![ok(USERS)];
+        responses.extend((0..7).map(|_| ok(SUBSCRIPTION)));
+        let (helix, requests) = mock_helix(responses);
+        let (sender, events) = mpsc::channel();
+        let _connector = EventSubConnector::new(&stand_in.url(), helix, "channel", "bot", sender);
+
+        let mut client = stand_in.accept();
+        send(&mut client, welcome("session", 10));
+        assert_eq!(
+            requests.recv().unwrap().url,
+            "/users?login=channel&login=bot"
+        );
+        let subscription = requests.recv().unwrap();
+        assert_eq!(subscription.method, "POST");
+        assert_eq!(subscription.url, "/eventsub/subscriptions");
+        let body: Value = serde_json::from_str(&subscription.body).unwrap();
+        assert_eq!(body["type"], "channel.follow");
+        assert_eq!(body["condition"]["moderator_user_id"], "9001");
+        assert_eq!(body["transport"]["session_id"], "session");
+
+        // notifications sent twice are only delivered once
+        send(&mut client, follow("1", "carkhy"));
+        send(&mut client, follow("1", "carkhy"));
+        send(&mut client, follow("2", "captaincallback"));
+        assert_eq!(events.recv().unwrap(), followed("carkhy"));
+        assert_eq!(events.recv().unwrap(), followed("captaincallback"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reconnecting_when_asked_or_when_keepalives_stop() {
+        let mut stand_in = StandIn::new();
+        let mut new_server = StandIn::new();
+        // the subscriptions of the first session are rejected, which must not matter
+        let (helix, _requests) = mock_helix(Vec::default());
+        let (sender, events) = mpsc::channel();
+        let _connector = EventSubConnector::new(&stand_in.url(), helix, "channel", "bot", sender);
+
+        let mut client = stand_in.accept();
+        send(&mut client, welcome("session", 1));
+        let reconnect = json!({
+            "metadata": {"message_id": "reconnect", "message_type": "session_reconnect"},
+            "payload": {"session": {"id": "session", "status": "reconnecting", "keepalive_timeout_seconds": null, "reconnect_url": new_server.url()}},
+        });
+        send(&mut client, reconnect.to_string());
+        let mut new_client = new_server.accept();
+        send(&mut new_client, welcome("session", 1));
+        send(&mut new_client, follow("1", "carkhy"));
+        assert_eq!(events.recv().unwrap(), followed("carkhy"));
+
+        // without keepalives the connector starts over with a new session
+        let mut client = stand_in.accept();
+        send(&mut client, welcome("new-session", 10));
+        send(&mut client, follow("2", "captaincallback"));
+        assert_eq!(events.recv().unwrap(), followed("captaincallback"));
+    }
+}
@@ -0,0 +1,68 @@
+use super::{HelixClient, Token};
+use std::{sync::mpsc, thread};
+use tiny_http::{Header, Response, Server};
+
+#[derive(Debug)]
+pub(crate) struct MockRequest {
+    pub method: String,
+    pub url: String,
+    pub body: String,
+}
+
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: &'static str,
+}
+
+pub(crate) fn ok(body: &'static str) -> MockResponse {
+    MockResponse {
+        status: 200,
+        headers: Vec::default(),
+        body,
+    }
+}
+
+// Answers requests with the given responses in order and reports the received requests
+pub(crate) fn mock_helix(
+    responses: Vec<MockResponse>,
+) -> (HelixClient, mpsc::Receiver<MockRequest>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let address = server.server_addr();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for response in responses {
+            let mut request = match server.recv() {
+                Ok(request) => request,
+                Err(_) => return,
+            };
+            let authorized = request.headers().iter().any(|header| {
+                header.field.equiv("Client-Id") && header.value.as_str() == "client-id"
+            }) && request.headers().iter().any(|header| {
+                header.field.equiv("Authorization") && header.value.as_str() == "Bearer token"
+            });
+            let mut body = String::default();
+            let _ = request.as_reader().read_to_string(&mut body);
+            let _ = sender.send(MockRequest {
+                method: request.method().to_string(),
+                url: request.url().to_owned(),
+                body,
+            });
+            let mut http_response = Response::from_string(response.body)
+                .with_status_code(if authorized { response.status } else { 401 });
+            for (name, value) in response.headers {
+                http_response.add_header(Header::from_bytes(name, value).unwrap());
+            }
+            let _ = request.respond(http_response);
+        }
+    });
+    let client = HelixClient::with_token(
+        &format!("http://{}", address),
+        "client-id",
+        Token {
+            access_token: Some("token".to_owned()),
+            dispenser: None,
+        },
+    );
+    (client, receiver)
+}
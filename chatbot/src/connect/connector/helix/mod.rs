@@ -0,0 +1,770 @@
+#[cfg(test)]
+pub(crate) mod mock;
+mod requests;
+mod types;
+
+use super::twitch_chat::{auth::AccessTokenDispenser, retry_manager::ExponentialRetryManager};
+use crate::connect::{error::ConnectorError, ChannelInfo};
+use chrono::{TimeZone, Utc};
+use futures_retry::FutureRetry;
+use reqwest::{header::HeaderMap, Client, Method, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Mutex as AsyncMutex;
+use types::HelixResponse;
+pub use types::{
+    AnnouncementColor, ChannelInformation, Chatter, Clip, EventSubSubscription, Followers, Game,
+    RateLimit, Stream, StreamMarker, User,
+};
+
+// maximum number of items per page the Helix API allows
+const PAGE_SIZE: usize = 100;
+
+struct Token {
+    access_token: Option<String>,
+    // without a dispenser (in tests) the token is never refreshed
+    dispenser: Option<AccessTokenDispenser>,
+}
+
+/// Typed client for the Twitch Helix API.
+/// Requests are retried on server errors and rate limits, and the access token is refreshed
+/// when it expires. The base URL is configurable so tests can run against a local server.
+#[derive(Clone)]
+pub struct HelixClient {
+    base_url: String,
+    client_id: String,
+    client: Client,
+    token: Arc<AsyncMutex<Token>>,
+    rate_limit: Arc<Mutex<Option<RateLimit>>>,
+}
+
+async fn error_message(response: Response) -> String {
+    let status = response.status();
+    response
+        .json::<Value>()
+        .await
+        .ok()
+        .and_then(|json| json["message"].as_str().map(String::from))
+        .unwrap_or_else(|| status.to_string())
+}
+
+impl HelixClient {
+    pub fn new(
+        base_url: &str,
+        client_id: &str,
+        access_token_dispenser: AccessTokenDispenser,
+    ) -> Self {
+        Self::with_token(
+            base_url,
+            client_id,
+            Token {
+                access_token: None,
+                dispenser: Some(access_token_dispenser),
+            },
+        )
+    }
+
+    fn with_token(base_url: &str, client_id: &str, token: Token) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client_id: client_id.to_owned(),
+            client: Client::new(),
+            token: Arc::new(AsyncMutex::new(token)),
+            rate_limit: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The rate limit reported by the last response
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit
+            .lock()
+            .ok()
+            .and_then(|rate_limit| rate_limit.clone())
+    }
+
+    async fn access_token(&self, refresh: bool) -> Result<String, ConnectorError> {
+        let mut token = self.token.lock().await;
+        if refresh || token.access_token.is_none() {
+            let access_token = match token.dispenser.as_mut() {
+                Some(dispenser) => dispenser.get().await?.to_owned(),
+                None => {
+                    return Err(ConnectorError::HelixRequestFailed(
+                        401,
+                        "The access token is invalid".to_owned(),
+                    ))
+                }
+            };
+            token.access_token = Some(access_token);
+        }
+        Ok(token.access_token.to_owned().unwrap_or_default())
+    }
+
+    // https://dev.twitch.tv/docs/api/guide#twitch-rate-limits
+    fn record_rate_limit(&self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+        if let (Some(limit), Some(remaining), Some(reset)) = (
+            header("ratelimit-limit"),
+            header("ratelimit-remaining"),
+            header("ratelimit-reset"),
+        ) {
+            let rate_limit = RateLimit {
+                limit: limit.max(0) as u64,
+                remaining: remaining.max(0) as u64,
+                reset: Utc
+                    .timestamp_opt(reset, 0)
+                    .single()
+                    .unwrap_or_else(Utc::now),
+            };
+            if rate_limit.remaining == 0 {
+                println!("Helix rate limit exhausted until {}", rate_limit.reset);
+            }
+            if let Ok(mut last_rate_limit) = self.rate_limit.lock() {
+                *last_rate_limit = Some(rate_limit);
+            }
+        }
+    }
+
+    fn time_until_reset(&self) -> Duration {
+        self.rate_limit()
+            .and_then(|rate_limit| (rate_limit.reset - Utc::now()).to_std().ok())
+            .unwrap_or_default()
+    }
+
+    // A single request, refreshing the access token once if it was rejected.
+    // None is a response without content.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Option<HelixResponse<T>>, ConnectorError> {
+        let mut refresh = false;
+        loop {
+            let mut request = self
+                .client
+                .request(method.clone(), format!("{}{}", self.base_url, path))
+                .query(query)
+                .header("Client-Id", &self.client_id)
+                .bearer_auth(self.access_token(refresh).await?);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let response = request.send().await?;
+            self.record_rate_limit(response.headers());
+            match response.status() {
+                StatusCode::NO_CONTENT => return Ok(None),
+                status if status.is_success() => return Ok(Some(response.json().await?)),
+                StatusCode::UNAUTHORIZED if !refresh => refresh = true,
+                StatusCode::TOO_MANY_REQUESTS => {
+                    return Err(ConnectorError::RateLimited(self.time_until_reset()))
+                }
+                status if status.is_client_error() => {
+                    return Err(ConnectorError::HelixRequestFailed(
+                        status.as_u16(),
+                        error_message(response).await,
+                    ))
+                }
+                status => {
+                    return Err(ConnectorError::ExternalServerError(format!(
+                        "Helix server sent bad response with http status code {}",
+                        status.as_u16()
+                    )))
+                }
+            }
+        }
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Option<HelixResponse<T>>, ConnectorError> {
+        FutureRetry::new(
+            || self.send(method.clone(), path, query, body),
+            ExponentialRetryManager::new(Some(1), Some(3)),
+        )
+        .await
+        .map(|val| val.0)
+        .map_err(|err| err.0)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>, ConnectorError> {
+        Ok(self
+            .request(Method::GET, path, query, None)
+            .await?
+            .map(|response| response.data)
+            .unwrap_or_default())
+    }
+
+    // Follows the pagination cursor until the limit is reached, returns the items and the
+    // total number of items if the endpoint reports it
+    async fn get_paged<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        limit: usize,
+    ) -> Result<(Vec<T>, Option<u64>), ConnectorError> {
+        let page_size = PAGE_SIZE.min(limit.max(1)).to_string();
+        let mut items = Vec::default();
+        let mut total = None;
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut page_query = query.to_vec();
+            page_query.push(("first", &page_size));
+            if let Some(cursor) = &cursor {
+                page_query.push(("after", cursor));
+            }
+            let response = match self
+                .request::<T>(Method::GET, path, &page_query, None)
+                .await?
+            {
+                Some(response) => response,
+                None => break,
+            };
+            total = response.total.or(total);
+            let empty_page = response.data.is_empty();
+            items.extend(response.data);
+            cursor = response.pagination.cursor;
+            if empty_page || cursor.is_none() || items.len() >= limit {
+                break;
+            }
+        }
+        items.truncate(limit);
+        Ok((items, total))
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Vec<T>, ConnectorError> {
+        Ok(self
+            .request(Method::POST, path, query, body)
+            .await?
+            .map(|response| response.data)
+            .unwrap_or_default())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#get-users
+    pub async fn get_users(&self, logins: &[&str]) -> Result<Vec<User>, ConnectorError> {
+        let query: Vec<(&str, &str)> = logins.iter().map(|login| ("login", *login)).collect();
+        self.get("/users", &query).await
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#get-streams
+    /// The streams of the users which are live right now
+    pub async fn get_streams(&self, user_logins: &[&str]) -> Result<Vec<Stream>, ConnectorError> {
+        let query: Vec<(&str, &str)> = user_logins
+            .iter()
+            .map(|login| ("user_login", *login))
+            .collect();
+        self.get("/streams", &query).await
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#get-channel-information
+    pub async fn get_channel_information(
+        &self,
+        broadcaster_id: &str,
+    ) -> Result<Option<ChannelInformation>, ConnectorError> {
+        Ok(self
+            .get("/channels", &[("broadcaster_id", broadcaster_id)])
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#modify-channel-information
+    /// Changes the title and/or the game of the channel
+    pub async fn modify_channel_information(
+        &self,
+        broadcaster_id: &str,
+        title: Option<&str>,
+        game_id: Option<&str>,
+    ) -> Result<(), ConnectorError> {
+        let mut body = Map::new();
+        if let Some(title) = title {
+            body.insert("title".to_owned(), json!(title));
+        }
+        if let Some(game_id) = game_id {
+            body.insert("game_id".to_owned(), json!(game_id));
+        }
+        self.request::<Value>(
+            Method::PATCH,
+            "/channels",
+            &[("broadcaster_id", broadcaster_id)],
+            Some(&Value::Object(body)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#get-games
+    /// Games (categories) with exactly the given names
+    pub async fn get_games(&self, names: &[&str]) -> Result<Vec<Game>, ConnectorError> {
+        let query: Vec<(&str, &str)> = names.iter().map(|name| ("name", *name)).collect();
+        self.get("/games", &query).await
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#search-categories
+    /// Games (categories) matching the query, best match first
+    pub async fn search_categories(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<Game>, ConnectorError> {
+        Ok(self
+            .get_paged("/search/categories", &[("query", query)], limit)
+            .await?
+            .0)
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#get-channel-followers
+    /// Up to limit followers, newest first. With a user id only that user's follow is returned.
+    pub async fn get_followers(
+        &self,
+        broadcaster_id: &str,
+        user_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Followers, ConnectorError> {
+        let mut query = vec![("broadcaster_id", broadcaster_id)];
+        if let Some(user_id) = user_id {
+            query.push(("user_id", user_id));
+        }
+        let (followers, total) = self.get_paged("/channels/followers", &query, limit).await?;
+        Ok(Followers {
+            total: total.unwrap_or(followers.len() as u64),
+            followers,
+        })
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#get-chatters
+    pub async fn get_chatters(
+        &self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        limit: usize,
+    ) -> Result<Vec<Chatter>, ConnectorError> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        Ok(self.get_paged("/chat/chatters", &query, limit).await?.0)
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#create-clip
+    pub async fn create_clip(&self, broadcaster_id: &str) -> Result<Clip, ConnectorError> {
+        self.post("/clips", &[("broadcaster_id", broadcaster_id)], None)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ConnectorError::ExternalServerError("Helix server did not create a clip".to_owned())
+            })
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#create-stream-marker
+    pub async fn create_stream_marker(
+        &self,
+        user_id: &str,
+        description: Option<&str>,
+    ) -> Result<StreamMarker, ConnectorError> {
+        let body = match description {
+            Some(description) => json!({"user_id": user_id, "description": description}),
+            None => json!({ "user_id": user_id }),
+        };
+        self.post("/streams/markers", &[], Some(&body))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ConnectorError::ExternalServerError(
+                    "Helix server did not create a stream marker".to_owned(),
+                )
+            })
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#send-chat-announcement
+    pub async fn send_announcement(
+        &self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        message: &str,
+        color: AnnouncementColor,
+    ) -> Result<(), ConnectorError> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        let body = json!({"message": message, "color": color.name()});
+        self.post::<Value>("/chat/announcements", &query, Some(&body))
+            .await?;
+        Ok(())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#send-a-shoutout
+    /// The official shoutout, twitch allows one every 2 minutes and one per channel every hour
+    pub async fn send_shoutout(
+        &self,
+        from_broadcaster_id: &str,
+        to_broadcaster_id: &str,
+        moderator_id: &str,
+    ) -> Result<(), ConnectorError> {
+        let query = [
+            ("from_broadcaster_id", from_broadcaster_id),
+            ("to_broadcaster_id", to_broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        self.post::<Value>("/chat/shoutouts", &query, None).await?;
+        Ok(())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#update-redemption-status
+    /// Marks the redemption as fulfilled, or cancels it which refunds the points.
+    /// Only redemptions of rewards created with the same client id can be updated.
+    pub async fn update_redemption_status(
+        &self,
+        broadcaster_id: &str,
+        reward_id: &str,
+        redemption_id: &str,
+        fulfilled: bool,
+    ) -> Result<(), ConnectorError> {
+        let query = [
+            ("id", redemption_id),
+            ("broadcaster_id", broadcaster_id),
+            ("reward_id", reward_id),
+        ];
+        let status = if fulfilled { "FULFILLED" } else { "CANCELED" };
+        self.request::<Value>(
+            Method::PATCH,
+            "/channel_points/custom_rewards/redemptions",
+            &query,
+            Some(&json!({ "status": status })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#add-channel-vip
+    pub async fn add_channel_vip(
+        &self,
+        broadcaster_id: &str,
+        user_id: &str,
+    ) -> Result<(), ConnectorError> {
+        let query = [("broadcaster_id", broadcaster_id), ("user_id", user_id)];
+        self.post::<Value>("/channels/vips", &query, None).await?;
+        Ok(())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#remove-channel-vip
+    pub async fn remove_channel_vip(
+        &self,
+        broadcaster_id: &str,
+        user_id: &str,
+    ) -> Result<(), ConnectorError> {
+        let query = [("broadcaster_id", broadcaster_id), ("user_id", user_id)];
+        self.request::<Value>(Method::DELETE, "/channels/vips", &query, None)
+            .await?;
+        Ok(())
+    }
+
+    // https://dev.twitch.tv/docs/api/reference#create-eventsub-subscription
+    /// Subscribes the EventSub WebSocket session to the events of the given type
+    pub async fn create_eventsub_subscription(
+        &self,
+        subscription_type: &str,
+        version: &str,
+        condition: &Value,
+        session_id: &str,
+    ) -> Result<EventSubSubscription, ConnectorError> {
+        let body = json!({
+            "type": subscription_type,
+            "version": version,
+            "condition": condition,
+            "transport": {"method": "websocket", "session_id": session_id},
+        });
+        self.post("/eventsub/subscriptions", &[], Some(&body))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                ConnectorError::ExternalServerError(
+                    "Helix server did not create a subscription".to_owned(),
+                )
+            })
+    }
+
+    /// Information about the channel of the user, None if there is no such user
+    pub async fn channel_info(&self, login: &str) -> Result<Option<ChannelInfo>, ConnectorError> {
+        let user = match self.get_users(&[login]).await?.into_iter().next() {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+        let channel = self.get_channel_information(&user.id).await?;
+        Ok(Some(ChannelInfo {
+            login: user.login,
+            display_name: user.display_name,
+            game_name: channel
+                .as_ref()
+                .map(|channel| channel.game_name.to_owned())
+                .unwrap_or_default(),
+            title: channel.map(|channel| channel.title).unwrap_or_default(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::{TwitchRequest, TwitchResponse};
+    use mock::{mock_helix, ok, MockResponse};
+
+    #[tokio::test]
+    async fn fetching_channel_info() {
+        let (client, requests) = mock_helix(vec![
+            ok(
+                r#"{"data": [{"id": "141981764", "login": "twitchdev", "display_name": "TwitchDev", "created_at": "2016-12-14T20:32:28Z"}]}"#,
+            ),
+            ok(
+                r#"{"data": [{"broadcaster_id": "141981764", "broadcaster_login": "twitchdev", "broadcaster_name": "TwitchDev", "game_id": "509670", "game_name": "Science & Technology", "title": "TwitchDev Monthly Update"}]}"#,
+            ),
+            ok(r#"{"data": []}"#),
+        ]);
+        assert_eq!(
+            client.channel_info("twitchdev").await.unwrap(),
+            Some(ChannelInfo {
+                login: "twitchdev".to_owned(),
+                display_name: "TwitchDev".to_owned(),
+                game_name: "Science & Technology".to_owned(),
+                title: "TwitchDev Monthly Update".to_owned(),
+            })
+        );
+        assert_eq!(requests.recv().unwrap().url, "/users?login=twitchdev");
+        assert_eq!(
+            requests.recv().unwrap().url,
+            "/channels?broadcaster_id=141981764"
+        );
+        assert_eq!(client.channel_info("nobody").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn paging_through_followers() {
+        let (client, requests) = mock_helix(vec![
+            ok(
+                r#"{"total": 3, "data": [{"user_id": "1", "user_login": "a", "user_name": "A", "followed_at": "2022-01-01T12:00:00Z"}, {"user_id": "2", "user_login": "b", "user_name": "B", "followed_at": "2022-01-01T12:00:00Z"}], "pagination": {"cursor": "next"}}"#,
+            ),
+            ok(
+                r#"{"total": 3, "data": [{"user_id": "3", "user_login": "c", "user_name": "C", "followed_at": "2022-01-01T12:00:00Z"}], "pagination": {}}"#,
+            ),
+        ]);
+        let followers = client.get_followers("42", None, 10).await.unwrap();
+        assert_eq!(followers.total, 3);
+        assert_eq!(
+            followers
+                .followers
+                .iter()
+                .map(|follower| follower.user_login.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            requests.recv().unwrap().url,
+            "/channels/followers?broadcaster_id=42&first=10"
+        );
+        assert_eq!(
+            requests.recv().unwrap().url,
+            "/channels/followers?broadcaster_id=42&first=10&after=next"
+        );
+    }
+
+    #[tokio::test]
+    async fn reporting_and_retrying_rate_limits() {
+        let reset = Utc::now().timestamp();
+        let rate_limit_headers = |remaining: u64| {
+            vec![
+                ("Ratelimit-Limit", "800".to_owned()),
+                ("Ratelimit-Remaining", remaining.to_string()),
+                ("Ratelimit-Reset", reset.to_string()),
+            ]
+        };
+        let (client, _requests) = mock_helix(vec![
+            MockResponse {
+                status: 429,
+                headers: rate_limit_headers(0),
+                body: r#"{"error": "Too Many Requests", "status": 429, "message": ""}"#,
+            },
+            MockResponse {
+                status: 200,
+                headers: rate_limit_headers(799),
+                body: r#"{"data": []}"#,
+            },
+        ]);
+        assert_eq!(client.get_streams(&["twitchdev"]).await.unwrap(), vec![]);
+        assert_eq!(
+            client.rate_limit(),
+            Some(RateLimit {
+                limit: 800,
+                remaining: 799,
+                reset: Utc.timestamp_opt(reset, 0).unwrap(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn modifying_the_channel() {
+        let (client, requests) = mock_helix(vec![
+            MockResponse {
+                status: 204,
+                headers: Vec::default(),
+                body: "",
+            },
+            MockResponse {
+                status: 400,
+                headers: Vec::default(),
+                body: r#"{"error": "Bad Request", "status": 400, "message": "The title is too long"}"#,
+            },
+        ]);
+        client
+            .modify_channel_information("42", Some("New title"), None)
+            .await
+            .unwrap();
+        let request = requests.recv().unwrap();
+        assert_eq!(request.method, "PATCH");
+        assert_eq!(request.url, "/channels?broadcaster_id=42");
+        assert_eq!(request.body, r#"{"title":"New title"}"#);
+        assert!(matches!(
+            client.modify_channel_information("42", Some("Too long"), None).await,
+            Err(ConnectorError::HelixRequestFailed(400, message)) if message == "The title is too long"
+        ));
+    }
+
+    #[tokio::test]
+    async fn sending_announcements_and_shoutouts() {
+        let no_content = || MockResponse {
+            status: 204,
+            headers: Vec::default(),
+            body: "",
+        };
+        let (client, requests) = mock_helix(vec![no_content(), no_content()]);
+        client
+            .send_announcement("42", "7", "Hello chat", AnnouncementColor::Purple)
+            .await
+            .unwrap();
+        client.send_shoutout("42", "141981764", "7").await.unwrap();
+        let announcement = requests.recv().unwrap();
+        assert_eq!(
+            announcement.url,
+            "/chat/announcements?broadcaster_id=42&moderator_id=7"
+        );
+        assert_eq!(
+            announcement.body,
+            r#"{"color":"purple","message":"Hello chat"}"#
+        );
+        let shoutout = requests.recv().unwrap();
+        assert_eq!(shoutout.method, "POST");
+        assert_eq!(
+            shoutout.url,
+            "/chat/shoutouts?from_broadcaster_id=42&to_broadcaster_id=141981764&moderator_id=7"
+        );
+    }
+
+    #[tokio::test]
+    async fn answering_follow_age_and_uptime() {
+        let (client, requests) = mock_helix(vec![
+            ok(
+                r#"{"data": [{"id": "7", "login": "carkhy", "display_name": "Carkhy", "created_at": "2019-05-01T10:00:00Z"}]}"#,
+            ),
+            ok(
+                r#"{"data": [{"id": "42", "login": "channel", "display_name": "Channel", "created_at": "2016-12-14T20:32:28Z"}]}"#,
+            ),
+            ok(
+                r#"{"total": 12, "data": [{"user_id": "7", "user_login": "carkhy", "user_name": "Carkhy", "followed_at": "2021-03-15T08:00:00Z"}], "pagination": {}}"#,
+            ),
+            ok(r#"{"data": [], "pagination": {}}"#),
+        ]);
+        assert_eq!(
+            client
+                .answer("channel", &TwitchRequest::FollowAge("carkhy".to_owned()))
+                .await,
+            TwitchResponse::FollowedAt("2021-03-15T08:00:00Z".parse().unwrap())
+        );
+        assert_eq!(requests.recv().unwrap().url, "/users?login=carkhy");
+        assert_eq!(requests.recv().unwrap().url, "/users?login=channel");
+        assert_eq!(
+            requests.recv().unwrap().url,
+            "/channels/followers?broadcaster_id=42&user_id=7&first=1"
+        );
+        assert_eq!(
+            client.answer("channel", &TwitchRequest::Uptime).await,
+            TwitchResponse::Offline
+        );
+    }
+
+    #[tokio::test]
+    async fn changing_the_game() {
+        let (client, requests) = mock_helix(vec![
+            ok(r#"{"data": []}"#),
+            ok(r#"{"data": [{"id": "509670", "name": "Science & Technology"}], "pagination": {}}"#),
+            ok(
+                r#"{"data": [{"id": "42", "login": "channel", "display_name": "Channel", "created_at": "2016-12-14T20:32:28Z"}]}"#,
+            ),
+            MockResponse {
+                status: 204,
+                headers: Vec::default(),
+                body: "",
+            },
+        ]);
+        assert_eq!(
+            client
+                .answer("channel", &TwitchRequest::SetGame("science".to_owned()))
+                .await,
+            TwitchResponse::GameChanged("Science & Technology".to_owned())
+        );
+        let urls: Vec<String> = requests.iter().take(4).map(|request| request.url).collect();
+        assert_eq!(urls[1], "/search/categories?query=science&first=1");
+        assert_eq!(urls[3], "/channels?broadcaster_id=42");
+    }
+
+    #[tokio::test]
+    async fn updating_redemptions() {
+        let (client, requests) = mock_helix(vec![
+            ok(
+                r#"{"data": [{"id": "42", "login": "channel", "display_name": "Channel", "created_at": "2016-12-14T20:32:28Z"}]}"#,
+            ),
+            ok(r#"{"data": [{"id": "17fa2df1", "status": "CANCELED"}]}"#),
+        ]);
+        let request = TwitchRequest::UpdateRedemption {
+            id: "17fa2df1".to_owned(),
+            reward_id: "92af127c".to_owned(),
+            fulfilled: false,
+        };
+        assert_eq!(
+            client.answer("channel", &request).await,
+            TwitchResponse::Done
+        );
+        requests.recv().unwrap();
+        let update = requests.recv().unwrap();
+        assert_eq!(update.method, "PATCH");
+        assert_eq!(
+            update.url,
+            "/channel_points/custom_rewards/redemptions?id=17fa2df1&broadcaster_id=42&reward_id=92af127c"
+        );
+        assert_eq!(update.body, r#"{"status":"CANCELED"}"#);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{Game, HelixClient, User};
+use crate::connect::{error::ConnectorError, TwitchRequest, TwitchResponse};
+
+impl HelixClient {
+    async fn user(&self, login: &str) -> Result<Option<User>, ConnectorError> {
+        Ok(self.get_users(&[login]).await?.into_iter().next())
+    }
+
+    // The exact game if there is one, otherwise the best match of a search
+    async fn find_game(&self, name: &str) -> Result<Option<Game>, ConnectorError> {
+        if let Some(game) = self.get_games(&[name]).await?.into_iter().next() {
+            return Ok(Some(game));
+        }
+        Ok(self.search_categories(name, 1).await?.into_iter().next())
+    }
+
+    async fn try_answer(
+        &self,
+        channel: &str,
+        request: &TwitchRequest,
+    ) -> Result<TwitchResponse, ConnectorError> {
+        use TwitchResponse::*;
+        let login = match request {
+            TwitchRequest::ChannelInfo(login) => login,
+            _ => channel,
+        };
+        Ok(match request {
+            TwitchRequest::ChannelInfo(_) | TwitchRequest::StreamInfo => {
+                match self.channel_info(login).await? {
+                    Some(info) => ChannelInfo(info),
+                    None => NotFound,
+                }
+            }
+            TwitchRequest::Uptime => match self.get_streams(&[channel]).await?.into_iter().next() {
+                Some(stream) => Live {
+                    started_at: stream.started_at,
+                },
+                None => Offline,
+            },
+            TwitchRequest::SetTitle(title) => match self.user(channel).await? {
+                Some(broadcaster) => {
+                    self.modify_channel_information(&broadcaster.id, Some(title), None)
+                        .await?;
+                    TitleChanged(title.to_owned())
+                }
+                None => NotFound,
+            },
+            TwitchRequest::SetGame(name) => {
+                match (self.find_game(name).await?, self.user(channel).await?) {
+                    (Some(game), Some(broadcaster)) => {
+                        self.modify_channel_information(&broadcaster.id, None, Some(&game.id))
+                            .await?;
+                        GameChanged(game.name)
+                    }
+                    _ => NotFound,
+                }
+            }
+            TwitchRequest::FollowAge(user_name) => {
+                match (self.user(user_name).await?, self.user(channel).await?) {
+                    (Some(user), Some(broadcaster)) => {
+                        let follow = self
+                            .get_followers(&broadcaster.id, Some(&user.id), 1)
+                            .await?
+                            .followers
+                            .into_iter()
+                            .next();
+                        match follow {
+                            Some(follow) => FollowedAt(follow.followed_at),
+                            None => NotFollowing,
+                        }
+                    }
+                    _ => NotFound,
+                }
+            }
+            TwitchRequest::AccountAge(user_name) => match self.user(user_name).await? {
+                Some(user) => AccountCreatedAt(user.created_at),
+                None => NotFound,
+            },
+            TwitchRequest::UpdateRedemption {
+                id,
+                reward_id,
+                fulfilled,
+            } => match self.user(channel).await? {
+                Some(broadcaster) => {
+                    self.update_redemption_status(&broadcaster.id, reward_id, id, *fulfilled)
+                        .await?;
+                    Done
+                }
+                None => NotFound,
+            },
+            TwitchRequest::AddVip(user_name) | TwitchRequest::RemoveVip(user_name) => {
+                match (self.user(user_name).await?, self.user(channel).await?) {
+                    (Some(user), Some(broadcaster)) => {
+                        if matches!(request, TwitchRequest::AddVip(_)) {
+                            self.add_channel_vip(&broadcaster.id, &user.id).await?;
+                        } else {
+                            self.remove_channel_vip(&broadcaster.id, &user.id).await?;
+                        }
+                        Done
+                    }
+                    _ => NotFound,
+                }
+            }
+        })
+    }
+
+    /// Answers the request of the bot for the given channel, errors become Failed
+    pub async fn answer(&self, channel: &str, request: &TwitchRequest) -> TwitchResponse {
+        self.try_answer(channel, request)
+            .await
+            .unwrap_or_else(|err| {
+                println!("Twitch request {:?} failed: {:?}", request, err);
+                TwitchResponse::Failed(err.to_string())
+            })
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+// Every Helix response wraps its results in a data array, lists are paged with a cursor
+#[derive(Debug, Deserialize)]
+pub(super) struct HelixResponse<T> {
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub pagination: Pagination,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct Pagination {
+    pub cursor: Option<String>,
+}
+
+/// The rate limit reported by the last Helix response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    // points per minute
+    pub limit: u64,
+    pub remaining: u64,
+    // when the bucket is full again
+    pub reset: DateTime<Utc>,
+}
+
+// https://dev.twitch.tv/docs/api/reference#get-users
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct User {
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub broadcaster_type: String,
+    #[serde(default)]
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// https://dev.twitch.tv/docs/api/reference#get-streams
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Stream {
+    pub id: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub game_id: String,
+    pub game_name: String,
+    pub title: String,
+    pub viewer_count: u64,
+    pub started_at: DateTime<Utc>,
+}
+
+// https://dev.twitch.tv/docs/api/reference#get-channel-information
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChannelInformation {
+    pub broadcaster_id: String,
+    pub broadcaster_login: String,
+    pub broadcaster_name: String,
+    pub game_id: String,
+    pub game_name: String,
+    pub title: String,
+}
+
+// https://dev.twitch.tv/docs/api/reference#get-games
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Game {
+    pub id: String,
+    pub name: String,
+}
+
+// https://dev.twitch.tv/docs/api/reference#get-channel-followers
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Follower {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub followed_at: DateTime<Utc>,
+}
+
+/// A page of followers up to the requested limit and the total number of followers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Followers {
+    pub total: u64,
+    pub followers: Vec<Follower>,
+}
+
+// https://dev.twitch.tv/docs/api/reference#get-chatters
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Chatter {
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+}
+
+// https://dev.twitch.tv/docs/api/reference#create-clip
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Clip {
+    pub id: String,
+    pub edit_url: String,
+}
+
+// https://dev.twitch.tv/docs/api/reference#create-stream-marker
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StreamMarker {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub description: String,
+    pub position_seconds: u64,
+}
+
+// https://dev.twitch.tv/docs/api/reference#create-eventsub-subscription
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct EventSubSubscription {
+    pub id: String,
+    pub status: String,
+    #[serde(rename = "type")]
+    pub subscription_type: String,
+    pub version: String,
+}
+
+/// Colors of announcements, the primary color is the channel's accent color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnouncementColor {
+    Primary,
+    Blue,
+    Green,
+    Orange,
+    Purple,
+}
+
+impl AnnouncementColor {
+    pub(super) fn name(&self) -> &str {
+        match self {
+            AnnouncementColor::Primary => "primary",
+            AnnouncementColor::Blue => "blue",
+            AnnouncementColor::Green => "green",
+            AnnouncementColor::Orange => "orange",
+            AnnouncementColor::Purple => "purple",
+        }
+    }
+}
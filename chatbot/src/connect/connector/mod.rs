@@ -1,3 +1,7 @@
+mod eventsub;
+mod helix;
 pub(crate) mod twitch_chat;
 
-pub use twitch_chat::TwitchChatConnector;
+pub use eventsub::EventSubConnector;
+pub use helix::HelixClient;
+pub use twitch_chat::{AccessTokenDispenser, TwitchChatConnector};
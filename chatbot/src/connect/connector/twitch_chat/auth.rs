@@ -8,6 +8,8 @@ use reqwest::Response;
 use serde_json::{from_str, Value};
 
 const VALIDATION_URL: &str = "https://id.twitch.tv/oauth2/validate";
+// chat access plus the scopes of the Helix endpoints the bot uses
+const SCOPES: &str = "chat:read chat:edit channel:manage:broadcast clips:edit moderator:read:followers moderator:read:chatters moderator:manage:announcements moderator:manage:shoutouts channel:manage:redemptions channel:manage:vips channel:read:hype_train";
 const REDIRECT_URI: &str = "https://localhost:3030";
 const AUTH_CONFIG_FILE: &str = "./auth_store";
 const AUTH_BUCKET_NAME: &str = "auth_config";
@@ -74,9 +76,10 @@ fn retrieve_code(client_id: &str) -> Result<String, ConnectorError> {
         ))
     })?;
     println!(
-            "Open link https://id.twitch.tv/oauth2/authorize?client_id={}&redirect_uri=https://localhost:3030&response_type=code&scope=chat:read%20chat:edit",
-            client_id,
-        );
+        "Open link https://id.twitch.tv/oauth2/authorize?client_id={}&redirect_uri=https://localhost:3030&response_type=code&scope={}",
+        client_id,
+        SCOPES.replace(' ', "%20"),
+    );
     let request: tiny_http::Request = server.recv().map_err(|err| {
         ConnectorError::MessageReceiveFailed(format!(
             "Could not receive request with code: {:?}",
@@ -94,12 +97,11 @@ fn load_saved_access_token() -> Result<(String, String), ConnectorError> {
     let bucket = store.bucket::<String, String>(Some(AUTH_BUCKET_NAME))?;
     let access_token = bucket.get("access_token")?;
     let refresh_token = bucket.get("refresh_token")?;
-    if access_token.is_some() && refresh_token.is_some() {
-        Ok((access_token.unwrap(), refresh_token.unwrap()))
-    } else {
-        Err(ConnectorError::StoredValueNotAvailable(
+    match (access_token, refresh_token) {
+        (Some(access_token), Some(refresh_token)) => Ok((access_token, refresh_token)),
+        _ => Err(ConnectorError::StoredValueNotAvailable(
             "access_token or refresh_token".to_owned(),
-        ))
+        )),
     }
 }
 
@@ -111,15 +113,13 @@ async fn request_access_token(uri: &str) -> Result<(String, String), ConnectorEr
             let json = get_json_from_response(response).await?;
             let access_token = json["access_token"].as_str();
             let refresh_token = json["refresh_token"].as_str();
-            if access_token.is_some() && refresh_token.is_some() {
-                Ok((
-                    access_token.unwrap().to_owned(),
-                    refresh_token.unwrap().to_owned(),
-                ))
-            } else {
-                Err(ConnectorError::ExternalServerError(
+            match (access_token, refresh_token) {
+                (Some(access_token), Some(refresh_token)) => {
+                    Ok((access_token.to_owned(), refresh_token.to_owned()))
+                }
+                _ => Err(ConnectorError::ExternalServerError(
                     "Server did not provide access token or refresh token in response".to_owned(),
-                ))
+                )),
             }
         }
         403 => {
@@ -177,7 +177,7 @@ async fn refresh_access_token(
     let query_params: HashMap<&str, &str> = HashMap::from([
         ("client_id", client_id),
         ("client_secret", client_secret),
-        ("refresh_token", &refresh_token),
+        ("refresh_token", refresh_token),
         ("grant_type", "refresh_token"),
     ]);
     let uri = create_url_with_query_params("https://id.twitch.tv/oauth2/token", &query_params);
@@ -200,28 +200,29 @@ async fn refresh_access_token_retrying(
 
 fn store_tokens(access_token: &str, refresh_token: &str) {
     let cfg = Config::new(AUTH_CONFIG_FILE);
-    if let Err(_) = Store::new(cfg)
+    if Store::new(cfg)
         .and_then(|store| store.bucket::<String, String>(Some(AUTH_BUCKET_NAME)))
         .and_then(|bucket| {
             let access_token_saving = bucket.set(ACCESS_TOKEN_PERSISTENCE_KEY, access_token);
             let refresh_token_saving = bucket.set(REFRESH_TOKEN_PERSISTENCE_KEY, refresh_token);
             access_token_saving.and(refresh_token_saving)
         })
+        .is_err()
     {
         println!("Could not store access token or refresh token");
     }
 }
 
-pub struct AccessTokenDispenser<'a> {
-    app_config: &'a AppConfig,
+/// Hands out valid access tokens, refreshing and persisting them when needed
+pub struct AccessTokenDispenser {
+    client_id: String,
+    client_secret: String,
     access_token: String,
     refresh_token: String,
 }
 
-impl<'a> AccessTokenDispenser<'a> {
-    pub async fn new(
-        app_config: &'a AppConfig,
-    ) -> Result<AccessTokenDispenser<'a>, ConnectorError> {
+impl AccessTokenDispenser {
+    pub async fn new(app_config: &AppConfig) -> Result<AccessTokenDispenser, ConnectorError> {
         let (access_token, refresh_token) = match load_saved_access_token() {
             Ok(val) => val,
             Err(_) => {
@@ -235,7 +236,8 @@ impl<'a> AccessTokenDispenser<'a> {
             }
         };
         Ok(Self {
-            app_config,
+            client_id: app_config.twitch_client_id().to_owned(),
+            client_secret: app_config.twitch_client_secret().to_owned(),
             access_token,
             refresh_token,
         })
@@ -247,8 +249,8 @@ impl<'a> AccessTokenDispenser<'a> {
             true => (self.access_token.to_owned(), self.refresh_token.to_owned()),
             false => {
                 refresh_access_token_retrying(
-                    &self.app_config.twitch_client_id(),
-                    &self.app_config.twitch_client_secret(),
+                    &self.client_id,
+                    &self.client_secret,
                     &self.refresh_token,
                 )
                 .await?
@@ -5,7 +5,7 @@ use super::{
 };
 use crate::{
     app_config::AppConfig,
-    connect::{error::ConnectorError, ChatBotEvent},
+    connect::{error::ConnectorError, ChatBotEvent, ModerationAction},
 };
 use std::{
     net::TcpStream,
@@ -23,6 +23,8 @@ pub struct TwitchChatConnector<'a> {
 impl<'a> TwitchChatConnector<'a> {
     pub async fn new(
         app_config: &'a AppConfig,
+        access_token_dispenser: &mut AccessTokenDispenser,
+        command_prefix: &str,
         chatbot_event_sender: Sender<ChatBotEvent>,
     ) -> TwitchChatConnector<'a> {
         let chat_client = ClientBuilder::new("ws://irc-ws.chat.twitch.tv:80")
@@ -30,9 +32,6 @@ impl<'a> TwitchChatConnector<'a> {
             .connect_insecure()
             .unwrap();
         let (receiver, mut sender) = chat_client.split().unwrap();
-        let mut access_token_dispenser = AccessTokenDispenser::new(app_config)
-            .await
-            .expect("Could not instantiate Twitch connector");
         let access_token: String = access_token_dispenser
             .get()
             .await
@@ -48,7 +47,12 @@ impl<'a> TwitchChatConnector<'a> {
         )
         .expect("Could not log in");
         let send_thread = send_thread(sender);
-        let receive_thread = receive_thread(receiver, chatbot_event_sender, send_thread.tx.clone());
+        let receive_thread = receive_thread(
+            receiver,
+            command_prefix.to_owned(),
+            chatbot_event_sender,
+            send_thread.tx.clone(),
+        );
         Self {
             send_thread,
             _receive_thread: receive_thread,
@@ -62,6 +66,13 @@ impl<'a> TwitchChatConnector<'a> {
             message.to_string(),
         ))?)
     }
+
+    pub fn moderate(&self, action: &ModerationAction) -> Result<(), ConnectorError> {
+        Ok(self.send_thread.tx.send(SendTask::from_moderation_action(
+            self.app_config.channel_name(),
+            action,
+        ))?)
+    }
 }
 
 struct ReceiveThread {
@@ -70,12 +81,13 @@ struct ReceiveThread {
 
 fn receive_thread(
     mut receiver: Reader<TcpStream>,
+    command_prefix: String,
     send_chat_bot_events: Sender<ChatBotEvent>,
     send_tasks: SyncSender<SendTask>,
 ) -> ReceiveThread {
     use ReceiveEvent::*;
     let handle = thread::spawn(move || 'outer: loop {
-        match receive(&mut receiver) {
+        match receive(&mut receiver, &command_prefix) {
             Ok(events) => {
                 for event in events {
                     if let ChatBotEvent(event_content) = event {
@@ -1,7 +1,8 @@
-mod auth;
+pub(crate) mod auth;
 mod connector;
 mod receive;
-mod retry_manager;
+pub(crate) mod retry_manager;
 pub(crate) mod send;
 
+pub use auth::AccessTokenDispenser;
 pub use connector::TwitchChatConnector;
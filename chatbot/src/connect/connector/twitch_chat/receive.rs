@@ -1,11 +1,15 @@
 use crate::connect::error::ConnectorError;
-use crate::connect::{types::CommandType, Badge, ChatBotEvent, Command, TextMessage, UserInfo};
+use crate::connect::{Badge, ChatBotEvent, Command, Emote, RoomState, TextMessage, UserInfo};
 use std::collections::{HashMap, HashSet};
 use std::net::TcpStream;
+use std::time::Duration;
 use websocket::WebSocketError;
 use websocket::{receiver::Reader, OwnedMessage};
 
-pub fn receive(receiver: &mut Reader<TcpStream>) -> Result<Vec<ReceiveEvent>, ConnectorError> {
+pub fn receive(
+    receiver: &mut Reader<TcpStream>,
+    command_prefix: &str,
+) -> Result<Vec<ReceiveEvent>, ConnectorError> {
     loop {
         match receiver.recv_message() {
             Err(WebSocketError::NoDataAvailable) => continue,
@@ -15,7 +19,9 @@ pub fn receive(receiver: &mut Reader<TcpStream>) -> Result<Vec<ReceiveEvent>, Co
                         println!("New websocket message: {}", text);
                         let events = text
                             .lines()
-                            .filter_map(ReceiveEvent::parse_from_message)
+                            .filter_map(|line| {
+                                ReceiveEvent::parse_from_message(line, command_prefix)
+                            })
                             .collect();
                         return Ok(events);
                     }
@@ -44,34 +50,21 @@ pub enum ReceiveEvent {
 }
 
 impl ReceiveEvent {
-    fn parse_command_kind(command_name: &str) -> CommandType {
-        match command_name {
-            "help" => CommandType::Help,
-            "info" => CommandType::Info,
-            "newcommand" => CommandType::NewCommand,
-            "removecommand" => CommandType::RemoveCommand,
-            "slap" => CommandType::Slap,
-            "discord" => CommandType::Discord,
-            "newrepeating" => CommandType::NewRepeating,
-            "removerepeating" => CommandType::RemoveRepeating,
-            _ => CommandType::Dynamic(command_name.to_owned()),
-        }
-    }
-
-    fn parse_command_from_message(message: &str) -> Option<(CommandType, Vec<String>)> {
-        if message == "!" {
-            return None;
-        }
+    // Command names are not case sensitive, they are passed on in lowercase
+    fn parse_command_from_message(
+        message: &str,
+        command_prefix: &str,
+    ) -> Option<(String, Vec<String>)> {
+        let message = message.strip_prefix(command_prefix)?;
         let mut words = message.split(' ');
-        words.next().map(|name| {
-            (
-                ReceiveEvent::parse_command_kind(&name[1..]),
-                words.map(String::from).collect(),
-            )
-        })
+        words
+            .next()
+            .filter(|name| !name.is_empty())
+            .map(|name| (name.to_lowercase(), words.map(String::from).collect()))
     }
 
-    pub fn parse_from_message(message: &str) -> Option<Self> {
+    /// Parses a line of the chat, messages starting with the prefix are commands
+    pub fn parse_from_message(message: &str, command_prefix: &str) -> Option<Self> {
         enum ParsingState {
             Start,
             Tags,
@@ -112,7 +105,11 @@ impl ReceiveEvent {
                     }
                 }
                 UserName => match codepoint {
-                    ' ' => return None,
+                    // messages from the server itself (e.g. ":tmi.twitch.tv CLEARMSG") have no user
+                    ' ' => {
+                        marker = i + 1;
+                        state = MessageToken;
+                    }
                     '!' => {
                         user_name = &message[marker..i];
                         state = AdditionalUserInfo;
@@ -142,6 +139,23 @@ impl ReceiveEvent {
                                     user_name.to_string(),
                                 )))
                             }
+                            "CLEARMSG" => {
+                                return parse_clear_message(&tags_map, &message[i + 1..])
+                                    .map(ReceiveEvent::ChatBotEvent)
+                            }
+                            "CLEARCHAT" => {
+                                return parse_clear_chat(&tags_map, &message[i + 1..])
+                                    .map(ReceiveEvent::ChatBotEvent)
+                            }
+                            "USERNOTICE" => {
+                                return parse_user_notice(&tags_map).map(ReceiveEvent::ChatBotEvent)
+                            }
+                            "USERSTATE" => {
+                                return Some(ReceiveEvent::ChatBotEvent(parse_user_state(tags_map)))
+                            }
+                            "ROOMSTATE" => {
+                                return parse_room_state(&tags_map).map(ReceiveEvent::ChatBotEvent)
+                            }
                             _ => return None,
                         };
                     }
@@ -152,25 +166,39 @@ impl ReceiveEvent {
                     }
                 }
                 MessageBody => {
+                    let message_id = tags_map.get("id").cloned().unwrap_or_default();
+                    let emotes = get_emotes(&tags_map);
+                    let first_message = tags_map.get("first-msg").map(String::as_str) == Some("1");
+                    let bits = tags_map
+                        .get("bits")
+                        .and_then(|bits| bits.parse().ok())
+                        .unwrap_or_default();
                     let badges = get_badges(tags_map);
                     let user_info = UserInfo {
                         name: user_name.to_owned(),
                         badges,
                     };
                     let user_message = message[i..].trim();
-                    if codepoint == '!' {
-                        let (command_kind, command_options) =
-                            ReceiveEvent::parse_command_from_message(user_message)?;
+                    if user_message.starts_with(command_prefix) {
+                        let (command_name, command_options) =
+                            ReceiveEvent::parse_command_from_message(user_message, command_prefix)?;
                         return Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-                            kind: command_kind,
+                            name: command_name,
                             options: command_options,
                             user: user_info,
+                            id: message_id,
+                            text: user_message.to_owned(),
+                            emotes,
                         })));
                     } else {
                         return Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(
                             TextMessage {
+                                id: message_id,
                                 text: message[i..].trim().to_owned(),
                                 user: user_info,
+                                emotes,
+                                first_message,
+                                bits,
                             },
                         )));
                     }
@@ -181,6 +209,83 @@ impl ReceiveEvent {
     }
 }
 
+// The trailing parameter of a message, e.g. "text" in "#channel :text"
+fn trailing_parameter(parameters: &str) -> Option<&str> {
+    parameters
+        .split_once(" :")
+        .map(|(_, trailing)| trailing.trim())
+        .filter(|trailing| !trailing.is_empty())
+}
+
+// https://dev.twitch.tv/docs/irc/commands#clearmsg
+fn parse_clear_message(tags: &HashMap<String, String>, parameters: &str) -> Option<ChatBotEvent> {
+    Some(ChatBotEvent::MessageDeleted {
+        user_name: tags.get("login")?.to_owned(),
+        message_id: tags.get("target-msg-id")?.to_owned(),
+        text: trailing_parameter(parameters)
+            .unwrap_or_default()
+            .to_owned(),
+    })
+}
+
+// https://dev.twitch.tv/docs/irc/commands#clearchat
+// Without a user name, the whole chat was cleared, which is not reported.
+fn parse_clear_chat(tags: &HashMap<String, String>, parameters: &str) -> Option<ChatBotEvent> {
+    let user_name = trailing_parameter(parameters)?.to_owned();
+    match tags
+        .get("ban-duration")
+        .and_then(|seconds| seconds.parse().ok())
+    {
+        Some(seconds) => Some(ChatBotEvent::UserTimedOut(
+            user_name,
+            Duration::from_secs(seconds),
+        )),
+        None => Some(ChatBotEvent::UserBanned(user_name)),
+    }
+}
+
+// https://dev.twitch.tv/docs/irc/commands#usernotice
+// Only raids are reported, other notices like subscriptions are ignored.
+fn parse_user_notice(tags: &HashMap<String, String>) -> Option<ChatBotEvent> {
+    match tags.get("msg-id").map(String::as_str) {
+        Some("raid") => Some(ChatBotEvent::Raid {
+            user_name: tags.get("msg-param-login")?.to_owned(),
+            viewers: tags
+                .get("msg-param-viewerCount")
+                .and_then(|viewers| viewers.parse().ok())
+                .unwrap_or_default(),
+        }),
+        _ => None,
+    }
+}
+
+// https://dev.twitch.tv/docs/irc/commands#userstate
+fn parse_user_state(tags: HashMap<String, String>) -> ChatBotEvent {
+    let is_moderator = tags.get("mod").map(String::as_str) == Some("1");
+    let bot = UserInfo {
+        name: String::default(),
+        badges: get_badges(tags),
+    };
+    ChatBotEvent::BotModeratorStatus(is_moderator || bot.has_elevated_rights())
+}
+
+// https://dev.twitch.tv/docs/irc/commands#roomstate
+// Only the modes the raid protection changes are reported.
+fn parse_room_state(tags: &HashMap<String, String>) -> Option<ChatBotEvent> {
+    let room_state = RoomState {
+        followers_only: tags
+            .get("followers-only")
+            .and_then(|minutes| minutes.parse::<i64>().ok())
+            .map(|minutes| {
+                u64::try_from(minutes)
+                    .ok()
+                    .map(|minutes| Duration::from_secs(minutes * 60))
+            }),
+        emote_only: tags.get("emote-only").map(|emote_only| emote_only == "1"),
+    };
+    (room_state != RoomState::default()).then_some(ChatBotEvent::RoomState(room_state))
+}
+
 fn parse_tags(tags_string: &str) -> HashMap<String, String> {
     tags_string
         .split(';')
@@ -215,6 +320,28 @@ fn get_badges(tags: HashMap<String, String>) -> HashSet<Badge> {
     }
 }
 
+// emotes tag format: "<emote id>:<start>-<end>,<start>-<end>/<emote id>:<start>-<end>"
+fn get_emotes(tags: &HashMap<String, String>) -> Vec<Emote> {
+    let emotes = match tags.get("emotes") {
+        Some(emotes) => emotes,
+        None => return Vec::default(),
+    };
+    emotes
+        .split('/')
+        .filter_map(|emote| emote.split_once(':'))
+        .flat_map(|(id, positions)| {
+            positions.split(',').filter_map(move |position| {
+                let (start, end) = position.split_once('-')?;
+                Some(Emote {
+                    id: id.to_owned(),
+                    start: start.parse().ok()?,
+                    end: end.parse().ok()?,
+                })
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -224,14 +351,18 @@ mod tests {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :This is a test message";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(
             TextMessage {
+                id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
                 text: "This is a test message".to_owned(),
                 user: UserInfo {
                     name: "chatter".to_owned(),
                     badges: HashSet::default(),
                 },
+                emotes: Vec::default(),
+                first_message: false,
+                bits: 0,
             },
         )));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
@@ -239,14 +370,18 @@ mod tests {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :This is a test message\n";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(
             TextMessage {
+                id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
                 text: "This is a test message".to_owned(),
                 user: UserInfo {
                     name: "chatter".to_owned(),
                     badges: HashSet::default(),
                 },
+                emotes: Vec::default(),
+                first_message: false,
+                bits: 0,
             },
         )));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
@@ -254,6 +389,7 @@ mod tests {
         let message = "@badge-info=;badges=badge1/2,badge2/10;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :This is a test message";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(
             TextMessage {
+                id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
                 text: "This is a test message".to_owned(),
                 user: UserInfo {
                     name: "chatter".to_owned(),
@@ -268,37 +404,206 @@ mod tests {
                         },
                     ]),
                 },
+                emotes: Vec::default(),
+                first_message: false,
+                bits: 0,
             },
         )));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
+    }
+
+    #[test]
+    fn parsing_first_messages() {
+        let message = "@badge-info=;badges=;color=;display-name=carkhy;emotes=;first-msg=1;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :Hi, first time here";
+        assert!(matches!(
+            ReceiveEvent::parse_from_message(message, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(text_message)))
+                if text_message.first_message
+        ));
+    }
+
+    #[test]
+    fn parsing_cheers() {
+        let message = "@badge-info=;badges=bits/100;bits=250;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :Cheer250 great stream";
+        assert!(matches!(
+            ReceiveEvent::parse_from_message(message, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(text_message)))
+                if text_message.bits == 250
+        ));
+    }
+
+    #[test]
+    fn parsing_emotes() {
+        let message = "@badge-info=;badges=;color=;display-name=carkhy;emotes=25:0-4,12-16/1902:6-10;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :Kappa Keepo Kappa";
+        let emote = |id: &str, start, end| Emote {
+            id: id.to_owned(),
+            start,
+            end,
+        };
+        match ReceiveEvent::parse_from_message(message, "!") {
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(text_message))) => {
+                assert_eq!(
+                    text_message.emotes,
+                    vec![emote("25", 0, 4), emote("25", 12, 16), emote("1902", 6, 10)]
+                );
+                assert_eq!(text_message.text_without_emotes(), "  ");
+            }
+            other => panic!("Unexpected parsing result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parsing_deleted_messages() {
+        let message = "@login=ronni;room-id=;target-msg-id=abc-123-def;tmi-sent-ts=1642720582342 :tmi.twitch.tv CLEARMSG #dallas :HeyGuys";
+        let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::MessageDeleted {
+            user_name: "ronni".to_owned(),
+            message_id: "abc-123-def".to_owned(),
+            text: "HeyGuys".to_owned(),
+        }));
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
+    }
+
+    #[test]
+    fn parsing_bans_and_timeouts() {
+        let ban = "@room-id=12345678;target-user-id=87654321;tmi-sent-ts=1642715756806 :tmi.twitch.tv CLEARCHAT #dallas :ronni";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(ban, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::UserBanned(
+                "ronni".to_owned()
+            )))
+        );
+        let timeout = "@ban-duration=350;room-id=12345678;target-user-id=87654321;tmi-sent-ts=1642719320727 :tmi.twitch.tv CLEARCHAT #dallas :ronni";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(timeout, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::UserTimedOut(
+                "ronni".to_owned(),
+                Duration::from_secs(350)
+            )))
+        );
+        let clear = "@room-id=12345678;tmi-sent-ts=1642715695392 :tmi.twitch.tv CLEARCHAT #dallas";
+        assert_eq!(ReceiveEvent::parse_from_message(clear, "!"), None);
+    }
+
+    #[test]
+    fn parsing_bot_moderator_status() {
+        let moderator = "@badge-info=;badges=moderator/1;color=;display-name=botanist;emote-sets=0;mod=1;subscriber=0;user-type=mod :tmi.twitch.tv USERSTATE #dallas";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(moderator, "!"),
+            Some(ReceiveEvent::ChatBotEvent(
+                ChatBotEvent::BotModeratorStatus(true)
+            ))
+        );
+        let broadcaster = "@badge-info=;badges=broadcaster/1;color=;display-name=botanist;emote-sets=0;mod=0;subscriber=0;user-type= :tmi.twitch.tv USERSTATE #botanist";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(broadcaster, "!"),
+            Some(ReceiveEvent::ChatBotEvent(
+                ChatBotEvent::BotModeratorStatus(true)
+            ))
+        );
+        let chatter = "@badge-info=;badges=;color=;display-name=botanist;emote-sets=0;mod=0;subscriber=0;user-type= :tmi.twitch.tv USERSTATE #dallas";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(chatter, "!"),
+            Some(ReceiveEvent::ChatBotEvent(
+                ChatBotEvent::BotModeratorStatus(false)
+            ))
+        );
+    }
+
+    #[test]
+    fn parsing_room_states() {
+        let join = "@emote-only=0;followers-only=10;r9k=0;room-id=12345678;slow=0;subs-only=0 :tmi.twitch.tv ROOMSTATE #dallas";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(join, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::RoomState(
+                RoomState {
+                    followers_only: Some(Some(Duration::from_secs(600))),
+                    emote_only: Some(false),
+                }
+            )))
+        );
+        let followers_only_off =
+            "@followers-only=-1;room-id=12345678 :tmi.twitch.tv ROOMSTATE #dallas";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(followers_only_off, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::RoomState(
+                RoomState {
+                    followers_only: Some(None),
+                    emote_only: None,
+                }
+            )))
+        );
+        let slow_mode = "@room-id=12345678;slow=10 :tmi.twitch.tv ROOMSTATE #dallas";
+        assert_eq!(ReceiveEvent::parse_from_message(slow_mode, "!"), None);
+    }
+
+    #[test]
+    fn parsing_raids() {
+        let raid = "@badge-info=;badges=;color=#9ACD32;display-name=TestChannel;emotes=;id=3d830f12-795c-447d-af3c-ea05e40fbddb;login=testchannel;mod=0;msg-id=raid;msg-param-displayName=TestChannel;msg-param-login=testchannel;msg-param-viewerCount=15;room-id=33332222;subscriber=0;system-msg=15\\sraiders\\sfrom\\sTestChannel\\shave\\sjoined!;tmi-sent-ts=1507246572675;user-id=123456;user-type= :tmi.twitch.tv USERNOTICE #othertestchannel";
+        assert_eq!(
+            ReceiveEvent::parse_from_message(raid, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Raid {
+                user_name: "testchannel".to_owned(),
+                viewers: 15,
+            }))
+        );
+        let subscription = "@badge-info=;badges=;login=viewer;msg-id=sub;msg-param-cumulative-months=1 :tmi.twitch.tv USERNOTICE #othertestchannel";
+        assert_eq!(ReceiveEvent::parse_from_message(subscription, "!"), None);
     }
 
     #[test]
     fn parsing_help_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!help";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::Help,
+            name: "help".to_owned(),
             options: Vec::default(),
             user: UserInfo {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!help".to_owned(),
+            emotes: Vec::default(),
+        })));
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
+    }
+
+    #[test]
+    fn parsing_commands_with_another_prefix() {
+        let message = "@badge-info=;badges=;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :~Help me";
+        let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
+            name: "help".to_owned(),
+            options: vec!["me".to_owned()],
+            user: UserInfo {
+                name: "chatter".to_owned(),
+                badges: HashSet::default(),
+            },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "~Help me".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "~"), expected);
+        assert!(matches!(
+            ReceiveEvent::parse_from_message(message, "!"),
+            Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::TextMessage(text_message)))
+                if text_message.text == "~Help me"
+        ));
     }
 
     #[test]
     fn parsing_info_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!info";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::Info,
+            name: "info".to_owned(),
             options: Vec::default(),
             user: UserInfo {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!info".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
@@ -307,7 +612,7 @@ mod tests {
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Join(
             "carkhy".to_owned(),
         )));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
@@ -316,28 +621,31 @@ mod tests {
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Part(
             "carkhy".to_owned(),
         )));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
     fn parsing_slap_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!slap anotheruser";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::Slap,
+            name: "slap".to_owned(),
             options: vec!["anotheruser".to_owned()],
             user: UserInfo {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!slap anotheruser".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
     fn parsing_newcommand_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!newcommand command Text to output";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::NewCommand,
+            name: "newcommand".to_owned(),
             options: vec![
                 "command".to_owned(),
                 "Text".to_owned(),
@@ -348,57 +656,69 @@ mod tests {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!newcommand command Text to output".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
     fn parsing_removecommand_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!removecommand command";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::RemoveCommand,
+            name: "removecommand".to_owned(),
             options: vec!["command".to_owned()],
             user: UserInfo {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!removecommand command".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
     fn parsing_discord_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!discord";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::Discord,
+            name: "discord".to_owned(),
             options: Vec::default(),
             user: UserInfo {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!discord".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
     fn parsing_dynamic_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!unknown command";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::Dynamic("unknown".to_owned()),
+            name: "unknown".to_owned(),
             options: vec!["command".to_owned()],
             user: UserInfo {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!unknown command".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 
     #[test]
     fn parsing_newrepeating_command() {
         let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!newrepeating command 60 Text to output";
         let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
-            kind: CommandType::NewRepeating,
+            name: "newrepeating".to_owned(),
             options: vec![
                 "command".to_owned(),
                 "60".to_owned(),
@@ -410,7 +730,52 @@ mod tests {
                 name: "chatter".to_owned(),
                 badges: HashSet::default(),
             },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!newrepeating command 60 Text to output".to_owned(),
+            emotes: Vec::default(),
+        })));
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
+    }
+
+    #[test]
+    fn parsing_permit_command() {
+        let message = "@badge-info=;badges=;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=0;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type= :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!permit anotheruser 120";
+        let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
+            name: "permit".to_owned(),
+            options: vec!["anotheruser".to_owned(), "120".to_owned()],
+            user: UserInfo {
+                name: "chatter".to_owned(),
+                badges: HashSet::default(),
+            },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!permit anotheruser 120".to_owned(),
+            emotes: Vec::default(),
+        })));
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
+    }
+
+    #[test]
+    fn parsing_timeout_command() {
+        let message = "@badge-info=;badges=moderator/1;client-nonce=1e51cee7513a4516545bbc36a22f27eb;color=;display-name=carkhy;emotes=;first-msg=0;flags=;id=60904094-3684-4871-9e8c-1400648a804d;mod=1;room-id=120630112;subscriber=0;tmi-sent-ts=1637614002702;turbo=0;user-id=70346833;user-type=mod :chatter!chatter@chatter.tmi.twitch.tv PRIVMSG #channel123 :!to anotheruser 10m calm down";
+        let expected = Some(ReceiveEvent::ChatBotEvent(ChatBotEvent::Command(Command {
+            name: "to".to_owned(),
+            options: vec![
+                "anotheruser".to_owned(),
+                "10m".to_owned(),
+                "calm".to_owned(),
+                "down".to_owned(),
+            ],
+            user: UserInfo {
+                name: "chatter".to_owned(),
+                badges: HashSet::from([Badge {
+                    name: "moderator".to_owned(),
+                    level: 1,
+                }]),
+            },
+            id: "60904094-3684-4871-9e8c-1400648a804d".to_owned(),
+            text: "!to anotheruser 10m calm down".to_owned(),
+            emotes: Vec::default(),
         })));
-        assert_eq!(ReceiveEvent::parse_from_message(message), expected);
+        assert_eq!(ReceiveEvent::parse_from_message(message, "!"), expected);
     }
 }
@@ -21,7 +21,12 @@ impl ErrorHandler<ConnectorError> for ExponentialRetryManager {
 
     fn handle(&mut self, attempt: usize, err: ConnectorError) -> RetryPolicy<Self::OutError> {
         match err {
-            ConnectorError::HTTP404 => RetryPolicy::ForwardError(err),
+            ConnectorError::HTTP404 | ConnectorError::HelixRequestFailed(..) => {
+                RetryPolicy::ForwardError(err)
+            }
+            ConnectorError::RateLimited(reset) if attempt <= self.max_num_attempts => {
+                RetryPolicy::WaitRetry(reset)
+            }
             _ => {
                 if attempt > self.max_num_attempts {
                     RetryPolicy::ForwardError(err)
@@ -1,5 +1,5 @@
-use crate::connect::error::ConnectorError;
-use std::net::TcpStream;
+use crate::connect::{error::ConnectorError, ModerationAction};
+use std::{fmt, net::TcpStream};
 use websocket::{sync::Writer, Message};
 
 pub fn send(sender: &mut Writer<TcpStream>, task: SendTask) -> Result<(), ConnectorError> {
@@ -24,13 +24,14 @@ pub fn get_login_tasks<'a>(
     user_name: &'a str,
     channel: &'a str,
 ) -> Vec<SendTask> {
-    return vec![
+    vec![
         SendTask::ProvideLoginPassword(password.to_string()),
         SendTask::ProvideLoginUserName(user_name.to_string()),
         SendTask::JoinChannel(channel.to_string()),
         SendTask::RequestCapabilities("membership".to_string()),
         SendTask::RequestCapabilities("tags".to_string()),
-    ];
+        SendTask::RequestCapabilities("commands".to_string()),
+    ]
 }
 
 pub enum SendTask {
@@ -40,19 +41,102 @@ pub enum SendTask {
     JoinChannel(String),
     RequestCapabilities(String),
     Pong,
+    // channel, message id
+    DeleteMessage(String, String),
+    // channel, user name, duration in seconds, reason
+    Timeout(String, String, u64, Option<String>),
+    // channel, user name, reason
+    Ban(String, String, Option<String>),
+    // channel, user name
+    Unban(String, String),
+    // channel, seconds between messages (None turns slow mode off)
+    SlowMode(String, Option<u64>),
+    // channel, minimum follow time in minutes (None turns followers-only mode off)
+    FollowersOnly(String, Option<u64>),
+    // channel, on/off
+    EmoteOnly(String, bool),
+    ClearChat(String),
 }
 
-impl ToString for SendTask {
-    fn to_string(&self) -> String {
+impl SendTask {
+    pub fn from_moderation_action(channel: &str, action: &ModerationAction) -> Self {
+        let channel = channel.to_string();
+        match action {
+            ModerationAction::DeleteMessage(id) => Self::DeleteMessage(channel, id.to_owned()),
+            ModerationAction::Timeout {
+                user,
+                duration,
+                reason,
+            } => Self::Timeout(
+                channel,
+                user.to_owned(),
+                duration.as_secs(),
+                reason.to_owned(),
+            ),
+            ModerationAction::Ban { user, reason } => {
+                Self::Ban(channel, user.to_owned(), reason.to_owned())
+            }
+            ModerationAction::Unban(user) => Self::Unban(channel, user.to_owned()),
+            ModerationAction::SlowMode(delay) => {
+                Self::SlowMode(channel, delay.map(|delay| delay.as_secs()))
+            }
+            ModerationAction::FollowersOnly(follow_time) => Self::FollowersOnly(
+                channel,
+                follow_time.map(|follow_time| follow_time.as_secs() / 60),
+            ),
+            ModerationAction::EmoteOnly(enabled) => Self::EmoteOnly(channel, *enabled),
+            ModerationAction::ClearChat => Self::ClearChat(channel),
+        }
+    }
+}
+
+fn with_reason(command: String, reason: &Option<String>) -> String {
+    match reason {
+        Some(reason) => format!("{} {}", command, reason),
+        None => command,
+    }
+}
+
+impl fmt::Display for SendTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::PrivateMessage(channel, message) => format!("PRIVMSG #{} :{}", channel, message),
-            Self::ProvideLoginPassword(password) => format!("PASS oauth:{}", password),
-            Self::ProvideLoginUserName(user_name) => format!("NICK {}", user_name),
-            Self::JoinChannel(channel) => format!("JOIN #{}", channel),
+            Self::PrivateMessage(channel, message) => {
+                write!(f, "PRIVMSG #{} :{}", channel, message)
+            }
+            Self::ProvideLoginPassword(password) => write!(f, "PASS oauth:{}", password),
+            Self::ProvideLoginUserName(user_name) => write!(f, "NICK {}", user_name),
+            Self::JoinChannel(channel) => write!(f, "JOIN #{}", channel),
             Self::RequestCapabilities(capability_name) => {
-                format!("CAP REQ :twitch.tv/{}", capability_name)
+                write!(f, "CAP REQ :twitch.tv/{}", capability_name)
+            }
+            Self::Pong => write!(f, "PONG :tmi.twitch.tv"),
+            Self::DeleteMessage(channel, id) => write!(f, "PRIVMSG #{} :/delete {}", channel, id),
+            Self::Timeout(channel, user, seconds, reason) => write!(
+                f,
+                "PRIVMSG #{} :{}",
+                channel,
+                with_reason(format!("/timeout {} {}", user, seconds), reason)
+            ),
+            Self::Ban(channel, user, reason) => write!(
+                f,
+                "PRIVMSG #{} :{}",
+                channel,
+                with_reason(format!("/ban {}", user), reason)
+            ),
+            Self::Unban(channel, user) => write!(f, "PRIVMSG #{} :/unban {}", channel, user),
+            Self::SlowMode(channel, Some(seconds)) => {
+                write!(f, "PRIVMSG #{} :/slow {}", channel, seconds)
             }
-            Self::Pong => format!("PONG :tmi.twitch.tv"),
+            Self::SlowMode(channel, None) => write!(f, "PRIVMSG #{} :/slowoff", channel),
+            Self::FollowersOnly(channel, Some(minutes)) => {
+                write!(f, "PRIVMSG #{} :/followers {}m", channel, minutes)
+            }
+            Self::FollowersOnly(channel, None) => {
+                write!(f, "PRIVMSG #{} :/followersoff", channel)
+            }
+            Self::EmoteOnly(channel, true) => write!(f, "PRIVMSG #{} :/emoteonly", channel),
+            Self::EmoteOnly(channel, false) => write!(f, "PRIVMSG #{} :/emoteonlyoff", channel),
+            Self::ClearChat(channel) => write!(f, "PRIVMSG #{} :/clear", channel),
         }
     }
 }
@@ -96,4 +180,69 @@ mod tests {
         let task = SendTask::Pong;
         assert_eq!(task.to_string(), "PONG :tmi.twitch.tv");
     }
+
+    #[test]
+    fn prints_delete_messages_correctly() {
+        let task = SendTask::DeleteMessage("channel123".to_string(), "abc-123".to_string());
+        assert_eq!(task.to_string(), "PRIVMSG #channel123 :/delete abc-123");
+    }
+
+    #[test]
+    fn prints_timeout_messages_correctly() {
+        let task = SendTask::Timeout(
+            "channel123".to_string(),
+            "user123".to_string(),
+            600,
+            Some("Banned phrase".to_string()),
+        );
+        assert_eq!(
+            task.to_string(),
+            "PRIVMSG #channel123 :/timeout user123 600 Banned phrase"
+        );
+    }
+
+    #[test]
+    fn prints_ban_messages_correctly() {
+        let task = SendTask::Ban("channel123".to_string(), "user123".to_string(), None);
+        assert_eq!(task.to_string(), "PRIVMSG #channel123 :/ban user123");
+    }
+
+    #[test]
+    fn prints_unban_messages_correctly() {
+        let task = SendTask::Unban("channel123".to_string(), "user123".to_string());
+        assert_eq!(task.to_string(), "PRIVMSG #channel123 :/unban user123");
+    }
+
+    #[test]
+    fn prints_chat_mode_messages_correctly() {
+        let channel = || "channel123".to_string();
+        assert_eq!(
+            SendTask::SlowMode(channel(), Some(30)).to_string(),
+            "PRIVMSG #channel123 :/slow 30"
+        );
+        assert_eq!(
+            SendTask::SlowMode(channel(), None).to_string(),
+            "PRIVMSG #channel123 :/slowoff"
+        );
+        assert_eq!(
+            SendTask::FollowersOnly(channel(), Some(10)).to_string(),
+            "PRIVMSG #channel123 :/followers 10m"
+        );
+        assert_eq!(
+            SendTask::FollowersOnly(channel(), None).to_string(),
+            "PRIVMSG #channel123 :/followersoff"
+        );
+        assert_eq!(
+            SendTask::EmoteOnly(channel(), true).to_string(),
+            "PRIVMSG #channel123 :/emoteonly"
+        );
+        assert_eq!(
+            SendTask::EmoteOnly(channel(), false).to_string(),
+            "PRIVMSG #channel123 :/emoteonlyoff"
+        );
+        assert_eq!(
+            SendTask::ClearChat(channel()).to_string(),
+            "PRIVMSG #channel123 :/clear"
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use super::connector::twitch_chat::send::SendTask;
-use std::sync::mpsc;
+use std::{sync::mpsc, time::Duration};
 use thiserror::Error;
 use websocket::websocket_base;
 
@@ -15,6 +15,12 @@ pub enum ConnectorError {
     HTTP404,
     #[error("Http status 403: forbidden: {0:?}")]
     HTTP403(String),
+    #[error("Helix request failed with http status {0}: {1}")]
+    HelixRequestFailed(u16, String),
+    #[error("Rate limit exceeded, it resets in {0:?}")]
+    RateLimited(Duration),
+    #[error("EventSub connection failed: {0}")]
+    EventSubFailed(String),
     #[error("No stored value available: {0}")]
     StoredValueNotAvailable(String),
     // Errors for other crates
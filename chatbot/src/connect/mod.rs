@@ -2,5 +2,8 @@ mod connector;
 mod error;
 mod types;
 
-pub use connector::TwitchChatConnector;
-pub use types::{Badge, ChatBotEvent, Command, CommandType, TextMessage, UserInfo};
+pub use connector::{AccessTokenDispenser, EventSubConnector, HelixClient, TwitchChatConnector};
+pub use types::{
+    Badge, ChannelInfo, ChatBotEvent, Command, Emote, ModerationAction, RoomState, TextMessage,
+    TwitchRequest, TwitchResponse, UserInfo, UserRole,
+};
@@ -0,0 +1,9 @@
+/// Public information about a twitch channel, as reported by the Helix API
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelInfo {
+    pub login: String,
+    pub display_name: String,
+    // name of the game last played, empty if the channel never set one
+    pub game_name: String,
+    pub title: String,
+}
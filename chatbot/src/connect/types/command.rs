@@ -1,21 +1,31 @@
-use super::user_info::UserInfo;
+use super::{
+    text_message::{Emote, TextMessage},
+    user_info::UserInfo,
+};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum CommandType {
-    Help,
-    Info,
-    NewCommand,
-    RemoveCommand,
-    Slap,
-    Discord,
-    Dynamic(String),
-    NewRepeating,
-    RemoveRepeating,
-}
-
-#[derive(Debug, PartialEq, Eq)]
+/// A message starting with '!', which commands of the bot are registered is decided by the bot
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct Command {
-    pub kind: CommandType,
+    // the name as written in chat (without the '!')
+    pub name: String,
     pub options: Vec<String>,
     pub user: UserInfo,
+    // id of the message as provided by the "id" tag, needed to delete the message
+    pub id: String,
+    // the whole message as written in chat, checked by the moderation like any other message
+    pub text: String,
+    pub emotes: Vec<Emote>,
+}
+
+impl Command {
+    /// The command as the text message it was sent as
+    pub fn text_message(&self) -> TextMessage {
+        TextMessage {
+            id: self.id.to_owned(),
+            text: self.text.to_owned(),
+            user: self.user.clone(),
+            emotes: self.emotes.clone(),
+            ..Default::default()
+        }
+    }
 }
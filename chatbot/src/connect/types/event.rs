@@ -1,6 +1,8 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
 use uuid::Uuid;
 
-use super::{text_message::TextMessage, Command};
+use super::{text_message::TextMessage, Command, RoomState, TwitchRequest, TwitchResponse};
 
 #[derive(Debug, PartialEq)]
 pub enum ChatBotEvent {
@@ -12,4 +14,51 @@ pub enum ChatBotEvent {
     // uuid is the message id, used to deduplicate
     // messages when a command is redefined
     TimedMessage(String, Uuid),
+    // a moderator (or the bot) deleted a message
+    MessageDeleted {
+        user_name: String,
+        message_id: String,
+        text: String,
+    },
+    // String is the name of the banned user
+    UserBanned(String),
+    UserTimedOut(String, Duration),
+    // whether the bot itself is a moderator (or the broadcaster) of the channel,
+    // sent by twitch when the bot joins and after each message the bot sends
+    BotModeratorStatus(bool),
+    // the chat modes of the channel changed, or the bot joined the channel
+    RoomState(RoomState),
+    // another channel raided this channel, user_name is the login of the raiding channel
+    Raid {
+        user_name: String,
+        viewers: u64,
+    },
+    // the following events arrive through EventSub instead of the chat
+    // a user followed the channel, user_name is the login of the follower
+    Follow {
+        user_name: String,
+    },
+    // a viewer redeemed a channel point reward, id and reward_id identify the redemption
+    // to fulfill or refund it
+    Redemption {
+        id: String,
+        reward_id: String,
+        reward_title: String,
+        user_name: String,
+        input: String,
+    },
+    HypeTrainStarted {
+        level: u64,
+    },
+    HypeTrainEnded {
+        level: u64,
+    },
+    StreamOnline {
+        started_at: DateTime<Utc>,
+    },
+    StreamOffline,
+    // timer sends a message to the bot when the VIP status a user redeemed runs out
+    VipExpired(String),
+    // answer to ChatBotCommand::CallTwitch
+    TwitchResponse(TwitchRequest, TwitchResponse),
 }
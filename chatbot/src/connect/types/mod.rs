@@ -1,9 +1,17 @@
+mod channel_info;
 mod command;
 mod event;
+mod moderation_action;
+mod room_state;
 mod text_message;
+mod twitch_request;
 mod user_info;
 
-pub use command::{Command, CommandType};
+pub use channel_info::ChannelInfo;
+pub use command::Command;
 pub use event::ChatBotEvent;
-pub use text_message::TextMessage;
-pub use user_info::{Badge, UserInfo};
+pub use moderation_action::ModerationAction;
+pub use room_state::RoomState;
+pub use text_message::{Emote, TextMessage};
+pub use twitch_request::{TwitchRequest, TwitchResponse};
+pub use user_info::{Badge, UserInfo, UserRole};
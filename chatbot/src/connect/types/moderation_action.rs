@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+/// Moderation actions the bot can perform in the channel.
+/// The bot account needs moderator status in the channel for these to have any effect.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModerationAction {
+    // String is the id of the message to delete
+    DeleteMessage(String),
+    Timeout {
+        user: String,
+        duration: Duration,
+        reason: Option<String>,
+    },
+    Ban {
+        user: String,
+        reason: Option<String>,
+    },
+    Unban(String),
+    // None turns slow mode off
+    SlowMode(Option<Duration>),
+    // only followers who have been following for the duration may chat, None turns it off
+    FollowersOnly(Option<Duration>),
+    EmoteOnly(bool),
+    ClearChat,
+}
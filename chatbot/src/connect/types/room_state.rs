@@ -0,0 +1,10 @@
+use std::time::Duration;
+
+/// Chat modes of the channel. Twitch sends all modes when the bot joins
+/// and only the changed mode afterwards, modes missing from an update are None.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoomState {
+    // Some(None) if followers-only mode is off
+    pub followers_only: Option<Option<Duration>>,
+    pub emote_only: Option<bool>,
+}
@@ -1,7 +1,40 @@
 use super::UserInfo;
 
-#[derive(Debug, PartialEq)]
+/// An emote used in a text message.
+/// start and end are the (inclusive) character positions of the emote in the message text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emote {
+    pub id: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct TextMessage {
+    // id of the message as provided by the "id" tag, needed to delete the message
+    pub id: String,
     pub text: String,
     pub user: UserInfo,
+    pub emotes: Vec<Emote>,
+    // whether this is the user's first message in the channel ("first-msg" tag)
+    pub first_message: bool,
+    // bits cheered with the message ("bits" tag)
+    pub bits: u64,
+}
+
+impl TextMessage {
+    /// The message text without the emotes
+    pub fn text_without_emotes(&self) -> String {
+        self.text
+            .chars()
+            .enumerate()
+            .filter(|(index, _)| {
+                !self
+                    .emotes
+                    .iter()
+                    .any(|emote| emote.start <= *index && *index <= emote.end)
+            })
+            .map(|(_, character)| character)
+            .collect()
+    }
 }
@@ -0,0 +1,45 @@
+use super::ChannelInfo;
+use chrono::{DateTime, Utc};
+
+/// Questions and changes the bot sends to the Twitch API, user names are logins
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TwitchRequest {
+    // information about another channel
+    ChannelInfo(String),
+    // title and game of the bot's channel
+    StreamInfo,
+    Uptime,
+    SetTitle(String),
+    // the name of the game, which does not have to be exact
+    SetGame(String),
+    FollowAge(String),
+    AccountAge(String),
+    // marks a channel point redemption as fulfilled, or cancels it and refunds the points
+    UpdateRedemption {
+        id: String,
+        reward_id: String,
+        fulfilled: bool,
+    },
+    AddVip(String),
+    RemoveVip(String),
+}
+
+/// Answers to a TwitchRequest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TwitchResponse {
+    ChannelInfo(ChannelInfo),
+    Live { started_at: DateTime<Utc> },
+    Offline,
+    TitleChanged(String),
+    // the name of the game as twitch knows it
+    GameChanged(String),
+    FollowedAt(DateTime<Utc>),
+    NotFollowing,
+    AccountCreatedAt(DateTime<Utc>),
+    // the change was made
+    Done,
+    // the requested user, channel or game does not exist
+    NotFound,
+    // the API could not be reached or refused the request
+    Failed(String),
+}
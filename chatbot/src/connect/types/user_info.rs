@@ -1,12 +1,34 @@
+use serde::Deserialize;
 use std::collections::HashSet;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Badge {
     pub name: String,
     pub level: u16,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Roles a user can have in the channel, derived from the user's badges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    Broadcaster,
+    Moderator,
+    Vip,
+    Subscriber,
+}
+
+impl UserRole {
+    fn badge_names(&self) -> &'static [&'static str] {
+        match self {
+            UserRole::Broadcaster => &["broadcaster"],
+            UserRole::Moderator => &["moderator"],
+            UserRole::Vip => &["vip"],
+            UserRole::Subscriber => &["subscriber", "founder"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct UserInfo {
     pub name: String,
     pub badges: HashSet<Badge>,
@@ -14,8 +36,16 @@ pub struct UserInfo {
 
 impl UserInfo {
     pub fn has_elevated_rights(&self) -> bool {
+        self.has_role(UserRole::Broadcaster) || self.has_role(UserRole::Moderator)
+    }
+
+    pub fn has_role(&self, role: UserRole) -> bool {
         self.badges
             .iter()
-            .any(|badge| badge.name == "broadcaster" || badge.name == "moderator")
+            .any(|badge| role.badge_names().contains(&badge.name.as_str()))
+    }
+
+    pub fn has_any_role(&self, roles: &[UserRole]) -> bool {
+        roles.iter().any(|role| self.has_role(*role))
     }
 }
@@ -0,0 +1,301 @@
+use super::{redemptions::RewardAction, responders::AutoResponder, Storage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const AUDIT_BUCKET: &str = "audit_log";
+const AUDIT_SETTINGS_BUCKET: &str = "audit_log_settings";
+// the id of the next entry, the API records changes from another thread
+const NEXT_ID_KEY: &str = "next_id";
+
+/// A repeating message as it is recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepeatingSettings {
+    pub seconds: u64,
+    pub text: String,
+}
+
+/// A value changed by a moderator, `None` means it did not exist (anymore)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    Command {
+        name: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    Alias {
+        alias: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    Repeating {
+        name: String,
+        old: Option<RepeatingSettings>,
+        new: Option<RepeatingSettings>,
+    },
+    Reward {
+        title: String,
+        old: Option<RewardAction>,
+        new: Option<RewardAction>,
+    },
+    Responder {
+        name: String,
+        old: Option<AutoResponder>,
+        new: Option<AutoResponder>,
+    },
+}
+
+impl Change {
+    /// The change that restores the old value
+    pub fn reverted(&self) -> Change {
+        match self.to_owned() {
+            Change::Command { name, old, new } => Change::Command {
+                name,
+                old: new,
+                new: old,
+            },
+            Change::Alias { alias, old, new } => Change::Alias {
+                alias,
+                old: new,
+                new: old,
+            },
+            Change::Repeating { name, old, new } => Change::Repeating {
+                name,
+                old: new,
+                new: old,
+            },
+            Change::Reward { title, old, new } => Change::Reward {
+                title,
+                old: new,
+                new: old,
+            },
+            Change::Responder { name, old, new } => Change::Responder {
+                name,
+                old: new,
+                new: old,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub time: DateTime<Utc>,
+    pub user: String,
+    // the command which made the changes
+    pub action: String,
+    pub changes: Vec<Change>,
+    // the id of the entry this one undid
+    pub reverts: Option<u64>,
+}
+
+/// Records who changed the custom commands, aliases, repeating messages,
+/// reward bindings and auto responders.
+/// Entries are never changed or removed, undoing a change adds an entry reverting it.
+#[derive(Debug)]
+pub struct AuditLog {
+    storage: Storage,
+    // the first entry recorded since the bot started
+    first_id: u64,
+}
+
+impl AuditLog {
+    pub fn new(storage: Storage) -> Self {
+        let first_id = audit_log(&storage)
+            .unwrap_or_else(|err| {
+                println!("Could not load audit log: {:?}", err);
+                Vec::default()
+            })
+            .last()
+            .map_or(0, |entry| entry.id + 1);
+        Self { storage, first_id }
+    }
+
+    fn entries(&self) -> Vec<AuditEntry> {
+        audit_log(&self.storage).unwrap_or_else(|err| {
+            println!("Could not load audit log: {:?}", err);
+            Vec::default()
+        })
+    }
+
+    /// Appends the changes to the log, nothing is recorded without changes
+    pub fn record(
+        &mut self,
+        user_name: &str,
+        action: &str,
+        changes: Vec<Change>,
+        reverts: Option<u64>,
+        now: DateTime<Utc>,
+    ) {
+        if changes.is_empty() {
+            return;
+        }
+        // logs of older versions have no stored id yet
+        let next_id = self.storage.update(
+            AUDIT_SETTINGS_BUCKET,
+            NEXT_ID_KEY,
+            |next_id: Option<u64>| next_id.unwrap_or(self.first_id) + 1,
+        );
+        let id = match next_id {
+            Ok(next_id) => next_id - 1,
+            Err(err) => {
+                println!("Could not save audit entry: {:?}", err);
+                return;
+            }
+        };
+        let entry = AuditEntry {
+            id,
+            time: now,
+            user: user_name.to_owned(),
+            action: action.to_owned(),
+            changes,
+            reverts,
+        };
+        // zero padded ids keep the keys in order
+        if let Err(err) = self
+            .storage
+            .set(AUDIT_BUCKET, &format!("{:020}", entry.id), entry)
+        {
+            println!("Could not save audit entry: {:?}", err);
+        }
+    }
+
+    /// The last change of the user which has not been undone yet.
+    /// Only changes since the bot started count, the custom commands and repeating messages
+    /// of earlier runs are gone.
+    pub fn last_change(&self, user_name: &str) -> Option<AuditEntry> {
+        let entries = self.entries();
+        let reverted: Vec<u64> = entries.iter().filter_map(|entry| entry.reverts).collect();
+        entries.into_iter().rev().find(|entry| {
+            entry.id >= self.first_id
+                && entry.user.eq_ignore_ascii_case(user_name)
+                && entry.reverts.is_none()
+                && !reverted.contains(&entry.id)
+        })
+    }
+
+    /// The entries which changed the custom command, newest first
+    pub fn command_history(&self, command_name: &str) -> Vec<AuditEntry> {
+        let mut entries: Vec<AuditEntry> = self
+            .entries()
+            .into_iter()
+            .filter(|entry| {
+                entry.changes.iter().any(
+                    |change| matches!(change, Change::Command { name, .. } if name == command_name),
+                )
+            })
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
+
+/// All entries of the audit log, oldest first
+pub fn audit_log(storage: &Storage) -> Result<Vec<AuditEntry>, kv::Error> {
+    Ok(storage
+        .entries::<AuditEntry>(AUDIT_BUCKET)?
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn command_change(name: &str, old: Option<&str>, new: Option<&str>) -> Change {
+        Change::Command {
+            name: name.to_owned(),
+            old: old.map(str::to_owned),
+            new: new.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn finding_the_last_change_to_undo() {
+        let storage = Storage::temporary().unwrap();
+        let mut audit = AuditLog::new(storage.clone());
+        audit.record("mod", "newcommand", Vec::default(), None, now());
+        audit.record(
+            "mod",
+            "newcommand",
+            vec![command_change("hello", None, Some("Hi!"))],
+            None,
+            now(),
+        );
+        audit.record(
+            "Mod",
+            "editcommand",
+            vec![command_change("hello", Some("Hi!"), Some("Hey!"))],
+            None,
+            now(),
+        );
+        audit.record(
+            "other",
+            "removecommand",
+            vec![command_change("bye", Some("Bye!"), None)],
+            None,
+            now(),
+        );
+        assert_eq!(audit.last_change("mod").unwrap().id, 1);
+
+        // undoing adds an entry, the undone change is skipped afterwards
+        audit.record(
+            "mod",
+            "undo",
+            vec![command_change("hello", Some("Hey!"), Some("Hi!"))],
+            Some(1),
+            now(),
+        );
+        assert_eq!(audit.last_change("mod").unwrap().id, 0);
+        assert_eq!(audit.command_history("hello").len(), 3);
+        assert_eq!(
+            audit.command_history("bye")[0].changes,
+            vec![command_change("bye", Some("Bye!"), None)]
+        );
+
+        // the ids continue after a restart, earlier changes cannot be undone anymore
+        let mut audit = AuditLog::new(storage.clone());
+        assert!(audit.last_change("mod").is_none());
+        audit.record(
+            "mod",
+            "removecommand",
+            vec![command_change("hello", Some("Hi!"), None)],
+            None,
+            now(),
+        );
+        assert_eq!(audit.last_change("mod").unwrap().id, 4);
+        assert_eq!(audit_log(&storage).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn logs_sharing_the_storage_use_distinct_ids() {
+        let storage = Storage::temporary().unwrap();
+        let mut bot_audit = AuditLog::new(storage.clone());
+        let mut api_audit = AuditLog::new(storage.clone());
+        let change = || vec![command_change("hello", None, Some("Hi!"))];
+        bot_audit.record("mod", "newcommand", change(), None, now());
+        api_audit.record("api", "PUT /responders", change(), None, now());
+        bot_audit.record("mod", "newcommand", change(), None, now());
+        let ids: Vec<u64> = audit_log(&storage)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn reverting_changes() {
+        assert_eq!(
+            command_change("hello", None, Some("Hi!")).reverted(),
+            command_change("hello", Some("Hi!"), None)
+        );
+    }
+}
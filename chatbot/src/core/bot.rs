@@ -2,18 +2,58 @@
 
 use uuid::Uuid;
 
-use super::ChatBotCommand;
-use crate::connect::{ChatBotEvent, Command, CommandType};
+use super::{
+    audit::AuditLog,
+    chat_log::{ChatLog, LogEntry, LogEvent, LogQuery},
+    cheers::Cheers,
+    command_registry::{CommandHandler, CommandRegistry, Dispatch, Permission, RegistryError},
+    moderation::ModerationPipeline,
+    plugins::{PluginEvent, PluginHost},
+    redemptions::{Redemption, Redemptions},
+    responders::AutoResponders,
+    scripts::{checked_scripts, script_source, ScriptEngine, SCRIPT_FLAG},
+    shoutout::Shoutouts,
+    stats::ViewerStats,
+    stream_info::StreamInfo,
+    time_format::human_duration,
+    welcome::{Greeter, Greeting},
+    BotConfig, BotConfigError, ChatBotCommand, Messages, Storage,
+};
+use crate::connect::{
+    ChatBotEvent, Command, ModerationAction, TwitchRequest, TwitchResponse, UserInfo,
+};
+use builtins::builtins;
+use chrono::Utc;
 use std::{
     collections::{HashMap, HashSet},
-    time::Duration,
+    fmt::Display,
+    rc::Rc,
+    time::{Duration, Instant, SystemTime},
 };
 
+mod builtins;
+
 #[derive(Debug)]
 pub struct ChatBot {
+    commands: CommandRegistry,
     chatters: HashSet<String>, // NOTE: probably replace String with a User struct when we need it.
     dynamic_commands: HashMap<String, String>,
     repeating_messages: HashMap<String, RepeatingMessage>,
+    moderation: ModerationPipeline,
+    // whether the bot may moderate the channel, reported by twitch
+    is_moderator: bool,
+    chat_log: ChatLog,
+    stats: ViewerStats,
+    greeter: Greeter,
+    shoutouts: Shoutouts,
+    stream_info: StreamInfo,
+    redemptions: Redemptions,
+    cheers: Cheers,
+    audit: AuditLog,
+    responders: AutoResponders,
+    scripts: ScriptEngine,
+    plugins: PluginHost,
+    messages: Messages,
 }
 
 #[derive(Debug)]
@@ -24,157 +64,631 @@ struct RepeatingMessage {
     timer_id: Uuid,
 }
 
-const HELP_MESSAGE: &str =
-    "!help: Show this help | !info: Show some information about the chat bot";
-const INFO_MESSAGE: &str =
-    "Hello, my name is TwitchBotanist. I am a twitch chat bot written in Rust. My source code is on GitHub (https://github.com/CaptainCallback/TwitchBotanist). If you want to know what you can ask me, write '!help' into the chat!";
-const NEW_COMMAND_SUCCESSFUL_MESSAGE: &str = "The new command has been defined successfully.";
-const NEW_COMMAND_NO_OPTION_MESSAGE: &str =
-    "newcommand requires at least two options but less were given.";
-const REMOVE_COMMAND_NO_OPTION_MESSAGE: &str =
-    "removecommand requires at least one option but none was given.";
-const REMOVE_COMMAND_SUCCESSFUL_MESSAGE: &str = "The command has been removed successfully.";
-const DENIED_MESSAGE: &str = "Denied: i ought to !slap you...";
-const DISCORD_MESSAGE: &str =
-    "You can join me on discord for news and updates here: https://discord.gg/qM6DTTQxDV";
-
-fn str_msg(string: &str) -> Option<ChatBotCommand> {
-    Some(ChatBotCommand::SendMessage(string.to_string()))
+// twitch does not send longer chat messages
+const MAX_MESSAGE_LENGTH: usize = 500;
+// leaves room for the header and footer of a help page
+const HELP_PAGE_LENGTH: usize = MAX_MESSAGE_LENGTH - 60;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+const NUKE_TIMEOUT: Duration = Duration::from_secs(600);
+const DEFAULT_LOG_COUNT: usize = 5;
+const MAX_LOG_COUNT: usize = 10;
+// how dates and times are shown in chat
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+// Joins the entries into pages that are at most max_length long, an entry never is split
+fn pages(entries: &[String], max_length: usize) -> Vec<String> {
+    let mut pages: Vec<String> = Vec::default();
+    for entry in entries {
+        match pages.last_mut() {
+            Some(page) if page.len() + 2 + entry.len() <= max_length => {
+                page.push_str(", ");
+                page.push_str(entry);
+            }
+            _ => pages.push(entry.to_owned()),
+        }
+    }
+    pages
+}
+
+fn moderate(action: ModerationAction) -> Option<ChatBotCommand> {
+    Some(ChatBotCommand::Moderate(action))
+}
+
+// Joins the remaining options to a reason, no options means no reason
+fn reason(options: &[String]) -> Option<String> {
+    Some(options.join(" ")).filter(|reason| !reason.is_empty())
 }
 
 impl ChatBot {
+    /// A bot with the default config, temporary storage and a temporary chat log
+    #[cfg(test)]
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self {
+        let storage = Storage::temporary().expect("Could not create temporary storage");
+        let chat_log = ChatLog::temporary().expect("Could not create temporary chat log");
+        Self::with_config(
+            &BotConfig::default(),
+            Messages::default(),
+            storage,
+            chat_log,
+        )
+        .expect("Default config is valid")
+    }
+
+    pub fn with_config(
+        config: &BotConfig,
+        messages: Messages,
+        storage: Storage,
+        chat_log: ChatLog,
+    ) -> Result<Self, BotConfigError> {
+        let mut bot = Self {
+            commands: CommandRegistry::new(&config.commands, storage.clone()),
             chatters: HashSet::default(),
             dynamic_commands: HashMap::default(),
             repeating_messages: HashMap::default(),
+            moderation: ModerationPipeline::new(
+                &config.moderation,
+                storage.clone(),
+                messages.clone(),
+            )?,
+            redemptions: Redemptions::new(&config.redemptions, storage.clone(), messages.clone()),
+            cheers: Cheers::new(
+                &config.cheers,
+                storage.clone(),
+                messages.clone(),
+                Utc::now(),
+            ),
+            audit: AuditLog::new(storage.clone()),
+            responders: AutoResponders::new(&config.responders, storage.clone())?,
+            scripts: ScriptEngine::new(&config.scripts, storage.clone()),
+            plugins: PluginHost::new(&config.plugins, storage.clone()),
+            is_moderator: false,
+            chat_log,
+            stats: ViewerStats::new(&config.stats, storage, Utc::now()),
+            greeter: Greeter::new(&config.welcome),
+            shoutouts: Shoutouts::new(&config.shoutout, messages.clone()),
+            stream_info: StreamInfo::new(&config.stream_info, messages.clone()),
+            messages,
+        };
+        for builtin in builtins(&bot.messages) {
+            bot.register_command(Rc::new(builtin))
+                .expect("The names of the built-in commands are unique");
+        }
+        for (name, source) in checked_scripts(&config.scripts, &bot.scripts)? {
+            bot.dynamic_commands
+                .insert(name, format!("{} {}", SCRIPT_FLAG, source));
         }
+        bot.plugins.reload(Instant::now());
+        Ok(bot)
     }
 
-    fn handle_command(&mut self, command: Command) -> Option<ChatBotCommand> {
-        println!("Executing this command: {:#?}", command);
-        use ChatBotCommand::*;
-        match command.kind {
-            CommandType::Discord => str_msg(DISCORD_MESSAGE),
-            CommandType::Help => str_msg(HELP_MESSAGE),
-            CommandType::Info => str_msg(INFO_MESSAGE),
-            CommandType::Slap => {
-                println!("Slapping one of these guys \n{:#?}", self.chatters);
-                // Notice how we can now do everything in a single expression
-                // because we removed the IO from this place
-                let slapping_user = command.user.name;
-                println!("This guy specifically : {}", &slapping_user);
-                command
-                    .options
-                    .get(0)
-                    .and_then(|slapped_user| self.chatters.get(slapped_user))
-                    .map(|slapped_user| {
-                        SendMessage(format!(
-                            "{} slaps {} around a bit with a large trout",
-                            slapping_user, slapped_user
-                        ))
-                    })
+    /// Adds a command to the bot, its name and aliases must not be taken yet
+    pub fn register_command(
+        &mut self,
+        handler: Rc<dyn CommandHandler>,
+    ) -> Result<(), RegistryError> {
+        self.commands.register(handler)
+    }
+
+    // The text as an answer in chat
+    fn reply(&self, key: &str, values: &[(&str, &dyn Display)]) -> Option<ChatBotCommand> {
+        Some(ChatBotCommand::SendMessage(
+            self.messages.format(key, values),
+        ))
+    }
+
+    fn duration(&self, duration: Duration) -> String {
+        human_duration(duration, &self.messages)
+    }
+
+    fn log_event(&mut self, event: &ChatBotEvent) {
+        let (user, event) = match event {
+            ChatBotEvent::TextMessage(message) => (
+                &message.user.name,
+                LogEvent::Message {
+                    id: message.id.to_owned(),
+                    text: message.text.to_owned(),
+                    badges: message
+                        .user
+                        .badges
+                        .iter()
+                        .map(|badge| badge.name.to_owned())
+                        .collect(),
+                    first_message: message.first_message,
+                },
+            ),
+            ChatBotEvent::Command(command) => (
+                &command.user.name,
+                LogEvent::Command {
+                    name: self.commands.command_name(&command.name).to_owned(),
+                    options: command.options.to_owned(),
+                },
+            ),
+            ChatBotEvent::Join(user) => (user, LogEvent::Join),
+            ChatBotEvent::Part(user) => (user, LogEvent::Part),
+            ChatBotEvent::MessageDeleted {
+                user_name,
+                message_id,
+                text,
+            } => (
+                user_name,
+                LogEvent::Deleted {
+                    id: message_id.to_owned(),
+                    text: text.to_owned(),
+                },
+            ),
+            ChatBotEvent::UserBanned(user) => (user, LogEvent::Ban),
+            ChatBotEvent::UserTimedOut(user, duration) => (
+                user,
+                LogEvent::Timeout {
+                    seconds: duration.as_secs(),
+                },
+            ),
+            ChatBotEvent::Raid { user_name, viewers } => {
+                (user_name, LogEvent::Raid { viewers: *viewers })
             }
-            CommandType::NewCommand => {
-                if command.user.has_elevated_rights() {
-                    if command.options.len() < 2 {
-                        str_msg(NEW_COMMAND_NO_OPTION_MESSAGE)
-                    } else {
-                        let new_command_name = &command.options[0];
-                        let new_command_message = command.options[1..].join(" ");
-                        self.dynamic_commands
-                            .insert(new_command_name.to_owned(), new_command_message);
-                        str_msg(NEW_COMMAND_SUCCESSFUL_MESSAGE)
-                    }
-                } else {
-                    str_msg(DENIED_MESSAGE)
-                }
+            ChatBotEvent::TimedMessage(..)
+            | ChatBotEvent::BotModeratorStatus(_)
+            | ChatBotEvent::RoomState(_)
+            | ChatBotEvent::TwitchResponse(..)
+            | ChatBotEvent::Follow { .. }
+            | ChatBotEvent::Redemption { .. }
+            | ChatBotEvent::HypeTrainStarted { .. }
+            | ChatBotEvent::HypeTrainEnded { .. }
+            | ChatBotEvent::StreamOnline { .. }
+            | ChatBotEvent::StreamOffline
+            | ChatBotEvent::VipExpired(_) => return,
+        };
+        let entry = LogEntry {
+            time: Utc::now(),
+            user: user.to_owned(),
+            event,
+        };
+        if let Err(err) = self.chat_log.append(&entry) {
+            println!("Could not write chat log: {:?}", err);
+        }
+    }
+
+    fn record_stats(&mut self, event: &ChatBotEvent) {
+        let now = Utc::now();
+        match event {
+            ChatBotEvent::TextMessage(message) => self.stats.message(&message.user.name, now),
+            ChatBotEvent::Command(command) => {
+                let command_name = self.commands.command_name(&command.name);
+                self.stats.command(&command.user.name, command_name, now)
             }
-            CommandType::RemoveCommand => {
-                if command.user.has_elevated_rights() {
-                    if command.options.is_empty() {
-                        str_msg(REMOVE_COMMAND_NO_OPTION_MESSAGE)
+            ChatBotEvent::Join(user) => self.stats.joined(user, now),
+            ChatBotEvent::Part(user) => self.stats.parted(user, now),
+            _ => {}
+        }
+    }
+
+    // Decided by the statistics from before the bot started, a join of a first time chatter
+    // is recorded before the first message
+    fn greeting(&self, event: &ChatBotEvent) -> Option<Greeting> {
+        let message = match event {
+            ChatBotEvent::TextMessage(message) => message,
+            _ => return None,
+        };
+        let now = Utc::now();
+        let previous = self.stats.earlier_user_stats(&message.user.name);
+        self.greeter
+            .greeting(message, previous.as_ref(), self.stats.is_enabled(), now)
+    }
+
+    fn welcome(&mut self, greeting: Greeting, user_name: &str) -> Option<ChatBotCommand> {
+        let streak = self
+            .stats
+            .user_stats(user_name, Utc::now())
+            .map_or(0, |stats| stats.stream_streak);
+        self.greeter
+            .welcome(greeting, user_name, streak, Instant::now())
+            .map(ChatBotCommand::SendMessage)
+    }
+
+    fn not_seen_message(&self, user_name: &str) -> String {
+        self.messages.format("not_seen", &[("user", &user_name)])
+    }
+
+    fn watchtime_message(&self, user_name: &str) -> String {
+        match self.stats.user_stats(user_name, Utc::now()) {
+            Some(stats) => self.messages.format(
+                "watchtime",
+                &[
+                    ("user", &stats.user),
+                    (
+                        "duration",
+                        &self.duration(Duration::from_secs(stats.watch_time_seconds)),
+                    ),
+                ],
+            ),
+            None => self.not_seen_message(user_name),
+        }
+    }
+
+    fn first_seen_message(&self, user_name: &str) -> String {
+        let now = Utc::now();
+        match self.stats.user_stats(user_name, now) {
+            Some(stats) => self.messages.format(
+                "first_seen",
+                &[
+                    ("user", &stats.user),
+                    ("date", &stats.first_seen.format(DATE_FORMAT)),
+                    (
+                        "duration",
+                        &self.duration((now - stats.first_seen).to_std().unwrap_or_default()),
+                    ),
+                ],
+            ),
+            None => self.not_seen_message(user_name),
+        }
+    }
+
+    fn user_stats_message(&self, user_name: &str) -> String {
+        match self.stats.user_stats(user_name, Utc::now()) {
+            Some(stats) => self.messages.format(
+                "user_stats",
+                &[
+                    ("user", &stats.user),
+                    ("messages", &stats.message_count),
+                    ("commands", &stats.command_count()),
+                    (
+                        "duration",
+                        &self.duration(Duration::from_secs(stats.watch_time_seconds)),
+                    ),
+                    ("streams", &stats.streams_attended),
+                ],
+            ),
+            None => self.not_seen_message(user_name),
+        }
+    }
+
+    fn stream_stats_message(&self) -> String {
+        let stats = self.stats.stream_stats(Utc::now());
+        let top_chatters: Vec<String> = stats
+            .top_chatters
+            .iter()
+            .map(|chatter| format!("{} ({})", chatter.user, chatter.messages))
+            .collect();
+        self.messages.format(
+            "stream_stats",
+            &[
+                ("messages", &stats.message_count),
+                ("per_minute", &format!("{:.1}", stats.messages_per_minute)),
+                ("chatters", &stats.unique_chatters),
+                ("peak", &stats.peak_chatters),
+                (
+                    "top_chatters",
+                    &if top_chatters.is_empty() {
+                        self.messages.text("stream_stats.no_chatters")
                     } else {
-                        let command_name = &command.options[0];
-                        self.dynamic_commands.remove(command_name);
-                        str_msg(REMOVE_COMMAND_SUCCESSFUL_MESSAGE)
-                    }
-                } else {
-                    str_msg(DENIED_MESSAGE)
-                }
+                        top_chatters.join(", ")
+                    },
+                ),
+            ],
+        )
+    }
+
+    fn last_seen_message(&self, user_name: &str) -> String {
+        let entries = self.chat_log.search(&LogQuery {
+            user: Some(user_name.to_owned()),
+            limit: 1,
+            ..Default::default()
+        });
+        let entry = match entries {
+            Ok(entries) => match entries.into_iter().next() {
+                Some(entry) => entry,
+                None => return self.not_seen_message(user_name),
+            },
+            Err(err) => {
+                println!("Could not search chat log: {:?}", err);
+                return self.messages.text("chat_log.unavailable");
             }
+        };
+        let ago = self.duration((Utc::now() - entry.time).to_std().unwrap_or_default());
+        let activity = match entry.event {
+            LogEvent::Message { text, .. } => self
+                .messages
+                .format("last_seen.message", &[("text", &text)]),
+            LogEvent::Command { name, .. } => self.messages.format(
+                "last_seen.command",
+                &[("command", &format!("{}{}", self.commands.prefix(), name))],
+            ),
+            LogEvent::Join => self.messages.text("last_seen.join"),
+            LogEvent::Part => self.messages.text("last_seen.part"),
+            LogEvent::Deleted { .. } => self.messages.text("last_seen.deleted"),
+            LogEvent::Ban => self.messages.text("last_seen.ban"),
+            LogEvent::Timeout { .. } => self.messages.text("last_seen.timeout"),
+            LogEvent::Raid { .. } => self.messages.text("last_seen.raid"),
+        };
+        self.messages.format(
+            "last_seen",
+            &[
+                ("user", &entry.user),
+                ("duration", &ago),
+                ("activity", &activity),
+            ],
+        )
+    }
 
-            CommandType::NewRepeating => {
-                if command.user.has_elevated_rights() {
-                    if command.options.len() < 2 {
-                        // TODO: set the correct message here
-                        str_msg(NEW_COMMAND_NO_OPTION_MESSAGE)
-                    } else {
-                        let message_name = &command.options[0];
-                        if let Ok(seconds) = &command.options[1].parse() {
-                            let interval = Duration::from_secs(*seconds);
-                            let id = Uuid::new_v4();
-                            self.repeating_messages.insert(
-                                message_name.to_string(),
-                                RepeatingMessage {
-                                    name: message_name.to_string(),
-                                    text: command.options[2..].join(" "),
-                                    interval,
-                                    timer_id: id,
-                                },
-                            );
-                            // TODO: set the correct message here
-                            Some(MultipleCommands(vec![
-                                ChatBotCommand::SendMessage(
-                                    NEW_COMMAND_SUCCESSFUL_MESSAGE.to_string(),
-                                ),
-                                TimedCallback {
-                                    duration: interval,
-                                    event: ChatBotEvent::TimedMessage(message_name.to_string(), id),
-                                },
-                            ]))
-                        } else {
-                            // TODO: set the correct message here
-                            str_msg(NEW_COMMAND_NO_OPTION_MESSAGE)
-                        }
+    fn logs_message(&self, user_name: &str, count: usize) -> String {
+        let entries = self.chat_log.search(&LogQuery {
+            user: Some(user_name.to_owned()),
+            messages_only: true,
+            limit: count,
+            ..Default::default()
+        });
+        let messages: Vec<String> = match entries {
+            Ok(entries) => entries
+                .into_iter()
+                .rev()
+                .filter_map(|entry| match entry.event {
+                    LogEvent::Message { text, .. } => {
+                        Some(format!("[{}] {}", entry.time.format(TIME_FORMAT), text))
                     }
-                } else {
-                    str_msg(DENIED_MESSAGE)
-                }
+                    _ => None,
+                })
+                .collect(),
+            Err(err) => {
+                println!("Could not search chat log: {:?}", err);
+                return self.messages.text("chat_log.unavailable");
             }
+        };
+        if messages.is_empty() {
+            self.messages.format("logs.none", &[("user", &user_name)])
+        } else {
+            self.messages.format(
+                "logs",
+                &[("user", &user_name), ("messages", &messages.join(" | "))],
+            )
+        }
+    }
 
-            CommandType::RemoveRepeating => {
-                if command.user.has_elevated_rights() {
-                    if command.options.is_empty() {
-                        // TODO: set the correct message here
-                        str_msg(REMOVE_COMMAND_NO_OPTION_MESSAGE)
-                    } else {
-                        let command_name = &command.options[0];
-                        self.repeating_messages.remove(command_name);
-                        // TODO: set the correct message here
-                        str_msg(REMOVE_COMMAND_SUCCESSFUL_MESSAGE)
-                    }
-                } else {
-                    str_msg(DENIED_MESSAGE)
-                }
+    /// The built-in and dynamic commands the user may use
+    fn help_page(&self, user: &UserInfo, page: usize) -> String {
+        let prefix = self.commands.prefix();
+        let mut names: Vec<String> = self
+            .commands
+            .handlers()
+            .filter(|handler| handler.permission().allows(user))
+            .map(|handler| format!("{}{}", prefix, handler.name()))
+            .collect();
+        let mut dynamic_commands: Vec<&String> = self.dynamic_commands.keys().collect();
+        dynamic_commands.sort();
+        names.extend(
+            dynamic_commands
+                .into_iter()
+                .map(|name| format!("{}{}", prefix, name)),
+        );
+        let pages = pages(&names, HELP_PAGE_LENGTH);
+        let page = page.max(1);
+        match pages.get(page - 1) {
+            Some(commands) => self.messages.format(
+                "help.page",
+                &[
+                    ("page", &page),
+                    ("pages", &pages.len()),
+                    ("commands", commands),
+                    ("prefix", &prefix),
+                ],
+            ),
+            None => self
+                .messages
+                .format("help.no_page", &[("pages", &pages.len())]),
+        }
+    }
+
+    /// The command as it is written in chat with its options, e.g. "!ban <user> [reason]"
+    fn command_usage(&self, name: &str) -> String {
+        let prefix = self.commands.prefix();
+        match self.commands.get(name) {
+            Some(handler) if !handler.usage().is_empty() => {
+                format!("{}{} {}", prefix, handler.name(), handler.usage())
             }
+            _ => format!("{}{}", prefix, self.commands.command_name(name)),
+        }
+    }
 
-            CommandType::Dynamic(command_name) => self
+    /// Usage and description of a command
+    fn command_help(&self, name: &str) -> String {
+        let name = name.to_lowercase();
+        let prefix = self.commands.prefix();
+        let handler = match self.commands.get(&name) {
+            Some(handler) => handler,
+            None if self
                 .dynamic_commands
-                .get(&command_name)
-                .map(String::from)
-                .map(SendMessage),
+                .contains_key(self.commands.resolve(&name)) =>
+            {
+                return self.messages.format(
+                    "help.custom_command",
+                    &[(
+                        "command",
+                        &format!("{}{}", prefix, self.commands.resolve(&name)),
+                    )],
+                )
+            }
+            None => {
+                return self
+                    .messages
+                    .format("no_command", &[("command", &format!("{}{}", prefix, name))])
+            }
+        };
+        let mut help = self.messages.format(
+            "help.command",
+            &[
+                ("usage", &self.command_usage(&name)),
+                ("help", &handler.help()),
+            ],
+        );
+        if handler.permission() == Permission::Moderator {
+            help = format!("{} {}", help, self.messages.text("help.moderators_only"));
+        }
+        let aliases: Vec<String> = handler
+            .aliases()
+            .iter()
+            .copied()
+            .chain(
+                self.commands
+                    .aliases()
+                    .into_iter()
+                    .filter(|(_, command_name)| *command_name == handler.name())
+                    .map(|(alias, _)| alias),
+            )
+            .map(|alias| format!("{}{}", prefix, alias))
+            .collect();
+        if !aliases.is_empty() {
+            let aliases = self
+                .messages
+                .format("help.aliases", &[("aliases", &aliases.join(", "))]);
+            help = format!("{} {}", help, aliases);
+        }
+        help
+    }
+
+    fn strikes_message(&self, user_name: &str) -> String {
+        let now = SystemTime::now();
+        let strikes = self.moderation.strikes(user_name, now);
+        if strikes.is_empty() {
+            return self
+                .messages
+                .format("strikes.none", &[("user", &user_name)]);
+        }
+        let strike_list: Vec<String> = strikes
+            .iter()
+            .map(|strike| {
+                let age = now.duration_since(strike.time).unwrap_or_default();
+                self.messages.format(
+                    "strikes.item",
+                    &[
+                        ("reason", &strike.reason),
+                        ("duration", &self.duration(age)),
+                    ],
+                )
+            })
+            .collect();
+        self.messages.format(
+            "strikes",
+            &[
+                ("user", &user_name),
+                ("count", &strikes.len()),
+                ("strikes", &strike_list.join(" | ")),
+            ],
+        )
+    }
+
+    fn handle_command(&mut self, command: Command) -> Option<ChatBotCommand> {
+        println!("Executing this command: {:#?}", command);
+        match self.commands.dispatch(&command, Instant::now()) {
+            Dispatch::Handler(handler) => handler.handle(self, &command),
+            Dispatch::Denied => self.reply("denied", &[]),
+            Dispatch::CoolingDown => None,
+            Dispatch::Unknown => {
+                let name = self.commands.resolve(&command.name).to_owned();
+                let text = self.dynamic_commands.get(&name)?.to_owned();
+                match script_source(&text) {
+                    Some(source) => self.run_script(&name, source, &command),
+                    None => Some(ChatBotCommand::SendMessage(text)),
+                }
+            }
+        }
+    }
+
+    fn run_script(
+        &mut self,
+        name: &str,
+        source: &str,
+        command: &Command,
+    ) -> Option<ChatBotCommand> {
+        use ChatBotCommand::*;
+        let mut commands = match self
+            .scripts
+            .run(name, source, &command.user, &command.options)
+        {
+            Ok(commands) => commands,
+            Err(err) => {
+                println!("The script of {} failed: {:?}", name, err);
+                return self.reply(
+                    "script_failed",
+                    &[
+                        ("command", &format!("{}{}", self.commands.prefix(), name)),
+                        ("error", &err),
+                    ],
+                );
+            }
+        };
+        if !self.is_moderator
+            && commands
+                .iter()
+                .any(|command| matches!(command, Moderate(_)))
+        {
+            commands.retain(|command| !matches!(command, Moderate(_)));
+            commands.push(SendMessage(self.messages.text("not_moderator")));
+        }
+        match commands.len() {
+            0 => None,
+            1 => commands.pop(),
+            _ => Some(MultipleCommands(commands)),
         }
     }
 
+    /// Handles the event and passes it on to the plugins, which answer after the bot
     pub fn handle_event(&mut self, event: ChatBotEvent) -> Option<ChatBotCommand> {
+        let plugin_event = PluginEvent::from_event(&event);
+        // responders, scripts, rewards and plugins build messages from user input
+        let command = self
+            .handle_bot_event(event)
+            .map(ChatBotCommand::without_chat_commands);
+        self.plugins.reload(Instant::now());
+        let mut plugin_commands: Vec<ChatBotCommand> = match plugin_event {
+            Some(plugin_event) => self
+                .plugins
+                .handle(&plugin_event)
+                .into_iter()
+                .map(ChatBotCommand::without_chat_commands)
+                .collect(),
+            None => return command,
+        };
+        if !self.is_moderator {
+            plugin_commands.retain(|command| match command {
+                ChatBotCommand::Moderate(action) => {
+                    println!("Not a moderator, a plugin could not run {:?}", action);
+                    false
+                }
+                _ => true,
+            });
+        }
+        if plugin_commands.is_empty() {
+            return command;
+        }
+        let mut commands: Vec<ChatBotCommand> = command.into_iter().collect();
+        commands.extend(plugin_commands);
+        Some(match commands.len() {
+            1 => commands.remove(0),
+            _ => ChatBotCommand::MultipleCommands(commands),
+        })
+    }
+
+    fn handle_bot_event(&mut self, event: ChatBotEvent) -> Option<ChatBotCommand> {
         use ChatBotCommand::*;
+        let greeting = self.greeting(&event);
+        self.log_event(&event);
+        self.record_stats(&event);
         match event {
-            ChatBotEvent::Command(command) => self.handle_command(command),
+            // commands are chat messages too, a violating one is not run
+            ChatBotEvent::Command(command) => {
+                match self
+                    .moderation
+                    .check(&command.text_message(), Instant::now())
+                {
+                    Some(moderation) => Some(moderation),
+                    None => self.handle_command(command),
+                }
+            }
             ChatBotEvent::Join(user) => {
                 println!("{:?} joined", &user);
                 self.chatters.insert(user);
-                None
+                self.moderation.joined(Instant::now())
             }
             ChatBotEvent::Part(user) => {
                 println!("{:?} parted", &user);
@@ -182,7 +696,24 @@ impl ChatBot {
                 None
             }
             ChatBotEvent::TextMessage(tm) => {
-                Some(LogTextMessage(format!("{}: {}", &tm.user.name, &tm.text)))
+                let mut commands = vec![LogTextMessage(format!("{}: {}", &tm.user.name, &tm.text))];
+                if tm.bits > 0 {
+                    println!("{} cheered {} bits", &tm.user.name, tm.bits);
+                    commands.extend(self.cheers.cheer(&tm.user.name, tm.bits, Utc::now()));
+                }
+                match self.moderation.check(&tm, Instant::now()) {
+                    Some(moderation) => commands.push(moderation),
+                    None => {
+                        commands.extend(
+                            greeting.and_then(|greeting| self.welcome(greeting, &tm.user.name)),
+                        );
+                        commands.extend(self.responders.respond(&tm, Instant::now()));
+                    }
+                }
+                Some(match commands.len() {
+                    1 => commands.remove(0),
+                    _ => MultipleCommands(commands),
+                })
             }
             ChatBotEvent::TimedMessage(message_name, id) => {
                 self.repeating_messages.get(&message_name).and_then(|msg| {
@@ -199,14 +730,121 @@ impl ChatBot {
                     }
                 })
             }
+            ChatBotEvent::MessageDeleted {
+                user_name,
+                message_id,
+                text,
+            } => {
+                println!("Message of {} deleted: {}", &user_name, &text);
+                self.moderation
+                    .message_deleted(&message_id, &text, Instant::now());
+                None
+            }
+            ChatBotEvent::UserBanned(user_name) => {
+                println!("{} banned", &user_name);
+                self.moderation.user_banned(&user_name, Instant::now());
+                None
+            }
+            ChatBotEvent::UserTimedOut(user_name, duration) => {
+                println!("{} timed out for {:?}", &user_name, duration);
+                None
+            }
+            ChatBotEvent::BotModeratorStatus(is_moderator) => {
+                if is_moderator != self.is_moderator {
+                    println!("Bot moderator status: {}", is_moderator);
+                    self.is_moderator = is_moderator;
+                }
+                None
+            }
+            ChatBotEvent::RoomState(room_state) => {
+                self.moderation.room_state(&room_state);
+                None
+            }
+            ChatBotEvent::Raid { user_name, viewers } => {
+                println!("{} raided with {} viewers", &user_name, viewers);
+                self.moderation.raid_announced(viewers, Instant::now());
+                if self.shoutouts.on_raid() {
+                    self.shoutouts.shoutout(&user_name, Instant::now()).ok()
+                } else {
+                    None
+                }
+            }
+            ChatBotEvent::Follow { user_name } => {
+                println!("{} followed", &user_name);
+                None
+            }
+            ChatBotEvent::Redemption {
+                id,
+                reward_id,
+                reward_title,
+                user_name,
+                input,
+            } => {
+                println!("{} redeemed {}", &user_name, &reward_title);
+                self.redemptions.redeem(
+                    &Redemption {
+                        id: &id,
+                        reward_id: &reward_id,
+                        reward_title: &reward_title,
+                        user_name: &user_name,
+                        input: &input,
+                    },
+                    self.is_moderator,
+                    Utc::now(),
+                )
+            }
+            ChatBotEvent::VipExpired(user_name) => Some(self.redemptions.vip_expired(&user_name)),
+            ChatBotEvent::HypeTrainStarted { level } => {
+                println!("Hype train started at level {}", level);
+                None
+            }
+            ChatBotEvent::HypeTrainEnded { level } => {
+                println!("Hype train ended at level {}", level);
+                None
+            }
+            ChatBotEvent::StreamOnline { started_at } => {
+                println!("Stream went online at {}", started_at);
+                self.stats.stream_started(started_at);
+                self.cheers.stream_started(started_at);
+                let expired_vips = self.redemptions.expired_vips(Utc::now());
+                (!expired_vips.is_empty()).then_some(MultipleCommands(expired_vips))
+            }
+            ChatBotEvent::StreamOffline => {
+                println!("Stream went offline");
+                self.stats.save(Utc::now());
+                None
+            }
+            ChatBotEvent::TwitchResponse(TwitchRequest::ChannelInfo(login), response) => {
+                let info = match response {
+                    TwitchResponse::ChannelInfo(info) => Some(info),
+                    _ => None,
+                };
+                self.shoutouts.channel_info(&login, info, Instant::now())
+            }
+            ChatBotEvent::TwitchResponse(TwitchRequest::AddVip(user_name), response) => self
+                .redemptions
+                .vip_added(&user_name, &response, Utc::now()),
+            ChatBotEvent::TwitchResponse(
+                request @ (TwitchRequest::UpdateRedemption { .. } | TwitchRequest::RemoveVip(_)),
+                response,
+            ) => {
+                if response != TwitchResponse::Done {
+                    println!("Twitch could not handle {:?}: {:?}", request, response);
+                }
+                None
+            }
+            ChatBotEvent::TwitchResponse(request, response) => {
+                Some(self.stream_info.response(request, response, Instant::now()))
+            }
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::redundant_pattern_matching)]
 mod testing {
     use super::*;
-    use crate::connect::{Badge, TextMessage, UserInfo};
+    use crate::connect::{Badge, ChannelInfo, ModerationAction, TextMessage, UserInfo};
 
     // It's now easy to test without connecting
     #[test]
@@ -232,11 +870,13 @@ mod testing {
     fn test_text_message() {
         let mut bot = ChatBot::new();
         let result = bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+            id: "message-id".to_string(),
             text: "Hello".to_string(),
             user: UserInfo {
                 name: "Carkhy".to_owned(),
                 badges: HashSet::default(),
             },
+            ..Default::default()
         }));
         assert!(
             matches!(result, Some(ChatBotCommand::LogTextMessage(message)) if message == "Carkhy: Hello")
@@ -251,8 +891,9 @@ mod testing {
                 name: "CaptainCallback".to_owned(),
                 badges: HashSet::default(),
             },
-            kind: CommandType::Slap,
+            name: "slap".to_owned(),
             options: vec!["Carkhy".to_string()],
+            ..Default::default()
         }));
         assert!(matches!(result, None));
     }
@@ -266,8 +907,9 @@ mod testing {
                 name: "Carkhy".to_owned(),
                 badges: HashSet::default(),
             },
-            kind: CommandType::Slap,
+            name: "slap".to_owned(),
             options: vec!["CaptainCallback".to_string()],
+            ..Default::default()
         }));
         assert!(matches!(result, Some(ChatBotCommand::SendMessage(message))
                          if message == format!("{} slaps {} around a bit with a large trout", "Carkhy", "CaptainCallback")));
@@ -281,11 +923,12 @@ mod testing {
                 name: "CaptainCallback".to_owned(),
                 badges: HashSet::default(),
             },
-            kind: CommandType::NewCommand,
+            name: "newcommand".to_owned(),
             options: vec!["test".to_string(), "testing".to_string()],
+            ..Default::default()
         }));
         assert!(matches!(result, Some(ChatBotCommand::SendMessage(message))
-                         if message == DENIED_MESSAGE));
+                         if message == bot.messages.text("denied")));
         assert!(!bot.dynamic_commands.contains_key("test"));
     }
 
@@ -300,11 +943,12 @@ mod testing {
                     level: 1,
                 }]),
             },
-            kind: CommandType::NewCommand,
+            name: "newcommand".to_owned(),
             options: vec!["test".to_string(), "testing".to_string()],
+            ..Default::default()
         }));
         assert!(matches!(result, Some(ChatBotCommand::SendMessage(message))
-                         if message != DENIED_MESSAGE));
+                         if message != bot.messages.text("denied")));
         assert!(bot.dynamic_commands.contains_key("test"));
     }
 
@@ -319,11 +963,1109 @@ mod testing {
                     level: 1,
                 }]),
             },
-            kind: CommandType::NewCommand,
+            name: "newcommand".to_owned(),
             options: vec!["test2".to_string(), "testing2".to_string()],
+            ..Default::default()
         }));
         assert!(matches!(result, Some(ChatBotCommand::SendMessage(message))
-                         if message != DENIED_MESSAGE));
+                         if message != bot.messages.text("denied")));
         assert!(bot.dynamic_commands.contains_key("test2"));
     }
+
+    #[test]
+    fn moderating_text_messages() {
+        let config: BotConfig =
+            serde_json::from_str(r#"{"moderation": {"banned_phrases": {"phrases": ["trout"]}}}"#)
+                .unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let result = bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+            id: "message-id".to_string(),
+            text: "Have a TR0UT".to_string(),
+            user: UserInfo {
+                name: "Carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }));
+        assert!(
+            matches!(result, Some(ChatBotCommand::MultipleCommands(commands))
+                         if matches!(&commands[..], [
+                             ChatBotCommand::LogTextMessage(_),
+                             ChatBotCommand::Moderate(ModerationAction::DeleteMessage(id))
+                         ] if id == "message-id"))
+        );
+    }
+
+    #[test]
+    fn moderating_commands() {
+        let config: BotConfig =
+            serde_json::from_str(r#"{"moderation": {"banned_phrases": {"phrases": ["trout"]}}}"#)
+                .unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let result = bot.handle_event(ChatBotEvent::Command(Command {
+            name: "info".to_owned(),
+            options: vec!["trout".to_owned()],
+            user: UserInfo {
+                name: "Carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            id: "message-id".to_owned(),
+            text: "!info trout".to_owned(),
+            emotes: Vec::default(),
+        }));
+        assert_eq!(
+            result,
+            Some(ChatBotCommand::Moderate(ModerationAction::DeleteMessage(
+                "message-id".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn moderating_links_in_commands() {
+        let config: BotConfig =
+            serde_json::from_str(r#"{"moderation": {"links": {"enabled": true}}}"#).unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let result = bot.handle_event(ChatBotEvent::Command(Command {
+            name: "foo".to_owned(),
+            options: vec!["https://scam.example.com".to_owned()],
+            user: UserInfo {
+                name: "Carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            id: "message-id".to_owned(),
+            text: "!foo https://scam.example.com".to_owned(),
+            emotes: Vec::default(),
+        }));
+        assert_eq!(
+            result,
+            Some(ChatBotCommand::Moderate(ModerationAction::DeleteMessage(
+                "message-id".to_owned()
+            )))
+        );
+    }
+
+    #[test]
+    fn raids_do_not_lock_down_the_chat() {
+        let config: BotConfig =
+            serde_json::from_str(r#"{"moderation": {"raid": {"enabled": true, "max_joins": 3}}}"#)
+                .unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        bot.handle_event(ChatBotEvent::Raid {
+            user_name: "friendly_streamer".to_owned(),
+            viewers: 10,
+        });
+        for index in 0..10 {
+            let result = bot.handle_event(ChatBotEvent::Join(format!("raider{}", index)));
+            assert_eq!(result, None);
+        }
+    }
+
+    #[test]
+    fn warning_with_the_reason_of_the_catalog() {
+        let config: BotConfig = serde_json::from_str(
+            r#"{"moderation": {"banned_phrases": {"phrases": ["trout"], "action": {"type": "warn"}}}}"#,
+        )
+        .unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let result = bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+            text: "Have a trout".to_string(),
+            user: UserInfo {
+                name: "Carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }));
+        assert!(matches!(
+            result,
+            Some(ChatBotCommand::MultipleCommands(commands))
+                if commands.contains(&ChatBotCommand::SendMessage(
+                    "@Carkhy Please mind your language.".to_owned()
+                ))
+        ));
+    }
+
+    #[test]
+    fn permitting_links() {
+        let config: BotConfig =
+            serde_json::from_str(r#"{"moderation": {"links": {"enabled": true}}}"#).unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let link_message = || {
+            ChatBotEvent::TextMessage(TextMessage {
+                id: "message-id".to_string(),
+                text: "see example.com".to_string(),
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            })
+        };
+        bot.handle_event(ChatBotEvent::Command(Command {
+            user: UserInfo {
+                name: "CaptainCallback".to_owned(),
+                badges: HashSet::from([Badge {
+                    name: "moderator".to_owned(),
+                    level: 1,
+                }]),
+            },
+            name: "permit".to_owned(),
+            options: vec!["@Carkhy".to_string()],
+            ..Default::default()
+        }));
+        assert!(matches!(
+            bot.handle_event(link_message()),
+            Some(ChatBotCommand::LogTextMessage(_))
+        ));
+        assert!(matches!(
+            bot.handle_event(link_message()),
+            Some(ChatBotCommand::MultipleCommands(_))
+        ));
+    }
+
+    #[test]
+    fn moderators_can_view_and_clear_strikes() {
+        let config: BotConfig = serde_json::from_str(
+            r#"{"moderation": {"banned_phrases": {"phrases": ["trout"]}, "strikes": {"enabled": true}}}"#,
+        )
+        .unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let result = bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+            id: "message-id".to_string(),
+            text: "trout".to_string(),
+            user: UserInfo {
+                name: "carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }));
+        assert!(
+            matches!(result, Some(ChatBotCommand::MultipleCommands(commands))
+                         if matches!(&commands[..], [
+                             ChatBotCommand::LogTextMessage(_),
+                             ChatBotCommand::MultipleCommands(punishment)
+                         ] if matches!(&punishment[..], [
+                             ChatBotCommand::Moderate(ModerationAction::DeleteMessage(_)),
+                             ChatBotCommand::SendMessage(warning)
+                         ] if warning.contains("strike 1"))))
+        );
+        let strikes_command = |name: &str| {
+            ChatBotEvent::Command(Command {
+                user: UserInfo {
+                    name: "CaptainCallback".to_owned(),
+                    badges: HashSet::from([Badge {
+                        name: "moderator".to_owned(),
+                        level: 1,
+                    }]),
+                },
+                name: name.to_owned(),
+                options: vec!["carkhy".to_string()],
+                ..Default::default()
+            })
+        };
+        assert!(matches!(bot.handle_event(strikes_command("strikes")),
+                         Some(ChatBotCommand::SendMessage(message)) if message.starts_with("carkhy has 1 active strike(s)")));
+        bot.handle_event(strikes_command("clearstrikes"));
+        assert!(matches!(bot.handle_event(strikes_command("strikes")),
+                         Some(ChatBotCommand::SendMessage(message)) if message == "carkhy has no active strikes."));
+    }
+
+    fn moderator_command(name: &str, options: &[&str]) -> ChatBotEvent {
+        ChatBotEvent::Command(Command {
+            user: UserInfo {
+                name: "CaptainCallback".to_owned(),
+                badges: HashSet::from([Badge {
+                    name: "moderator".to_owned(),
+                    level: 1,
+                }]),
+            },
+            name: name.to_owned(),
+            options: options.iter().map(|option| option.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn moderation_commands_require_bot_moderator_status() {
+        let mut bot = ChatBot::new();
+        assert!(matches!(
+            bot.handle_event(moderator_command("clear", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message == bot.messages.text("not_moderator")
+        ));
+        bot.handle_event(ChatBotEvent::BotModeratorStatus(true));
+        assert!(matches!(
+            bot.handle_event(moderator_command("clear", &[])),
+            Some(ChatBotCommand::Moderate(ModerationAction::ClearChat))
+        ));
+    }
+
+    #[test]
+    fn timing_out_users() {
+        let mut bot = ChatBot::new();
+        bot.handle_event(ChatBotEvent::BotModeratorStatus(true));
+        assert!(matches!(
+            bot.handle_event(moderator_command("to", &["@carkhy", "5m", "calm", "down"])),
+            Some(ChatBotCommand::Moderate(ModerationAction::Timeout { user, duration, reason }))
+                if user == "carkhy"
+                    && duration == Duration::from_secs(300)
+                    && reason.as_deref() == Some("calm down")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("to", &["carkhy", "spam"])),
+            Some(ChatBotCommand::Moderate(ModerationAction::Timeout { duration, reason, .. }))
+                if duration == DEFAULT_TIMEOUT && reason.as_deref() == Some("spam")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("slow", &["off"])),
+            Some(ChatBotCommand::Moderate(ModerationAction::SlowMode(None)))
+        ));
+    }
+
+    #[test]
+    fn nuking_a_phrase() {
+        let mut bot = ChatBot::new();
+        bot.handle_event(ChatBotEvent::BotModeratorStatus(true));
+        for (user_name, text) in [
+            ("carkhy", "the boss dies"),
+            ("viewer", "hello"),
+            ("other", "He DIES"),
+        ] {
+            bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+                text: text.to_owned(),
+                user: UserInfo {
+                    name: user_name.to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            }));
+        }
+        let result = bot.handle_event(moderator_command("nuke", &["dies", "1m"]));
+        assert!(
+            matches!(result, Some(ChatBotCommand::MultipleCommands(commands))
+                         if matches!(&commands[..], [
+                             ChatBotCommand::SendMessage(_),
+                             ChatBotCommand::Moderate(ModerationAction::Timeout { user: first, .. }),
+                             ChatBotCommand::Moderate(ModerationAction::Timeout { user: second, .. }),
+                         ] if first == "other" && second == "carkhy"))
+        );
+    }
+
+    #[test]
+    fn searching_the_chat_log() {
+        let mut bot = ChatBot::new();
+        for text in ["first", "second", "third"] {
+            bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+                text: text.to_owned(),
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            }));
+        }
+        bot.handle_event(ChatBotEvent::Part("carkhy".to_owned()));
+        assert!(matches!(
+            bot.handle_event(moderator_command("lastseen", &["@Carkhy"])),
+            Some(ChatBotCommand::SendMessage(message))
+                if message.starts_with("carkhy was last seen") && message.ends_with("leaving the chat")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("logs", &["carkhy", "2"])),
+            Some(ChatBotCommand::SendMessage(message))
+                if message.starts_with("Last messages of carkhy: [")
+                    && message.contains("second | [")
+                    && message.ends_with("third")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("lastseen", &["nobody"])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "I have not seen nobody yet."
+        ));
+    }
+
+    #[test]
+    fn welcoming_first_time_chatters() {
+        let config: BotConfig = serde_json::from_str(r#"{"welcome": {"enabled": true}}"#).unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        let message = || {
+            ChatBotEvent::TextMessage(TextMessage {
+                text: "hello".to_owned(),
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            })
+        };
+        bot.handle_event(ChatBotEvent::Join("carkhy".to_owned()));
+        assert!(
+            matches!(bot.handle_event(message()), Some(ChatBotCommand::MultipleCommands(commands))
+                         if matches!(&commands[..], [
+                             ChatBotCommand::LogTextMessage(_),
+                             ChatBotCommand::SendMessage(welcome)
+                         ] if welcome == "Welcome to the chat, carkhy!"))
+        );
+        assert!(matches!(
+            bot.handle_event(message()),
+            Some(ChatBotCommand::LogTextMessage(_))
+        ));
+    }
+
+    #[test]
+    fn shouting_out_raiders() {
+        let mut bot = ChatBot::new();
+        assert!(matches!(
+            bot.handle_event(ChatBotEvent::Raid {
+                user_name: "TwitchDev".to_owned(),
+                viewers: 15,
+            }),
+            Some(ChatBotCommand::CallTwitch(TwitchRequest::ChannelInfo(login))) if login == "twitchdev"
+        ));
+        assert!(matches!(
+            bot.handle_event(ChatBotEvent::TwitchResponse(
+                TwitchRequest::ChannelInfo("twitchdev".to_owned()),
+                TwitchResponse::ChannelInfo(ChannelInfo {
+                    login: "twitchdev".to_owned(),
+                    display_name: "TwitchDev".to_owned(),
+                    game_name: "Science & Technology".to_owned(),
+                    title: String::default(),
+                }),
+            )),
+            Some(ChatBotCommand::SendMessage(message)) if message.contains("https://twitch.tv/twitchdev")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("so", &["other"])),
+            Some(ChatBotCommand::SendMessage(message)) if message.starts_with("Shoutouts are on cooldown")
+        ));
+    }
+
+    #[test]
+    fn channel_point_rewards() {
+        let mut bot = ChatBot::new();
+        assert!(matches!(
+            bot.handle_event(moderator_command("reward", &["Add", "quote", "|", "quote"])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "The reward Add quote now adds a quote."
+        ));
+        let redeem = |bot: &mut ChatBot, reward_title: &str, input: &str| {
+            bot.handle_event(ChatBotEvent::Redemption {
+                id: "17fa2df1".to_owned(),
+                reward_id: "92af127c".to_owned(),
+                reward_title: reward_title.to_owned(),
+                user_name: "carkhy".to_owned(),
+                input: input.to_owned(),
+            })
+        };
+        assert!(matches!(
+            redeem(&mut bot, "add quote", "I am not a bot"),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands.len() == 2
+        ));
+        assert!(redeem(&mut bot, "Hydrate", "").is_none());
+        assert!(matches!(
+            bot.handle_event(moderator_command("quote", &["1"])),
+            Some(ChatBotCommand::SendMessage(message)) if message.starts_with("Quote #1: \"I am not a bot\" (added by carkhy")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("rewards", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "Rewards: Add quote adds a quote"
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("reward", &["Add", "quote", "|", "dance"])),
+            Some(ChatBotCommand::SendMessage(message)) if message.starts_with("Usage: !reward")
+        ));
+
+        // text from viewers cannot run chat commands
+        bot.handle_event(moderator_command(
+            "reward",
+            &["Say", "|", "message", "{input}"],
+        ));
+        assert!(matches!(
+            redeem(&mut bot, "Say", "/ban x"),
+            Some(ChatBotCommand::MultipleCommands(commands))
+                if commands[0] == ChatBotCommand::SendMessage("ban x".to_owned())
+        ));
+        assert!(matches!(
+            redeem(&mut bot, "Say", " .ban x"),
+            Some(ChatBotCommand::MultipleCommands(commands))
+                if commands[0] == ChatBotCommand::SendMessage("ban x".to_owned())
+        ));
+    }
+
+    #[test]
+    fn cheer_commands() {
+        let mut bot = ChatBot::new();
+        assert!(matches!(
+            bot.handle_event(moderator_command("topcheerers", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "Nobody has cheered yet."
+        ));
+        bot.handle_event(moderator_command("bitgoal", &["500"]));
+        let result = bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+            text: "Cheer500 great stream".to_owned(),
+            user: UserInfo {
+                name: "carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            bits: 500,
+            ..Default::default()
+        }));
+        assert!(matches!(
+            result,
+            Some(ChatBotCommand::MultipleCommands(commands))
+                if commands.contains(&ChatBotCommand::SendMessage(
+                    "The bit goal of 500 bits has been reached, thank you all!".to_owned()
+                ))
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("topcheerers", &["stream"])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "Top cheerers this stream: carkhy (500)"
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("bitgoal", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "Bit goal: 500/500 (100%)"
+        ));
+
+        // the stream going online starts counting the bits of a new stream
+        bot.handle_event(ChatBotEvent::StreamOnline {
+            started_at: Utc::now() + chrono::Duration::seconds(1),
+        });
+        assert!(matches!(
+            bot.handle_event(moderator_command("bitgoal", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "Bit goal: 0/500 (0%)"
+        ));
+    }
+
+    #[test]
+    fn help_lists_the_allowed_commands() {
+        let mut bot = ChatBot::new();
+        let help = |bot: &mut ChatBot, badges: HashSet<Badge>, options: &[&str]| match bot
+            .handle_event(ChatBotEvent::Command(Command {
+                name: "help".to_owned(),
+                options: options.iter().map(|option| option.to_string()).collect(),
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges,
+                },
+                ..Default::default()
+            })) {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected help {:?}", result),
+        };
+        let moderator = || {
+            HashSet::from([Badge {
+                name: "moderator".to_owned(),
+                level: 1,
+            }])
+        };
+        let viewer_help = help(&mut bot, HashSet::default(), &[]);
+        assert!(viewer_help.starts_with("Commands (1/1): !help, !info, !discord, !slap, "));
+        assert!(!viewer_help.contains("!ban"));
+        assert!(help(&mut bot, moderator(), &[]).contains("!ban"));
+
+        // dynamic commands are listed too, on as many pages as needed
+        for number in 0..40 {
+            bot.handle_event(moderator_command(
+                "newcommand",
+                &[&format!("custom{}", number), "text"],
+            ));
+        }
+        let first_page = help(&mut bot, HashSet::default(), &[]);
+        assert!(first_page.starts_with("Commands (1/2): "));
+        assert!(first_page.len() <= MAX_MESSAGE_LENGTH);
+        assert!(help(&mut bot, HashSet::default(), &["2"]).contains("!custom9"));
+        assert_eq!(
+            help(&mut bot, HashSet::default(), &["3"]),
+            "There are only 2 page(s) of commands."
+        );
+
+        assert_eq!(
+            help(&mut bot, HashSet::default(), &["!to"]),
+            "!to <user> [duration] [reason]: Times out a user. Only for moderators."
+        );
+        assert_eq!(
+            help(&mut bot, HashSet::default(), &["custom1"]),
+            "!custom1: A custom command of this channel."
+        );
+        assert_eq!(
+            help(&mut bot, HashSet::default(), &["nothing"]),
+            "There is no command !nothing."
+        );
+    }
+
+    #[test]
+    fn help_uses_the_configured_prefix() {
+        let config: BotConfig = serde_json::from_str(r#"{"commands": {"prefix": "?"}}"#).unwrap();
+        let mut bot = ChatBot::with_config(
+            &config,
+            Messages::default(),
+            Storage::temporary().unwrap(),
+            ChatLog::temporary().unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            bot.handle_event(moderator_command("help", &["?discord"])),
+            Some(ChatBotCommand::SendMessage(message))
+                if message == "?discord: Shows the link to the discord server."
+        ));
+    }
+
+    struct Chatters;
+
+    impl CommandHandler for Chatters {
+        fn name(&self) -> &str {
+            "chatters"
+        }
+
+        fn aliases(&self) -> &[&str] {
+            &["viewers"]
+        }
+
+        fn help(&self) -> &str {
+            "Shows the number of chatters"
+        }
+
+        fn cooldown(&self) -> Duration {
+            Duration::from_secs(60)
+        }
+
+        fn handle(&self, bot: &mut ChatBot, _command: &Command) -> Option<ChatBotCommand> {
+            Some(ChatBotCommand::SendMessage(format!(
+                "{} chatter(s)",
+                bot.chatters.len()
+            )))
+        }
+    }
+
+    #[test]
+    fn managing_aliases() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert_eq!(
+            message(bot.handle_event(moderator_command("alias", &["add", "dc", "!Discord"]))),
+            "!dc now runs !discord."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("dc", &[]))),
+            bot.messages.text("discord")
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("alias", &["add", "ban", "discord"]))),
+            "!ban is already a command or alias."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("alias", &["add", "x", "nothing"]))),
+            "There is no command !nothing."
+        );
+
+        // aliases of dynamic commands
+        bot.handle_event(moderator_command("newcommand", &["Hello", "Hi there!"]));
+        bot.handle_event(moderator_command("alias", &["add", "hi", "hello"]));
+        assert_eq!(
+            message(bot.handle_event(moderator_command("hi", &[]))),
+            "Hi there!"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("alias", &[]))),
+            "Aliases: !dc = !discord, !hi = !hello"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("help", &["dc"]))),
+            "!discord: Shows the link to the discord server. Also: !dc"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("alias", &["remove", "dc"]))),
+            "The alias !dc has been removed."
+        );
+        assert!(bot.handle_event(moderator_command("dc", &[])).is_none());
+        assert!(
+            message(bot.handle_event(moderator_command("alias", &["rename"])))
+                .starts_with("Usage: !alias [add <alias> <command>|remove <alias>]")
+        );
+    }
+
+    #[test]
+    fn managing_custom_commands() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert_eq!(
+            message(bot.handle_event(moderator_command("commands", &[]))),
+            "There are no custom commands yet."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("newcommand", &["!Hello", "Hi!"]))),
+            "The command !hello has been created."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("newcommand", &["hello", "Hey!"]))),
+            "The command !hello already exists, use !editcommand to change it."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("newcommand", &["ban", "Bye!"]))),
+            "!ban is already a built-in command or alias."
+        );
+        assert!(
+            message(bot.handle_event(moderator_command("newcommand", &["hello"])))
+                .starts_with("Usage: !newcommand [--script] <name> <text>")
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command(
+                "editcommand",
+                &["hello", "Hey", "there!"]
+            ))),
+            "The command !hello has been updated."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("editcommand", &["bye", "Bye!"]))),
+            "There is no custom command !bye."
+        );
+
+        // aliases follow a renamed command
+        bot.handle_event(moderator_command("alias", &["add", "hi", "hello"]));
+        assert_eq!(
+            message(bot.handle_event(moderator_command("renamecommand", &["hello", "greet"]))),
+            "The command !hello is now !greet."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("renamecommand", &["greet", "discord"]))),
+            "!discord is already a command or alias."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("showcommand", &["hi"]))),
+            "!greet: Hey there!"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("commands", &[]))),
+            "Custom commands (1/1): !greet"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("removecommand", &["greet"]))),
+            "The command !greet has been removed."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("removecommand", &["greet"]))),
+            "There is no custom command !greet."
+        );
+    }
+
+    #[test]
+    fn undoing_changes() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "You have no changes to undo."
+        );
+        bot.handle_event(moderator_command("newcommand", &["hello", "Hi!"]));
+        bot.handle_event(moderator_command("alias", &["add", "hi", "hello"]));
+        bot.handle_event(moderator_command("editcommand", &["hello", "Hey!"]));
+        bot.handle_event(moderator_command("renamecommand", &["hello", "greet"]));
+
+        // a rename is undone as a whole
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "Your !renamecommand of !hello has been undone."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("hi", &[]))),
+            "Hey!"
+        );
+        assert!(!bot.dynamic_commands.contains_key("greet"));
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "Your !editcommand of !hello has been undone."
+        );
+        assert_eq!(bot.dynamic_commands["hello"], "Hi!");
+        assert!(
+            message(bot.handle_event(moderator_command("history", &["hello"])))
+                .starts_with("History of !hello: edited by CaptainCallback on ")
+        );
+
+        // changes made since prevent undoing
+        bot.dynamic_commands
+            .insert("hello".to_owned(), "Hello!".to_owned());
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "Your !alias of the alias !hi has been undone."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "!hello has been changed since your !newcommand, it can't be undone."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("history", &["bye"]))),
+            "!bye has not been changed yet."
+        );
+    }
+
+    #[test]
+    fn undoing_reward_and_responder_changes() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        bot.handle_event(moderator_command(
+            "reward",
+            &["Hydrate", "|", "message", "Drink!"],
+        ));
+        bot.handle_event(moderator_command(
+            "responder",
+            &["add", "game", "word", "game", "|", "Rust"],
+        ));
+        bot.handle_event(moderator_command("responder", &["cooldown", "game", "1m"]));
+        bot.handle_event(moderator_command("responder", &["remove", "game"]));
+
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "Your !responder of the auto responder game has been undone."
+        );
+        assert_eq!(bot.responders.get("game").unwrap().cooldown_seconds, 60);
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "Your !responder of the auto responder game has been undone."
+        );
+        assert_eq!(bot.responders.get("game").unwrap().cooldown_seconds, 0);
+        bot.handle_event(moderator_command("undo", &[]));
+        assert!(bot.responders.get("game").is_none());
+        assert_eq!(
+            message(bot.handle_event(moderator_command("undo", &[]))),
+            "Your !reward of the reward Hydrate has been undone."
+        );
+        assert!(bot.redemptions.action("Hydrate").is_none());
+    }
+
+    #[test]
+    fn auto_responders() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        let text_message = |text: &str| {
+            ChatBotEvent::TextMessage(TextMessage {
+                id: "message-id".to_string(),
+                text: text.to_string(),
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            })
+        };
+        assert_eq!(
+            message(bot.handle_event(moderator_command(
+                "responder",
+                &["add", "Game", "word", "what", "game", "|", "@{user}", "we", "play", "Rust"]
+            ))),
+            "The auto responder game has been saved."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("responder", &["cooldown", "game", "1m"]))),
+            "The auto responder game has been saved."
+        );
+        assert!(matches!(
+            bot.handle_event(text_message("So what game is this?")),
+            Some(ChatBotCommand::MultipleCommands(commands))
+                if matches!(&commands[..], [
+                    ChatBotCommand::LogTextMessage(_),
+                    ChatBotCommand::SendMessage(response)
+                ] if response == "@carkhy we play Rust")
+        ));
+        assert!(matches!(
+            bot.handle_event(text_message("what game?")),
+            Some(ChatBotCommand::LogTextMessage(_))
+        ));
+        assert_eq!(
+            message(bot.handle_event(moderator_command(
+                "responder",
+                &["add", "broken", "regex", "(", "|", "Oops"]
+            ))),
+            "The pattern of the auto responder broken is not a valid regular expression."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("responder", &["cooldown", "x", "1m"]))),
+            "There is no auto responder x."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("responders", &[]))),
+            "Auto responders (1/1): game (\"what game\")"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("responder", &["remove", "game"]))),
+            "The auto responder game has been removed."
+        );
+    }
+
+    #[test]
+    fn script_commands() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert!(message(bot.handle_event(moderator_command(
+            "newcommand",
+            &["--script", "hug", "let", "x", "=", ";"]
+        )))
+        .starts_with("The script has a syntax error: "));
+        assert!(!bot.dynamic_commands.contains_key("hug"));
+        assert_eq!(
+            message(bot.handle_event(moderator_command(
+                "newcommand",
+                &["--script", "hug", r#"user.name + " hugs " + args[0]"#]
+            ))),
+            "The command !hug has been created."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("hug", &["carkhy"]))),
+            "CaptainCallback hugs carkhy"
+        );
+        assert!(message(bot.handle_event(moderator_command("hug", &[])))
+            .starts_with("The script of !hug failed: "));
+
+        // moderation needs the bot to be a moderator
+        bot.handle_event(moderator_command(
+            "editcommand",
+            &["--script", "hug", r#"timeout(args[0], 10)"#],
+        ));
+        assert_eq!(
+            message(bot.handle_event(moderator_command("hug", &["carkhy"]))),
+            bot.messages.text("not_moderator")
+        );
+        bot.handle_event(ChatBotEvent::BotModeratorStatus(true));
+        assert!(matches!(
+            bot.handle_event(moderator_command("hug", &["carkhy"])),
+            Some(ChatBotCommand::Moderate(ModerationAction::Timeout { user, .. })) if user == "carkhy"
+        ));
+    }
+
+    #[test]
+    fn managing_repeating_messages() {
+        let mut bot = ChatBot::new();
+        let message = |result| match result {
+            Some(ChatBotCommand::SendMessage(message)) => message,
+            result => panic!("Unexpected result {:?}", result),
+        };
+        assert!(message(bot.handle_event(moderator_command(
+            "newrepeating",
+            &["follow", "0", "Follow!"]
+        )))
+        .starts_with("Usage: !newrepeating <name> <interval> <text>"));
+        let result = bot.handle_event(moderator_command(
+            "newrepeating",
+            &["follow", "10m", "Follow!"],
+        ));
+        assert!(
+            matches!(result, Some(ChatBotCommand::MultipleCommands(commands))
+                         if matches!(&commands[..], [
+                             ChatBotCommand::SendMessage(message),
+                             ChatBotCommand::TimedCallback { duration, .. }
+                         ] if message == "The repeating message follow has been created, it is posted every 10 minutes."
+                             && *duration == Duration::from_secs(600)))
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("newrepeating", &["follow", "5m", "Hi"]))),
+            "The repeating message follow already exists, use !editrepeating to change it."
+        );
+
+        // editing restarts the timer, the previous one stops
+        let first_id = bot.repeating_messages["follow"].timer_id;
+        assert!(matches!(
+            bot.handle_event(moderator_command("editrepeating", &["follow", "1h"])),
+            Some(ChatBotCommand::MultipleCommands(_))
+        ));
+        assert_eq!(bot.repeating_messages["follow"].text, "Follow!");
+        assert!(bot
+            .handle_event(ChatBotEvent::TimedMessage("follow".to_owned(), first_id))
+            .is_none());
+        assert_eq!(
+            message(bot.handle_event(moderator_command("editrepeating", &["discord", "1h"]))),
+            "There is no repeating message discord."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("repeatings", &[]))),
+            "Repeating messages: follow (every 1 hour)"
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("removerepeating", &["follow"]))),
+            "The repeating message follow has been removed."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("removerepeating", &["follow"]))),
+            "There is no repeating message follow."
+        );
+        assert_eq!(
+            message(bot.handle_event(moderator_command("repeatings", &[]))),
+            "There are no repeating messages yet."
+        );
+    }
+
+    #[test]
+    fn registering_commands() {
+        let mut bot = ChatBot::new();
+        bot.register_command(Rc::new(Chatters)).unwrap();
+        assert_eq!(
+            bot.register_command(Rc::new(Chatters)),
+            Err(RegistryError::NameTaken("chatters".to_owned()))
+        );
+        bot.handle_event(ChatBotEvent::Join("carkhy".to_owned()));
+        let viewer_command = |name: &str| {
+            ChatBotEvent::Command(Command {
+                name: name.to_owned(),
+                options: Vec::default(),
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            })
+        };
+        assert!(matches!(
+            bot.handle_event(viewer_command("viewers")),
+            Some(ChatBotCommand::SendMessage(message)) if message == "1 chatter(s)"
+        ));
+        assert!(bot.handle_event(viewer_command("chatters")).is_none());
+        assert!(matches!(
+            bot.handle_event(viewer_command("lastseen")),
+            Some(ChatBotCommand::SendMessage(message)) if message == bot.messages.text("denied")
+        ));
+    }
+
+    #[test]
+    fn stream_info_commands() {
+        let mut bot = ChatBot::new();
+        let viewer_command = |name: &str, options: &[&str]| {
+            ChatBotEvent::Command(Command {
+                name: name.to_owned(),
+                options: options.iter().map(|option| option.to_string()).collect(),
+                user: UserInfo {
+                    name: "Carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                ..Default::default()
+            })
+        };
+        assert!(matches!(
+            bot.handle_event(viewer_command("followage", &[])),
+            Some(ChatBotCommand::CallTwitch(TwitchRequest::FollowAge(user))) if user == "carkhy"
+        ));
+        assert!(matches!(
+            bot.handle_event(viewer_command("title", &["New", "title"])),
+            Some(ChatBotCommand::SendMessage(message)) if message == bot.messages.text("denied")
+        ));
+        assert!(matches!(
+            bot.handle_event(moderator_command("title", &["New", "title"])),
+            Some(ChatBotCommand::CallTwitch(TwitchRequest::SetTitle(title))) if title == "New title"
+        ));
+
+        // answers are cached for a while
+        assert!(matches!(
+            bot.handle_event(ChatBotEvent::TwitchResponse(
+                TwitchRequest::Uptime,
+                TwitchResponse::Offline,
+            )),
+            Some(ChatBotCommand::SendMessage(message)) if message == "The stream is offline."
+        ));
+        assert!(matches!(
+            bot.handle_event(viewer_command("uptime", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "The stream is offline."
+        ));
+    }
+
+    #[test]
+    fn viewer_statistics_commands() {
+        let mut bot = ChatBot::new();
+        bot.handle_event(ChatBotEvent::Join("carkhy".to_owned()));
+        bot.handle_event(ChatBotEvent::TextMessage(TextMessage {
+            text: "hello".to_owned(),
+            user: UserInfo {
+                name: "carkhy".to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }));
+        let viewer_command = |name: &str, options: &[&str]| {
+            ChatBotEvent::Command(Command {
+                user: UserInfo {
+                    name: "carkhy".to_owned(),
+                    badges: HashSet::default(),
+                },
+                name: name.to_owned(),
+                options: options.iter().map(|option| option.to_string()).collect(),
+                ..Default::default()
+            })
+        };
+        assert!(matches!(
+            bot.handle_event(viewer_command("watchtime", &[])),
+            Some(ChatBotCommand::SendMessage(message)) if message.starts_with("carkhy has watched for")
+        ));
+        assert!(matches!(
+            bot.handle_event(viewer_command("firstseen", &["@nobody"])),
+            Some(ChatBotCommand::SendMessage(message)) if message == "I have not seen nobody yet."
+        ));
+        assert!(matches!(
+            bot.handle_event(viewer_command("stats", &["carkhy"])),
+            Some(ChatBotCommand::SendMessage(message))
+                if message.starts_with("carkhy: 1 message(s), 3 command(s)")
+        ));
+        assert!(matches!(
+            bot.handle_event(viewer_command("stats", &[])),
+            Some(ChatBotCommand::SendMessage(message))
+                if message.starts_with("This stream: 1 message(s)") && message.ends_with("carkhy (1)")
+        ));
+    }
 }
@@ -0,0 +1,1355 @@
+use super::{
+    moderate, pages, reason, ChatBot, RepeatingMessage, DATE_FORMAT, DEFAULT_LOG_COUNT,
+    DEFAULT_TIMEOUT, HELP_PAGE_LENGTH, MAX_LOG_COUNT, NUKE_TIMEOUT, TIME_FORMAT,
+};
+use crate::{
+    connect::{ChatBotEvent, Command, ModerationAction, TwitchRequest},
+    core::{
+        audit::{Change, RepeatingSettings},
+        command_registry::{CommandHandler, Permission},
+        redemptions::RewardAction,
+        responders::{AutoResponder, MatchMode, ResponderError},
+        scripts::{script_source, SCRIPT_FLAG},
+        time_format::parse_duration,
+        ChatBotCommand::{self, *},
+        Messages,
+    },
+};
+use chrono::Utc;
+use std::time::{Duration, Instant, SystemTime};
+use uuid::Uuid;
+
+// the number of revisions !history shows
+const HISTORY_LENGTH: usize = 5;
+
+type Handle = fn(&mut ChatBot, &Command) -> Option<ChatBotCommand>;
+
+/// A command of the bot itself, handled by one of the functions below. Its help and
+/// usage are the messages `command.<name>.help` and `command.<name>.usage`.
+pub(super) struct Builtin {
+    name: &'static str,
+    usage: String,
+    permission: Permission,
+    // the bot has to be a moderator of the channel to run the command
+    moderates: bool,
+    help: String,
+    handle: Handle,
+}
+
+impl Builtin {
+    fn new(name: &'static str, handle: Handle) -> Self {
+        Self {
+            name,
+            usage: String::new(),
+            permission: Permission::Everyone,
+            moderates: false,
+            help: String::new(),
+            handle,
+        }
+    }
+
+    fn describe(self, messages: &Messages) -> Self {
+        Self {
+            help: messages.text(&format!("command.{}.help", self.name)),
+            usage: messages
+                .get(&format!("command.{}.usage", self.name))
+                .unwrap_or_default()
+                .to_owned(),
+            ..self
+        }
+    }
+
+    fn moderators_only(self) -> Self {
+        Self {
+            permission: Permission::Moderator,
+            ..self
+        }
+    }
+
+    fn moderating(self) -> Self {
+        Self {
+            moderates: true,
+            ..self.moderators_only()
+        }
+    }
+}
+
+impl CommandHandler for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn usage(&self) -> &str {
+        &self.usage
+    }
+
+    fn permission(&self) -> Permission {
+        self.permission
+    }
+
+    fn help(&self) -> &str {
+        &self.help
+    }
+
+    fn handle(&self, bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+        if self.moderates && !bot.is_moderator {
+            return bot.reply("not_moderator", &[]);
+        }
+        (self.handle)(bot, command)
+    }
+}
+
+/// The built-in commands in the order they are listed to users
+pub(super) fn builtins(messages: &Messages) -> Vec<Builtin> {
+    [
+        Builtin::new("help", help),
+        Builtin::new("info", |bot, _| {
+            bot.reply("info", &[("prefix", &bot.commands.prefix())])
+        }),
+        Builtin::new("discord", |bot, _| bot.reply("discord", &[])),
+        Builtin::new("slap", slap),
+        Builtin::new("newcommand", new_command).moderators_only(),
+        Builtin::new("editcommand", edit_command).moderators_only(),
+        Builtin::new("renamecommand", rename_command).moderators_only(),
+        Builtin::new("removecommand", remove_command).moderators_only(),
+        Builtin::new("commands", commands),
+        Builtin::new("showcommand", show_command),
+        Builtin::new("alias", alias).moderators_only(),
+        Builtin::new("newrepeating", new_repeating).moderators_only(),
+        Builtin::new("editrepeating", edit_repeating).moderators_only(),
+        Builtin::new("removerepeating", remove_repeating).moderators_only(),
+        Builtin::new("repeatings", repeatings).moderators_only(),
+        Builtin::new("undo", undo).moderators_only(),
+        Builtin::new("history", history).moderators_only(),
+        Builtin::new("permit", permit).moderators_only(),
+        Builtin::new("strikes", strikes).moderators_only(),
+        Builtin::new("clearstrikes", clear_strikes).moderators_only(),
+        Builtin::new("to", timeout).moderating(),
+        Builtin::new("ban", ban).moderating(),
+        Builtin::new("unban", unban).moderating(),
+        Builtin::new("delete", delete).moderating(),
+        Builtin::new("nuke", nuke).moderating(),
+        Builtin::new("slow", slow).moderating(),
+        Builtin::new("followers", followers).moderating(),
+        Builtin::new("emoteonly", emote_only).moderating(),
+        Builtin::new("clear", |_, _| moderate(ModerationAction::ClearChat)).moderating(),
+        Builtin::new("lockdown", lockdown).moderating(),
+        Builtin::new("lastseen", last_seen).moderators_only(),
+        Builtin::new("logs", logs).moderators_only(),
+        Builtin::new("watchtime", watchtime),
+        Builtin::new("firstseen", first_seen),
+        Builtin::new("stats", stats),
+        Builtin::new("so", shoutout).moderators_only(),
+        Builtin::new("uptime", |bot, _| {
+            Some(
+                bot.stream_info
+                    .request(TwitchRequest::Uptime, Instant::now()),
+            )
+        }),
+        Builtin::new("title", title),
+        Builtin::new("game", game),
+        Builtin::new("followage", |bot, command| {
+            let user_name = user_name_or_self(command);
+            Some(
+                bot.stream_info
+                    .request(TwitchRequest::FollowAge(user_name), Instant::now()),
+            )
+        }),
+        Builtin::new("accountage", |bot, command| {
+            let user_name = user_name_or_self(command);
+            Some(
+                bot.stream_info
+                    .request(TwitchRequest::AccountAge(user_name), Instant::now()),
+            )
+        }),
+        Builtin::new("reward", reward).moderators_only(),
+        Builtin::new("rewards", rewards),
+        Builtin::new("responder", responder).moderators_only(),
+        Builtin::new("responders", responders).moderators_only(),
+        Builtin::new("quote", quote),
+        Builtin::new("topcheerers", top_cheerers),
+        Builtin::new("bitgoal", bit_goal),
+    ]
+    .into_iter()
+    .map(|builtin| builtin.describe(messages))
+    .collect()
+}
+
+fn help(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        None => Some(SendMessage(bot.help_page(&command.user, 1))),
+        Some(option) => match option.parse() {
+            Ok(page) => Some(SendMessage(bot.help_page(&command.user, page))),
+            Err(_) => Some(SendMessage(bot.command_help(&name_option(bot, option)))),
+        },
+    }
+}
+
+// the options of the command as registered
+fn usage(bot: &ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    bot.reply("usage", &[("usage", &bot.command_usage(&command.name))])
+}
+
+// The name of a command with the prefix, as users type it
+fn prefixed(bot: &ChatBot, name: &str) -> String {
+    format!("{}{}", bot.commands.prefix(), name)
+}
+
+// the user named in the first option, or the user of the command
+fn user_name_or_self(command: &Command) -> String {
+    command
+        .options
+        .first()
+        .unwrap_or(&command.user.name)
+        .trim_start_matches('@')
+        .to_lowercase()
+}
+
+fn slap(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    println!("Slapping one of these guys \n{:#?}", bot.chatters);
+    // Notice how we can now do everything in a single expression
+    // because we removed the IO from this place
+    let slapping_user = &command.user.name;
+    println!("This guy specifically : {}", slapping_user);
+    command
+        .options
+        .first()
+        .and_then(|slapped_user| bot.chatters.get(slapped_user))
+        .map(|slapped_user| {
+            SendMessage(
+                bot.messages
+                    .format("slap", &[("user", slapping_user), ("target", slapped_user)]),
+            )
+        })
+}
+
+// A name given in the options, with or without the prefix
+fn name_option(bot: &ChatBot, option: &str) -> String {
+    option
+        .trim_start_matches(bot.commands.prefix())
+        .to_lowercase()
+}
+
+// Records the changes the command made in the audit log
+fn audit(bot: &mut ChatBot, command: &Command, changes: Vec<Change>) {
+    let action = bot.commands.command_name(&command.name).to_owned();
+    bot.audit
+        .record(&command.user.name, &action, changes, None, Utc::now());
+}
+
+// The name and text given as `[--script] <name> <text>`, a script must compile
+fn name_and_text(bot: &ChatBot, command: &Command) -> Result<(String, String), String> {
+    let (is_script, options) = match command.options.split_first() {
+        Some((flag, options)) if flag == SCRIPT_FLAG => (true, options),
+        _ => (false, &command.options[..]),
+    };
+    if options.len() < 2 {
+        return Err(bot
+            .messages
+            .format("usage", &[("usage", &bot.command_usage(&command.name))]));
+    }
+    let mut text = options[1..].join(" ");
+    if is_script {
+        text = format!("{} {}", SCRIPT_FLAG, text);
+    }
+    if let Some(Err(err)) = script_source(&text).map(|source| bot.scripts.check(source)) {
+        return Err(bot
+            .messages
+            .format("script_syntax_error", &[("error", &err)]));
+    }
+    Ok((name_option(bot, &options[0]), text))
+}
+
+fn new_command(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let (name, text) = match name_and_text(bot, command) {
+        Ok(name_and_text) => name_and_text,
+        Err(reply) => return Some(SendMessage(reply)),
+    };
+    let subject = prefixed(bot, &name);
+    let reply = if bot.dynamic_commands.contains_key(&name) {
+        bot.messages.format(
+            "custom_command.exists",
+            &[("command", &subject), ("prefix", &bot.commands.prefix())],
+        )
+    } else if bot.commands.is_taken(&name) {
+        bot.messages
+            .format("custom_command.builtin", &[("command", &subject)])
+    } else {
+        let reply = bot
+            .messages
+            .format("custom_command.created", &[("command", &subject)]);
+        bot.dynamic_commands
+            .insert(name.to_owned(), text.to_owned());
+        audit(
+            bot,
+            command,
+            vec![Change::Command {
+                name,
+                old: None,
+                new: Some(text),
+            }],
+        );
+        reply
+    };
+    Some(SendMessage(reply))
+}
+
+fn edit_command(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let (name, text) = match name_and_text(bot, command) {
+        Ok(name_and_text) => name_and_text,
+        Err(reply) => return Some(SendMessage(reply)),
+    };
+    let subject = prefixed(bot, &name);
+    match bot.dynamic_commands.get(&name).cloned() {
+        Some(old) => {
+            let reply = bot
+                .messages
+                .format("custom_command.updated", &[("command", &subject)]);
+            bot.dynamic_commands
+                .insert(name.to_owned(), text.to_owned());
+            audit(
+                bot,
+                command,
+                vec![Change::Command {
+                    name,
+                    old: Some(old),
+                    new: Some(text),
+                }],
+            );
+            Some(SendMessage(reply))
+        }
+        None => bot.reply("custom_command.unknown", &[("command", &subject)]),
+    }
+}
+
+fn rename_command(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    if command.options.len() != 2 {
+        return usage(bot, command);
+    }
+    let name = name_option(bot, &command.options[0]);
+    let new_name = name_option(bot, &command.options[1]);
+    let subject = prefixed(bot, &name);
+    let new_subject = prefixed(bot, &new_name);
+    let reply = if !bot.dynamic_commands.contains_key(&name) {
+        bot.messages
+            .format("custom_command.unknown", &[("command", &subject)])
+    } else if bot.dynamic_commands.contains_key(&new_name) || bot.commands.is_taken(&new_name) {
+        bot.messages
+            .format("custom_command.taken", &[("command", &new_subject)])
+    } else {
+        let mut changes: Vec<Change> = bot
+            .commands
+            .aliases()
+            .into_iter()
+            .filter(|(_, command_name)| *command_name == name)
+            .map(|(alias, _)| Change::Alias {
+                alias: alias.to_owned(),
+                old: Some(name.to_owned()),
+                new: Some(new_name.to_owned()),
+            })
+            .collect();
+        if let Some(text) = bot.dynamic_commands.remove(&name) {
+            bot.dynamic_commands
+                .insert(new_name.to_owned(), text.to_owned());
+            changes.insert(
+                0,
+                Change::Command {
+                    name: name.to_owned(),
+                    old: Some(text.to_owned()),
+                    new: None,
+                },
+            );
+            changes.insert(
+                1,
+                Change::Command {
+                    name: new_name.to_owned(),
+                    old: None,
+                    new: Some(text),
+                },
+            );
+        }
+        bot.commands.rename_command(&name, &new_name);
+        audit(bot, command, changes);
+        bot.messages.format(
+            "custom_command.renamed",
+            &[("command", &subject), ("new_command", &new_subject)],
+        )
+    };
+    Some(SendMessage(reply))
+}
+
+fn remove_command(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let name = match command.options.first() {
+        Some(name) => name_option(bot, name),
+        None => return usage(bot, command),
+    };
+    let subject = prefixed(bot, &name);
+    match bot.dynamic_commands.remove(&name) {
+        Some(text) => {
+            let reply = bot
+                .messages
+                .format("custom_command.removed", &[("command", &subject)]);
+            audit(
+                bot,
+                command,
+                vec![Change::Command {
+                    name,
+                    old: Some(text),
+                    new: None,
+                }],
+            );
+            Some(SendMessage(reply))
+        }
+        None => bot.reply("custom_command.unknown", &[("command", &subject)]),
+    }
+}
+
+fn commands(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let prefix = bot.commands.prefix();
+    let mut names: Vec<String> = bot
+        .dynamic_commands
+        .keys()
+        .map(|name| format!("{}{}", prefix, name))
+        .collect();
+    if names.is_empty() {
+        return bot.reply("custom_commands.none", &[]);
+    }
+    names.sort();
+    let pages = pages(&names, HELP_PAGE_LENGTH);
+    let page = command
+        .options
+        .first()
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1usize)
+        .max(1);
+    match pages.get(page - 1) {
+        Some(names) => bot.reply(
+            "custom_commands",
+            &[
+                ("page", &page),
+                ("pages", &pages.len()),
+                ("commands", names),
+            ],
+        ),
+        None => bot.reply("help.no_page", &[("pages", &pages.len())]),
+    }
+}
+
+fn show_command(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let name = match command.options.first() {
+        Some(name) => name_option(bot, name),
+        None => return usage(bot, command),
+    };
+    let name = bot.commands.resolve(&name);
+    let subject = prefixed(bot, name);
+    match bot.dynamic_commands.get(name) {
+        // scripts loaded from files may span lines
+        Some(text) => bot.reply(
+            "custom_command.show",
+            &[
+                ("command", &subject),
+                ("text", &text.replace(['\r', '\n'], " ")),
+            ],
+        ),
+        None => bot.reply("custom_command.unknown", &[("command", &subject)]),
+    }
+}
+
+fn alias(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let prefix = bot.commands.prefix().to_owned();
+    let options: Vec<String> = command
+        .options
+        .iter()
+        .map(|option| option.trim_start_matches(prefix.as_str()).to_lowercase())
+        .collect();
+    match options.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {
+            let aliases: Vec<String> = bot
+                .commands
+                .aliases()
+                .into_iter()
+                .map(|(alias, name)| format!("{}{} = {}{}", prefix, alias, prefix, name))
+                .collect();
+            if aliases.is_empty() {
+                bot.reply("aliases.none", &[])
+            } else {
+                bot.reply("aliases", &[("aliases", &aliases.join(", "))])
+            }
+        }
+        ["add", alias, name] => {
+            let exists = bot.commands.get(name).is_some()
+                || bot
+                    .dynamic_commands
+                    .contains_key(bot.commands.resolve(name));
+            let subject = prefixed(bot, alias);
+            if !exists {
+                bot.reply("no_command", &[("command", &prefixed(bot, name))])
+            } else if bot.dynamic_commands.contains_key(alias) {
+                bot.reply("alias.is_command", &[("alias", &subject)])
+            } else {
+                match bot.commands.add_alias(alias, name) {
+                    Ok(()) => {
+                        let name = bot.commands.command_name(alias).to_owned();
+                        let reply = bot.messages.format(
+                            "alias.added",
+                            &[("alias", &subject), ("command", &prefixed(bot, &name))],
+                        );
+                        audit(
+                            bot,
+                            command,
+                            vec![Change::Alias {
+                                alias: alias.to_owned(),
+                                old: None,
+                                new: Some(name),
+                            }],
+                        );
+                        Some(SendMessage(reply))
+                    }
+                    Err(_) => bot.reply("alias.taken", &[("alias", &subject)]),
+                }
+            }
+        }
+        ["remove", alias] => match alias_of(bot, alias) {
+            Some(name) if bot.commands.remove_alias(alias).is_ok() => {
+                audit(
+                    bot,
+                    command,
+                    vec![Change::Alias {
+                        alias: alias.to_owned(),
+                        old: Some(name),
+                        new: None,
+                    }],
+                );
+                bot.reply("alias.removed", &[("alias", &prefixed(bot, alias))])
+            }
+            _ => bot.reply("alias.unknown", &[("alias", &prefixed(bot, alias))]),
+        },
+        _ => usage(bot, command),
+    }
+}
+
+// The command an alias added in the config or chat stands for
+fn alias_of(bot: &ChatBot, alias: &str) -> Option<String> {
+    bot.commands
+        .aliases()
+        .into_iter()
+        .find(|(name, _)| *name == alias)
+        .map(|(_, command_name)| command_name.to_owned())
+}
+
+fn repeating_settings(bot: &ChatBot, name: &str) -> Option<RepeatingSettings> {
+    bot.repeating_messages
+        .get(name)
+        .map(|message| RepeatingSettings {
+            seconds: message.interval.as_secs(),
+            text: message.text.to_owned(),
+        })
+}
+
+// Starts the timer of the message, a new timer id stops the previous timer
+fn schedule(bot: &mut ChatBot, name: &str, text: String, interval: Duration) -> ChatBotCommand {
+    let id = Uuid::new_v4();
+    bot.repeating_messages.insert(
+        name.to_owned(),
+        RepeatingMessage {
+            name: name.to_owned(),
+            text,
+            interval,
+            timer_id: id,
+        },
+    );
+    TimedCallback {
+        duration: interval,
+        event: ChatBotEvent::TimedMessage(name.to_owned(), id),
+    }
+}
+
+fn interval_option(option: &str) -> Option<Duration> {
+    parse_duration(option).filter(|interval| !interval.is_zero())
+}
+
+fn new_repeating(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let interval = command
+        .options
+        .get(1)
+        .and_then(|interval| interval_option(interval));
+    let interval = match interval {
+        Some(interval) if command.options.len() >= 3 => interval,
+        _ => return usage(bot, command),
+    };
+    let name = command.options[0].to_lowercase();
+    if bot.repeating_messages.contains_key(&name) {
+        return bot.reply(
+            "repeating.exists",
+            &[("name", &name), ("prefix", &bot.commands.prefix())],
+        );
+    }
+    let timer = schedule(bot, &name, command.options[2..].join(" "), interval);
+    let new = repeating_settings(bot, &name);
+    audit(
+        bot,
+        command,
+        vec![Change::Repeating {
+            name: name.to_owned(),
+            old: None,
+            new,
+        }],
+    );
+    Some(MultipleCommands(vec![
+        SendMessage(bot.messages.format(
+            "repeating.created",
+            &[("name", &name), ("interval", &bot.duration(interval))],
+        )),
+        timer,
+    ]))
+}
+
+fn edit_repeating(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let interval = command
+        .options
+        .get(1)
+        .and_then(|interval| interval_option(interval));
+    let interval = match interval {
+        Some(interval) => interval,
+        None => return usage(bot, command),
+    };
+    let name = command.options[0].to_lowercase();
+    let text = match bot.repeating_messages.get(&name) {
+        Some(_) if command.options.len() > 2 => command.options[2..].join(" "),
+        Some(message) => message.text.to_owned(),
+        None => return bot.reply("repeating.unknown", &[("name", &name)]),
+    };
+    let old = repeating_settings(bot, &name);
+    let timer = schedule(bot, &name, text, interval);
+    let new = repeating_settings(bot, &name);
+    audit(
+        bot,
+        command,
+        vec![Change::Repeating {
+            name: name.to_owned(),
+            old,
+            new,
+        }],
+    );
+    Some(MultipleCommands(vec![
+        SendMessage(bot.messages.format(
+            "repeating.updated",
+            &[("name", &name), ("interval", &bot.duration(interval))],
+        )),
+        timer,
+    ]))
+}
+
+fn remove_repeating(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let name = match command.options.first() {
+        Some(name) => name.to_lowercase(),
+        None => return usage(bot, command),
+    };
+    let old = repeating_settings(bot, &name);
+    match bot.repeating_messages.remove(&name) {
+        Some(_) => {
+            let reply = bot.messages.format("repeating.removed", &[("name", &name)]);
+            audit(
+                bot,
+                command,
+                vec![Change::Repeating {
+                    name,
+                    old,
+                    new: None,
+                }],
+            );
+            Some(SendMessage(reply))
+        }
+        None => bot.reply("repeating.unknown", &[("name", &name)]),
+    }
+}
+
+fn repeatings(bot: &mut ChatBot, _: &Command) -> Option<ChatBotCommand> {
+    let mut messages: Vec<&RepeatingMessage> = bot.repeating_messages.values().collect();
+    if messages.is_empty() {
+        return bot.reply("repeatings.none", &[]);
+    }
+    messages.sort_by(|first, second| first.name.cmp(&second.name));
+    let messages: Vec<String> = messages
+        .into_iter()
+        .map(|message| {
+            bot.messages.format(
+                "repeatings.item",
+                &[
+                    ("name", &message.name),
+                    ("interval", &bot.duration(message.interval)),
+                ],
+            )
+        })
+        .collect();
+    bot.reply("repeatings", &[("messages", &messages.join(", "))])
+}
+
+// Whether the value the change set is still in place
+fn is_current(bot: &ChatBot, change: &Change) -> bool {
+    match change {
+        Change::Command { name, new, .. } => bot.dynamic_commands.get(name) == new.as_ref(),
+        Change::Alias { alias, new, .. } => alias_of(bot, alias) == *new,
+        Change::Repeating { name, new, .. } => repeating_settings(bot, name) == *new,
+        Change::Reward { title, new, .. } => bot.redemptions.action(title) == *new,
+        Change::Responder { name, new, .. } => bot.responders.get(name) == *new,
+    }
+}
+
+// Sets the new value of the change, returns the timer of a restored repeating message
+fn apply(bot: &mut ChatBot, change: &Change) -> Option<ChatBotCommand> {
+    match change {
+        Change::Command { name, new, .. } => {
+            match new {
+                Some(text) => bot
+                    .dynamic_commands
+                    .insert(name.to_owned(), text.to_owned()),
+                None => bot.dynamic_commands.remove(name),
+            };
+            None
+        }
+        Change::Alias { alias, new, .. } => {
+            bot.commands.remove_alias(alias).ok();
+            if let Some(name) = new {
+                bot.commands.add_alias(alias, name).ok();
+            }
+            None
+        }
+        Change::Repeating { name, new, .. } => match new {
+            Some(settings) => Some(schedule(
+                bot,
+                name,
+                settings.text.to_owned(),
+                Duration::from_secs(settings.seconds),
+            )),
+            None => {
+                bot.repeating_messages.remove(name);
+                None
+            }
+        },
+        Change::Reward { title, new, .. } => {
+            bot.redemptions.bind(title, new.to_owned());
+            None
+        }
+        Change::Responder { name, new, .. } => {
+            match new {
+                Some(responder) => {
+                    if let Err(err) = bot.responders.set(responder.to_owned()) {
+                        println!("Could not restore auto responder: {:?}", err);
+                    }
+                }
+                None => {
+                    bot.responders.remove(name);
+                }
+            }
+            None
+        }
+    }
+}
+
+fn change_subject(bot: &ChatBot, change: &Change) -> String {
+    match change {
+        Change::Command { name, .. } => prefixed(bot, name),
+        Change::Alias { alias, .. } => bot
+            .messages
+            .format("change.alias", &[("alias", &prefixed(bot, alias))]),
+        Change::Repeating { name, .. } => {
+            bot.messages.format("change.repeating", &[("name", name)])
+        }
+        Change::Reward { title, .. } => bot.messages.format("change.reward", &[("title", title)]),
+        Change::Responder { name, .. } => {
+            bot.messages.format("change.responder", &[("name", name)])
+        }
+    }
+}
+
+fn undo(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let entry = match bot.audit.last_change(&command.user.name) {
+        Some(entry) => entry,
+        None => return bot.reply("undo.none", &[]),
+    };
+    let action = prefixed(bot, &entry.action);
+    if let Some(change) = entry.changes.iter().find(|change| !is_current(bot, change)) {
+        return bot.reply(
+            "undo.changed",
+            &[
+                ("subject", &change_subject(bot, change)),
+                ("action", &action),
+            ],
+        );
+    }
+    let changes: Vec<Change> = entry.changes.iter().rev().map(Change::reverted).collect();
+    let mut commands = vec![SendMessage(bot.messages.format(
+        "undo.done",
+        &[
+            ("action", &action),
+            ("subject", &change_subject(bot, &entry.changes[0])),
+        ],
+    ))];
+    commands.extend(changes.iter().filter_map(|change| apply(bot, change)));
+    bot.audit.record(
+        &command.user.name,
+        "undo",
+        changes,
+        Some(entry.id),
+        Utc::now(),
+    );
+    if commands.len() == 1 {
+        commands.pop()
+    } else {
+        Some(MultipleCommands(commands))
+    }
+}
+
+fn history(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let name = match command.options.first() {
+        Some(name) => name_option(bot, name),
+        None => return usage(bot, command),
+    };
+    let revisions: Vec<String> = bot
+        .audit
+        .command_history(&name)
+        .into_iter()
+        .take(HISTORY_LENGTH)
+        .filter_map(|entry| {
+            let change = entry.changes.into_iter().find_map(|change| match change {
+                Change::Command {
+                    name: changed,
+                    old,
+                    new,
+                } if changed == name => Some((old, new)),
+                _ => None,
+            })?;
+            let key = match change {
+                (None, _) => "history.created",
+                (_, None) => "history.removed",
+                _ => "history.edited",
+            };
+            Some(bot.messages.format(
+                key,
+                &[
+                    ("user", &entry.user),
+                    ("time", &entry.time.format(TIME_FORMAT)),
+                ],
+            ))
+        })
+        .collect();
+    let subject = prefixed(bot, &name);
+    if revisions.is_empty() {
+        bot.reply("history.none", &[("command", &subject)])
+    } else {
+        bot.reply(
+            "history",
+            &[("command", &subject), ("revisions", &revisions.join(", "))],
+        )
+    }
+}
+
+fn permit(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => {
+            let user_name = user_name.trim_start_matches('@');
+            let duration = command
+                .options
+                .get(1)
+                .and_then(|seconds| seconds.parse().ok())
+                .map(Duration::from_secs);
+            bot.moderation.permit(user_name, duration, Instant::now());
+            match duration {
+                Some(duration) => bot.reply(
+                    "permit.seconds",
+                    &[("user", &user_name), ("seconds", &duration.as_secs())],
+                ),
+                None => bot.reply("permit.once", &[("user", &user_name)]),
+            }
+        }
+        None => missing_user(bot, command),
+    }
+}
+
+fn strikes(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => Some(SendMessage(
+            bot.strikes_message(user_name.trim_start_matches('@')),
+        )),
+        None => missing_user(bot, command),
+    }
+}
+
+fn clear_strikes(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => {
+            let user_name = user_name.trim_start_matches('@');
+            if bot.moderation.clear_strikes(user_name, SystemTime::now()) {
+                bot.reply("strikes.cleared", &[("user", &user_name)])
+            } else {
+                bot.reply("strikes.none", &[("user", &user_name)])
+            }
+        }
+        None => missing_user(bot, command),
+    }
+}
+
+// The reply to a command that names no user
+fn missing_user(bot: &ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    bot.reply(
+        "missing_user",
+        &[(
+            "command",
+            &prefixed(bot, bot.commands.command_name(&command.name)),
+        )],
+    )
+}
+
+// the user named in the first option of a moderation command
+fn target(command: &Command) -> Option<String> {
+    command
+        .options
+        .first()
+        .map(|user_name| user_name.trim_start_matches('@').to_owned())
+}
+
+fn timeout(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let options = &command.options;
+    let user = match target(command) {
+        Some(user) => user,
+        None => return usage(bot, command),
+    };
+    let (duration, reason) = match options.get(1).and_then(|d| parse_duration(d)) {
+        Some(duration) => (duration, reason(&options[2..])),
+        None => (DEFAULT_TIMEOUT, reason(&options[1..])),
+    };
+    moderate(ModerationAction::Timeout {
+        user,
+        duration,
+        reason,
+    })
+}
+
+fn ban(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match target(command) {
+        Some(user) => moderate(ModerationAction::Ban {
+            user,
+            reason: reason(&command.options[1..]),
+        }),
+        None => usage(bot, command),
+    }
+}
+
+fn unban(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match target(command) {
+        Some(user) => moderate(ModerationAction::Unban(user)),
+        None => usage(bot, command),
+    }
+}
+
+fn delete(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match target(command) {
+        Some(user) => match bot.moderation.last_message_id(&user) {
+            Some(id) => moderate(ModerationAction::DeleteMessage(id)),
+            None => bot.reply("delete.no_message", &[("user", &user)]),
+        },
+        None => usage(bot, command),
+    }
+}
+
+fn nuke(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let options = &command.options;
+    let lookback = options.last().and_then(|lookback| parse_duration(lookback));
+    let (lookback, phrase) = match lookback {
+        Some(lookback) if options.len() >= 2 => (lookback, options[..options.len() - 1].join(" ")),
+        _ => return usage(bot, command),
+    };
+    let users = bot
+        .moderation
+        .users_who_said(&phrase, lookback, Instant::now());
+    let mut commands = vec![SendMessage(
+        bot.messages
+            .format("nuke", &[("count", &users.len()), ("phrase", &phrase)]),
+    )];
+    let reason = bot.messages.format("nuke.reason", &[("phrase", &phrase)]);
+    commands.extend(users.into_iter().map(|user| {
+        Moderate(ModerationAction::Timeout {
+            user,
+            duration: NUKE_TIMEOUT,
+            reason: Some(reason.to_owned()),
+        })
+    }));
+    Some(MultipleCommands(commands))
+}
+
+fn slow(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first().map(String::as_str) {
+        Some("off") => moderate(ModerationAction::SlowMode(None)),
+        Some(delay) => match parse_duration(delay) {
+            Some(delay) if delay.is_zero() => moderate(ModerationAction::SlowMode(None)),
+            Some(delay) => moderate(ModerationAction::SlowMode(Some(delay))),
+            None => usage(bot, command),
+        },
+        None => usage(bot, command),
+    }
+}
+
+fn followers(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first().map(String::as_str) {
+        Some("off") => moderate(ModerationAction::FollowersOnly(None)),
+        Some(follow_time) => match parse_duration(follow_time) {
+            Some(follow_time) => moderate(ModerationAction::FollowersOnly(Some(follow_time))),
+            None => usage(bot, command),
+        },
+        None => moderate(ModerationAction::FollowersOnly(Some(Duration::ZERO))),
+    }
+}
+
+fn emote_only(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first().map(String::as_str) {
+        Some("on") => moderate(ModerationAction::EmoteOnly(true)),
+        Some("off") => moderate(ModerationAction::EmoteOnly(false)),
+        _ => usage(bot, command),
+    }
+}
+
+fn lockdown(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first().map(String::as_str) {
+        Some("on") => bot
+            .moderation
+            .lockdown()
+            .or_else(|| bot.reply("lockdown.active", &[])),
+        Some("off") => bot
+            .moderation
+            .end_lockdown()
+            .or_else(|| bot.reply("lockdown.inactive", &[])),
+        _ => usage(bot, command),
+    }
+}
+
+fn last_seen(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => Some(SendMessage(
+            bot.last_seen_message(user_name.trim_start_matches('@')),
+        )),
+        None => usage(bot, command),
+    }
+}
+
+fn logs(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => {
+            let count = command
+                .options
+                .get(1)
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(DEFAULT_LOG_COUNT)
+                .clamp(1, MAX_LOG_COUNT);
+            Some(SendMessage(
+                bot.logs_message(user_name.trim_start_matches('@'), count),
+            ))
+        }
+        None => usage(bot, command),
+    }
+}
+
+fn watchtime(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let user_name = command.options.first().unwrap_or(&command.user.name);
+    Some(SendMessage(
+        bot.watchtime_message(user_name.trim_start_matches('@')),
+    ))
+}
+
+fn first_seen(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let user_name = command.options.first().unwrap_or(&command.user.name);
+    Some(SendMessage(
+        bot.first_seen_message(user_name.trim_start_matches('@')),
+    ))
+}
+
+fn stats(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => Some(SendMessage(
+            bot.user_stats_message(user_name.trim_start_matches('@')),
+        )),
+        None => Some(SendMessage(bot.stream_stats_message())),
+    }
+}
+
+fn shoutout(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        Some(user_name) => Some(
+            bot.shoutouts
+                .shoutout(user_name.trim_start_matches('@'), Instant::now())
+                .unwrap_or_else(|remaining| {
+                    SendMessage(bot.messages.format(
+                        "shoutout.cooldown",
+                        &[("duration", &bot.duration(remaining))],
+                    ))
+                }),
+        ),
+        None => usage(bot, command),
+    }
+}
+
+// Shows the title and game to everyone, moderators can change them
+fn stream_info(
+    bot: &mut ChatBot,
+    command: &Command,
+    change: fn(String) -> TwitchRequest,
+) -> Option<ChatBotCommand> {
+    if command.options.is_empty() {
+        Some(
+            bot.stream_info
+                .request(TwitchRequest::StreamInfo, Instant::now()),
+        )
+    } else if command.user.has_elevated_rights() {
+        Some(CallTwitch(change(command.options.join(" "))))
+    } else {
+        bot.reply("denied", &[])
+    }
+}
+
+fn title(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    stream_info(bot, command, TwitchRequest::SetTitle)
+}
+
+fn game(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    stream_info(bot, command, TwitchRequest::SetGame)
+}
+
+fn reward(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let text = command.options.join(" ");
+    let (title, action) = match text.split_once('|') {
+        Some((title, action)) if !title.trim().is_empty() => (title.trim(), action.trim()),
+        _ => return usage(bot, command),
+    };
+    let new = if action.eq_ignore_ascii_case("none") {
+        None
+    } else {
+        match RewardAction::parse(action) {
+            Some(action) => Some(action),
+            None => return usage(bot, command),
+        }
+    };
+    let reply = match &new {
+        Some(action) => bot.messages.format(
+            "reward.bound",
+            &[
+                ("title", &title),
+                ("action", &action.description(&bot.messages)),
+            ],
+        ),
+        None => bot.messages.format("reward.unbound", &[("title", &title)]),
+    };
+    let old = bot.redemptions.action(title);
+    bot.redemptions.bind(title, new.to_owned());
+    audit(
+        bot,
+        command,
+        vec![Change::Reward {
+            title: title.to_owned(),
+            old,
+            new,
+        }],
+    );
+    Some(SendMessage(reply))
+}
+
+fn rewards(bot: &mut ChatBot, _: &Command) -> Option<ChatBotCommand> {
+    let rewards: Vec<String> = bot
+        .redemptions
+        .bindings()
+        .into_iter()
+        .map(|(title, action)| {
+            bot.messages.format(
+                "rewards.item",
+                &[
+                    ("title", &title),
+                    ("action", &action.description(&bot.messages)),
+                ],
+            )
+        })
+        .collect();
+    if rewards.is_empty() {
+        bot.reply("rewards.none", &[])
+    } else {
+        bot.reply("rewards", &[("rewards", &rewards.join(" | "))])
+    }
+}
+
+fn responder(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let options: Vec<&str> = command.options.iter().map(String::as_str).collect();
+    let (action, name) = match options[..] {
+        [action, name, ..] => (action.to_lowercase(), name),
+        _ => return usage(bot, command),
+    };
+    let existing = bot.responders.get(name);
+    let responder = match (action.as_str(), existing, &options[2..]) {
+        ("add", existing, [mode, ..]) => {
+            let text = options[3..].join(" ");
+            match (MatchMode::parse(mode), text.split_once(" | ")) {
+                (Some(mode), Some((pattern, response)))
+                    if !pattern.trim().is_empty() && !response.trim().is_empty() =>
+                {
+                    AutoResponder {
+                        name: name.to_lowercase(),
+                        pattern: pattern.trim().to_owned(),
+                        mode,
+                        response: response.trim().to_owned(),
+                        // a replaced responder keeps its settings
+                        cooldown_seconds: existing
+                            .as_ref()
+                            .map_or(0, |existing| existing.cooldown_seconds),
+                        permission: existing
+                            .as_ref()
+                            .map_or(Permission::Everyone, |existing| existing.permission),
+                    }
+                }
+                _ => return usage(bot, command),
+            }
+        }
+        (_, None, _) => return bot.reply("responder.unknown", &[("name", &name)]),
+        ("remove", Some(existing), []) => {
+            bot.responders.remove(name);
+            let reply = bot
+                .messages
+                .format("responder.removed", &[("name", &existing.name)]);
+            audit(
+                bot,
+                command,
+                vec![Change::Responder {
+                    name: existing.name.to_lowercase(),
+                    old: Some(existing),
+                    new: None,
+                }],
+            );
+            return Some(SendMessage(reply));
+        }
+        ("cooldown", Some(existing), [duration]) => match parse_duration(duration) {
+            Some(duration) => AutoResponder {
+                cooldown_seconds: duration.as_secs(),
+                ..existing
+            },
+            None => return usage(bot, command),
+        },
+        ("permission", Some(existing), [permission]) => {
+            let permission = match permission.to_lowercase().as_str() {
+                "everyone" => Permission::Everyone,
+                "moderator" | "moderators" => Permission::Moderator,
+                _ => return usage(bot, command),
+            };
+            AutoResponder {
+                permission,
+                ..existing
+            }
+        }
+        _ => return usage(bot, command),
+    };
+    let name = responder.name.to_owned();
+    let old = bot.responders.get(&name);
+    match bot.responders.set(responder.to_owned()) {
+        Ok(()) => {
+            let reply = bot.messages.format("responder.saved", &[("name", &name)]);
+            audit(
+                bot,
+                command,
+                vec![Change::Responder {
+                    name: name.to_lowercase(),
+                    old,
+                    new: Some(responder),
+                }],
+            );
+            Some(SendMessage(reply))
+        }
+        Err(ResponderError::Regex(_)) => bot.reply("responder.invalid_pattern", &[("name", &name)]),
+        Err(err) => {
+            println!("Could not save auto responder: {:?}", err);
+            bot.reply("responder.not_saved", &[])
+        }
+    }
+}
+
+fn responders(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let responders: Vec<String> = bot
+        .responders
+        .responders()
+        .into_iter()
+        .map(|responder| format!("{} (\"{}\")", responder.name, responder.pattern))
+        .collect();
+    if responders.is_empty() {
+        return bot.reply("responders.none", &[]);
+    }
+    let pages = pages(&responders, HELP_PAGE_LENGTH);
+    let page = command
+        .options
+        .first()
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1usize)
+        .max(1);
+    match pages.get(page - 1) {
+        Some(responders) => bot.reply(
+            "responders",
+            &[
+                ("page", &page),
+                ("pages", &pages.len()),
+                ("responders", responders),
+            ],
+        ),
+        None => bot.reply("responders.no_page", &[("pages", &pages.len())]),
+    }
+}
+
+fn quote(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let count = bot.redemptions.quote_count();
+    let number = match command.options.first() {
+        Some(number) => number.parse().ok(),
+        // a pseudo random quote is good enough for chat
+        None if count > 0 => Some(Utc::now().timestamp_subsec_nanos() as usize % count + 1),
+        None => None,
+    };
+    match number.and_then(|number| bot.redemptions.quote(number).map(|quote| (number, quote))) {
+        Some((number, quote)) => bot.reply(
+            "quote",
+            &[
+                ("number", &number),
+                ("text", &quote.text),
+                ("user", &quote.user),
+                ("date", &quote.time.format(DATE_FORMAT)),
+            ],
+        ),
+        None if count == 0 => bot.reply("quotes.none", &[]),
+        None => bot.reply("quotes.pick", &[("count", &count)]),
+    }
+}
+
+fn top_cheerers(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    let this_stream = command
+        .options
+        .first()
+        .is_some_and(|option| option.eq_ignore_ascii_case("stream"));
+    let cheerers: Vec<String> = bot
+        .cheers
+        .top_cheerers(this_stream)
+        .iter()
+        .map(|cheerer| format!("{} ({})", cheerer.user, cheerer.bits))
+        .collect();
+    let key = if cheerers.is_empty() {
+        "top_cheerers.none"
+    } else if this_stream {
+        "top_cheerers.stream"
+    } else {
+        "top_cheerers"
+    };
+    bot.reply(key, &[("cheerers", &cheerers.join(", "))])
+}
+
+fn bit_goal(bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+    match command.options.first() {
+        None => Some(SendMessage(bot.cheers.goal_message())),
+        Some(_) if !command.user.has_elevated_rights() => bot.reply("denied", &[]),
+        Some(bits) => match bits.parse() {
+            Ok(bits) => {
+                bot.cheers.set_goal(bits);
+                Some(SendMessage(bot.cheers.goal_message()))
+            }
+            Err(_) => usage(bot, command),
+        },
+    }
+}
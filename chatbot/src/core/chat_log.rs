@@ -0,0 +1,392 @@
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+const FILE_EXTENSION: &str = "jsonl";
+// messages a user wrote within this time before being banned are labelled as spam
+const BANNED_MESSAGES_MINUTES: i64 = 10;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ChatLogConfig {
+    pub enabled: bool,
+    // log files older than this are deleted, 0 keeps them forever
+    pub retention_days: u64,
+}
+
+impl Default for ChatLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LogEvent {
+    Message {
+        id: String,
+        text: String,
+        badges: Vec<String>,
+        first_message: bool,
+    },
+    Command {
+        name: String,
+        options: Vec<String>,
+    },
+    Join,
+    Part,
+    Deleted {
+        id: String,
+        text: String,
+    },
+    Ban,
+    Timeout {
+        seconds: u64,
+    },
+    Raid {
+        viewers: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub time: DateTime<Utc>,
+    pub user: String,
+    #[serde(flatten)]
+    pub event: LogEvent,
+}
+
+/// Filters for searching the chat log, empty filters match everything
+#[derive(Debug, Default)]
+pub struct LogQuery {
+    pub user: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    // case insensitive text of messages
+    pub text: Option<String>,
+    pub messages_only: bool,
+    // maximum number of results, 0 means no limit
+    pub limit: usize,
+}
+
+impl LogQuery {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        let text_matches = match (&self.text, &entry.event) {
+            (None, _) => true,
+            (Some(text), LogEvent::Message { text: message, .. }) => {
+                message.to_lowercase().contains(&text.to_lowercase())
+            }
+            (Some(_), _) => false,
+        };
+        self.user
+            .as_ref()
+            .is_none_or(|user| user.eq_ignore_ascii_case(&entry.user))
+            && self.from.is_none_or(|from| entry.time >= from)
+            && self.to.is_none_or(|to| entry.time <= to)
+            && (!self.messages_only || matches!(entry.event, LogEvent::Message { .. }))
+            && text_matches
+    }
+}
+
+/// Chat log written to one JSON Lines file per day
+#[derive(Debug)]
+pub struct ChatLog {
+    directory: PathBuf,
+    enabled: bool,
+    retention_days: u64,
+    current_date: Option<NaiveDate>,
+    // the directory is removed when the log is dropped
+    temporary: bool,
+}
+
+impl ChatLog {
+    pub fn open(directory: &str, config: &ChatLogConfig) -> Result<Self, io::Error> {
+        fs::create_dir_all(directory)?;
+        Ok(Self {
+            directory: PathBuf::from(directory),
+            enabled: config.enabled,
+            retention_days: config.retention_days,
+            current_date: None,
+            temporary: false,
+        })
+    }
+
+    /// Chat log in a new temporary directory
+    #[cfg(test)]
+    pub fn temporary() -> Result<Self, io::Error> {
+        let directory = std::env::temp_dir().join(format!("chatbot_log_{}", uuid::Uuid::new_v4()));
+        let mut chat_log = Self::open(directory.to_str().unwrap(), &ChatLogConfig::default())?;
+        chat_log.temporary = true;
+        Ok(chat_log)
+    }
+
+    fn file_path(&self, date: NaiveDate) -> PathBuf {
+        self.directory
+            .join(format!("{}.{}", date.format("%Y-%m-%d"), FILE_EXTENSION))
+    }
+
+    // Dates of all log files, newest first
+    fn log_dates(&self) -> Result<Vec<NaiveDate>, io::Error> {
+        let mut dates: Vec<NaiveDate> = fs::read_dir(&self.directory)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != FILE_EXTENSION {
+                    return None;
+                }
+                NaiveDate::parse_from_str(path.file_stem()?.to_str()?, "%Y-%m-%d").ok()
+            })
+            .collect();
+        dates.sort_unstable_by(|first, second| second.cmp(first));
+        Ok(dates)
+    }
+
+    fn delete_old_files(&self, today: NaiveDate) -> Result<(), io::Error> {
+        if self.retention_days == 0 {
+            return Ok(());
+        }
+        let oldest = today - ChronoDuration::days(self.retention_days as i64);
+        for date in self.log_dates()? {
+            if date < oldest {
+                fs::remove_file(self.file_path(date))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the entry to the file of its day
+    pub fn append(&mut self, entry: &LogEntry) -> Result<(), io::Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let date = entry.time.date_naive();
+        if self.current_date != Some(date) {
+            self.current_date = Some(date);
+            self.delete_old_files(date)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.file_path(date))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
+
+    fn read_file(path: &Path) -> Result<Vec<LogEntry>, io::Error> {
+        let file = fs::File::open(path)?;
+        Ok(BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect())
+    }
+
+    /// Entries matching the query, newest first
+    pub fn search(&self, query: &LogQuery) -> Result<Vec<LogEntry>, io::Error> {
+        let mut results = Vec::default();
+        for date in self.log_dates()? {
+            if query.to.is_some_and(|to| date > to.date_naive()) {
+                continue;
+            }
+            if query.from.is_some_and(|from| date < from.date_naive()) {
+                break;
+            }
+            let entries = Self::read_file(&self.file_path(date))?;
+            for entry in entries.into_iter().rev() {
+                if query.matches(&entry) {
+                    results.push(entry);
+                    if results.len() == query.limit {
+                        return Ok(results);
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// All logged messages labelled for training the spam classifier ("spam<TAB>text" or
+    /// "ham<TAB>text"). Deleted messages and the last messages before a ban are spam.
+    pub fn labelled_messages(&self) -> Result<String, io::Error> {
+        let mut entries = self.search(&LogQuery::default())?;
+        entries.reverse();
+        let deleted: HashSet<&str> = entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                LogEvent::Deleted { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        let bans: HashMap<String, DateTime<Utc>> = entries
+            .iter()
+            .filter(|entry| entry.event == LogEvent::Ban)
+            .map(|entry| (entry.user.to_lowercase(), entry.time))
+            .collect();
+        let lines: Vec<String> = entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                LogEvent::Message { id, text, .. } => {
+                    let banned = bans.get(&entry.user.to_lowercase()).is_some_and(|ban| {
+                        *ban >= entry.time
+                            && *ban - entry.time <= ChronoDuration::minutes(BANNED_MESSAGES_MINUTES)
+                    });
+                    let label = if deleted.contains(id.as_str()) || banned {
+                        "spam"
+                    } else {
+                        "ham"
+                    };
+                    Some(format!("{}\t{}", label, text.replace(['\t', '\n'], " ")))
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(lines.join("\n"))
+    }
+}
+
+impl Drop for ChatLog {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.directory);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn message(time: DateTime<Utc>, user: &str, id: &str, text: &str) -> LogEntry {
+        LogEntry {
+            time,
+            user: user.to_owned(),
+            event: LogEvent::Message {
+                id: id.to_owned(),
+                text: text.to_owned(),
+                badges: Vec::default(),
+                first_message: false,
+            },
+        }
+    }
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, 1, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn writing_daily_files() {
+        let mut chat_log = ChatLog::temporary().unwrap();
+        chat_log
+            .append(&message(day(1), "carkhy", "1", "hello"))
+            .unwrap();
+        chat_log
+            .append(&message(day(2), "carkhy", "2", "hi"))
+            .unwrap();
+        assert_eq!(
+            chat_log.log_dates().unwrap(),
+            vec![day(2).date_naive(), day(1).date_naive()]
+        );
+    }
+
+    #[test]
+    fn searching_entries() {
+        let mut chat_log = ChatLog::temporary().unwrap();
+        let entries = [
+            message(day(1), "carkhy", "1", "Hello there"),
+            message(day(2), "viewer", "2", "hello"),
+            LogEntry {
+                time: day(3),
+                user: "Carkhy".to_owned(),
+                event: LogEvent::Join,
+            },
+            message(day(4), "carkhy", "3", "bye"),
+        ];
+        for entry in &entries {
+            chat_log.append(entry).unwrap();
+        }
+        let search = |query| chat_log.search(&query).unwrap();
+        assert_eq!(
+            search(LogQuery {
+                user: Some("CARKHY".to_owned()),
+                ..Default::default()
+            }),
+            vec![entries[3].clone(), entries[2].clone(), entries[0].clone()]
+        );
+        assert_eq!(
+            search(LogQuery {
+                user: Some("carkhy".to_owned()),
+                messages_only: true,
+                limit: 1,
+                ..Default::default()
+            }),
+            vec![entries[3].clone()]
+        );
+        assert_eq!(
+            search(LogQuery {
+                text: Some("hello".to_owned()),
+                from: Some(day(2)),
+                to: Some(day(3)),
+                ..Default::default()
+            }),
+            vec![entries[1].clone()]
+        );
+    }
+
+    #[test]
+    fn deleting_old_files() {
+        let directory = std::env::temp_dir().join(format!("chatbot_log_{}", uuid::Uuid::new_v4()));
+        let mut chat_log = ChatLog::open(
+            directory.to_str().unwrap(),
+            &ChatLogConfig {
+                enabled: true,
+                retention_days: 2,
+            },
+        )
+        .unwrap();
+        chat_log.temporary = true;
+        chat_log
+            .append(&message(day(1), "carkhy", "1", "hello"))
+            .unwrap();
+        chat_log
+            .append(&message(day(4), "carkhy", "2", "hi"))
+            .unwrap();
+        assert_eq!(chat_log.log_dates().unwrap(), vec![day(4).date_naive()]);
+    }
+
+    #[test]
+    fn labelling_messages() {
+        let mut chat_log = ChatLog::temporary().unwrap();
+        let entries = [
+            message(day(1), "carkhy", "1", "hello"),
+            message(day(1), "spammer", "2", "buy followers"),
+            message(day(1), "raider", "3", "hate"),
+            LogEntry {
+                time: day(1),
+                user: "spammer".to_owned(),
+                event: LogEvent::Deleted {
+                    id: "2".to_owned(),
+                    text: "buy followers".to_owned(),
+                },
+            },
+            LogEntry {
+                time: day(1),
+                user: "raider".to_owned(),
+                event: LogEvent::Ban,
+            },
+        ];
+        for entry in &entries {
+            chat_log.append(entry).unwrap();
+        }
+        assert_eq!(
+            chat_log.labelled_messages().unwrap(),
+            "ham\thello\nspam\tbuy followers\nspam\thate"
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use super::{ChatBotCommand, Messages, Storage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const TOTALS_BUCKET: &str = "cheer_totals";
+const STREAMS_BUCKET: &str = "cheer_streams";
+const SETTINGS_BUCKET: &str = "cheer_settings";
+const OVERLAY_BUCKET: &str = "overlay_events";
+const GOAL_KEY: &str = "goal";
+const TOP_CHEERERS: usize = 5;
+// overlays only need the recent events, older ones are removed
+const OVERLAY_EVENTS: usize = 100;
+
+/// What the bot does when a cheer reaches a threshold
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CheerAction {
+    // posts the text, which supports the variables {user}, {bits} and {total}
+    Message { text: String },
+    // queues an event for a stream overlay, served by the HTTP API
+    Overlay { event: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CheerThreshold {
+    pub bits: u64,
+    pub action: CheerAction,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CheerConfig {
+    // bits to collect per stream, 0 means no goal; a goal set with !bitgoal takes precedence
+    pub goal: u64,
+    // only the highest threshold a cheer reaches is triggered
+    pub thresholds: Vec<CheerThreshold>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cheerer {
+    pub user: String,
+    pub bits: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheerStream {
+    pub started: DateTime<Utc>,
+    pub bits: u64,
+    // bits per user
+    pub cheerers: BTreeMap<String, u64>,
+    pub goal_reached: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlayEvent {
+    pub time: DateTime<Utc>,
+    pub event: String,
+    pub user: String,
+    pub bits: u64,
+}
+
+/// Counts the bits cheered in chat per stream and overall, tracks the stream's goal and
+/// triggers the configured threshold actions.
+/// A stream lasts from the start of the bot or the stream going online until it goes online
+/// again; every cheer is saved right away.
+#[derive(Debug)]
+pub struct Cheers {
+    storage: Storage,
+    config_goal: u64,
+    thresholds: Vec<CheerThreshold>,
+    stream: CheerStream,
+    messages: Messages,
+    // tells apart the overlay events of the same millisecond
+    overlay_sequence: u64,
+}
+
+impl Cheers {
+    pub fn new(
+        config: &CheerConfig,
+        storage: Storage,
+        messages: Messages,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut thresholds = config.thresholds.to_owned();
+        thresholds.sort_by_key(|threshold| threshold.bits);
+        Self {
+            storage,
+            config_goal: config.goal,
+            thresholds,
+            stream: CheerStream {
+                started: now,
+                bits: 0,
+                cheerers: BTreeMap::default(),
+                goal_reached: false,
+            },
+            messages,
+            overlay_sequence: 0,
+        }
+    }
+
+    /// Starts counting the bits of a new stream when Twitch reports the stream online.
+    pub fn stream_started(&mut self, started_at: DateTime<Utc>) {
+        if started_at <= self.stream.started {
+            return;
+        }
+        self.stream = CheerStream {
+            started: started_at,
+            bits: 0,
+            cheerers: BTreeMap::default(),
+            goal_reached: false,
+        };
+    }
+
+    pub fn goal(&self) -> u64 {
+        self.storage
+            .get::<u64>(SETTINGS_BUCKET, GOAL_KEY)
+            .unwrap_or_else(|err| {
+                println!("Could not load bit goal: {:?}", err);
+                None
+            })
+            .unwrap_or(self.config_goal)
+    }
+
+    /// Sets the goal of the stream, a new goal can be reached again
+    pub fn set_goal(&mut self, bits: u64) {
+        if let Err(err) = self.storage.set(SETTINGS_BUCKET, GOAL_KEY, bits) {
+            println!("Could not save bit goal: {:?}", err);
+        }
+        self.stream.goal_reached = bits > 0 && self.stream.bits >= bits;
+        self.save_stream();
+    }
+
+    pub fn goal_message(&self) -> String {
+        match self.goal() {
+            0 => self
+                .messages
+                .format("cheers.total", &[("bits", &self.stream.bits)]),
+            goal => self.messages.format(
+                "cheers.goal",
+                &[
+                    ("bits", &self.stream.bits),
+                    ("goal", &goal),
+                    ("percent", &(self.stream.bits * 100 / goal).min(100)),
+                ],
+            ),
+        }
+    }
+
+    fn save_stream(&self) {
+        if let Err(err) = self.storage.set(
+            STREAMS_BUCKET,
+            &self.stream.started.to_rfc3339(),
+            self.stream.to_owned(),
+        ) {
+            println!("Could not save cheers of the stream: {:?}", err);
+        }
+    }
+
+    fn add_to_total(&self, user_name: &str, bits: u64) {
+        let key = user_name.to_lowercase();
+        let total = match self.storage.get::<Cheerer>(TOTALS_BUCKET, &key) {
+            Ok(Some(cheerer)) => cheerer.bits + bits,
+            Ok(None) => bits,
+            Err(err) => {
+                println!("Could not load cheer total: {:?}", err);
+                bits
+            }
+        };
+        let cheerer = Cheerer {
+            user: user_name.to_owned(),
+            bits: total,
+        };
+        if let Err(err) = self.storage.set(TOTALS_BUCKET, &key, cheerer) {
+            println!("Could not save cheer total: {:?}", err);
+        }
+    }
+
+    fn add_overlay_event(&mut self, event: &str, user_name: &str, bits: u64, now: DateTime<Utc>) {
+        let overlay_event = OverlayEvent {
+            time: now,
+            event: event.to_owned(),
+            user: user_name.to_owned(),
+            bits,
+        };
+        // zero padded milliseconds and sequence numbers keep the keys in order
+        let key = format!(
+            "{:020}-{:020}",
+            now.timestamp_millis(),
+            self.overlay_sequence
+        );
+        self.overlay_sequence += 1;
+        if let Err(err) = self.storage.set(OVERLAY_BUCKET, &key, overlay_event) {
+            println!("Could not save overlay event: {:?}", err);
+        }
+        self.trim_overlay_events();
+    }
+
+    fn trim_overlay_events(&self) {
+        let keys: Vec<String> = match self.storage.entries::<OverlayEvent>(OVERLAY_BUCKET) {
+            Ok(entries) => entries.into_iter().map(|(key, _)| key).collect(),
+            Err(err) => {
+                println!("Could not load overlay events: {:?}", err);
+                return;
+            }
+        };
+        for key in keys.iter().take(keys.len().saturating_sub(OVERLAY_EVENTS)) {
+            if let Err(err) = self.storage.remove(OVERLAY_BUCKET, key) {
+                println!("Could not remove overlay event: {:?}", err);
+            }
+        }
+    }
+
+    /// Records the cheer, returns the messages of the reached threshold and goal
+    pub fn cheer(&mut self, user_name: &str, bits: u64, now: DateTime<Utc>) -> Vec<ChatBotCommand> {
+        let mut commands = Vec::default();
+        self.stream.bits += bits;
+        *self
+            .stream
+            .cheerers
+            .entry(user_name.to_lowercase())
+            .or_default() += bits;
+        self.add_to_total(user_name, bits);
+
+        let action = self
+            .thresholds
+            .iter()
+            .rev()
+            .find(|threshold| bits >= threshold.bits)
+            .map(|threshold| threshold.action.to_owned());
+        match action {
+            Some(CheerAction::Message { text }) => {
+                commands.push(ChatBotCommand::SendMessage(
+                    text.replace("{user}", user_name)
+                        .replace("{bits}", &bits.to_string())
+                        .replace("{total}", &self.stream.bits.to_string()),
+                ));
+            }
+            Some(CheerAction::Overlay { event }) => {
+                self.add_overlay_event(&event, user_name, bits, now)
+            }
+            None => {}
+        }
+
+        let goal = self.goal();
+        if goal > 0 && !self.stream.goal_reached && self.stream.bits >= goal {
+            self.stream.goal_reached = true;
+            commands.push(ChatBotCommand::SendMessage(
+                self.messages
+                    .format("cheers.goal_reached", &[("goal", &goal)]),
+            ));
+        }
+        self.save_stream();
+        commands
+    }
+
+    /// The users who cheered the most bits, in this stream or overall
+    pub fn top_cheerers(&self, this_stream: bool) -> Vec<Cheerer> {
+        let mut cheerers: Vec<Cheerer> = if this_stream {
+            self.stream
+                .cheerers
+                .iter()
+                .map(|(user, bits)| Cheerer {
+                    user: user.to_owned(),
+                    bits: *bits,
+                })
+                .collect()
+        } else {
+            self.storage
+                .entries::<Cheerer>(TOTALS_BUCKET)
+                .unwrap_or_else(|err| {
+                    println!("Could not load cheer totals: {:?}", err);
+                    Vec::default()
+                })
+                .into_iter()
+                .map(|(_, cheerer)| cheerer)
+                .collect()
+        };
+        cheerers.sort_by(|first, second| {
+            second
+                .bits
+                .cmp(&first.bits)
+                .then_with(|| first.user.cmp(&second.user))
+        });
+        cheerers.truncate(TOP_CHEERERS);
+        cheerers
+    }
+}
+
+/// Overlay events after the given time in milliseconds since the epoch, oldest first
+pub fn overlay_events(
+    storage: &Storage,
+    since_millis: i64,
+) -> Result<Vec<OverlayEvent>, kv::Error> {
+    Ok(storage
+        .entries::<OverlayEvent>(OVERLAY_BUCKET)?
+        .into_iter()
+        .map(|(_, event)| event)
+        .filter(|event| event.time.timestamp_millis() > since_millis)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn cheers(storage: Storage, now: DateTime<Utc>) -> Cheers {
+        let config: CheerConfig = serde_json::from_str(
+            r#"{"goal": 500, "thresholds": [
+                {"bits": 1000, "action": {"type": "overlay", "event": "confetti"}},
+                {"bits": 100, "action": {"type": "message", "text": "Thank you {user} for {bits} bits! {total} bits this stream."}}
+            ]}"#,
+        )
+        .unwrap();
+        Cheers::new(&config, storage, Messages::default(), now)
+    }
+
+    #[test]
+    fn triggering_thresholds_and_the_goal() {
+        let storage = Storage::temporary().unwrap();
+        let mut cheers = cheers(storage.clone(), now());
+        assert_eq!(cheers.cheer("carkhy", 50, now()), vec![]);
+        assert_eq!(
+            cheers.cheer("Carkhy", 150, now()),
+            vec![ChatBotCommand::SendMessage(
+                "Thank you Carkhy for 150 bits! 200 bits this stream.".to_owned()
+            )]
+        );
+        assert_eq!(cheers.goal_message(), "Bit goal: 200/500 (40%)");
+
+        // only the highest threshold is triggered, the goal is announced once
+        let later = now() + ChronoDuration::seconds(1);
+        assert_eq!(
+            cheers.cheer("botanist", 1000, later),
+            vec![ChatBotCommand::SendMessage(
+                "The bit goal of 500 bits has been reached, thank you all!".to_owned()
+            )]
+        );
+        assert_eq!(cheers.cheer("botanist", 10, later), vec![]);
+        assert_eq!(
+            overlay_events(&storage, 0).unwrap(),
+            vec![OverlayEvent {
+                time: later,
+                event: "confetti".to_owned(),
+                user: "botanist".to_owned(),
+                bits: 1000,
+            }]
+        );
+        assert!(overlay_events(&storage, later.timestamp_millis())
+            .unwrap()
+            .is_empty());
+
+        cheers.set_goal(2000);
+        assert_eq!(cheers.goal_message(), "Bit goal: 1210/2000 (60%)");
+    }
+
+    #[test]
+    fn keeping_recent_overlay_events() {
+        let storage = Storage::temporary().unwrap();
+        let mut cheers = cheers(storage.clone(), now());
+        for bits in 1000..1000 + OVERLAY_EVENTS as u64 + 5 {
+            cheers.cheer("carkhy", bits, now());
+        }
+        let events = overlay_events(&storage, 0).unwrap();
+        assert_eq!(events.len(), OVERLAY_EVENTS);
+        assert_eq!(events[0].bits, 1005);
+        assert_eq!(events[OVERLAY_EVENTS - 1].bits, 1104);
+    }
+
+    #[test]
+    fn ranking_cheerers_per_stream_and_overall() {
+        let storage = Storage::temporary().unwrap();
+        let mut cheers = cheers(storage.clone(), now());
+        cheers.cheer("carkhy", 300, now());
+        cheers.cheer("botanist", 100, now());
+
+        // the stream going online starts a new stream
+        cheers.stream_started(now() + ChronoDuration::hours(1));
+        assert_eq!(cheers.top_cheerers(true), vec![]);
+        assert_eq!(cheers.top_cheerers(false).len(), 2);
+
+        // the goal and the totals persist across streams
+        cheers.set_goal(1000);
+        let next_stream = now() + ChronoDuration::days(1);
+        let mut cheers = self::cheers(storage, next_stream);
+        cheers.cheer("Botanist", 250, next_stream);
+        assert_eq!(cheers.goal_message(), "Bit goal: 250/1000 (25%)");
+        assert_eq!(
+            cheers.top_cheerers(true),
+            vec![Cheerer {
+                user: "botanist".to_owned(),
+                bits: 250
+            }]
+        );
+        assert_eq!(
+            cheers.top_cheerers(false),
+            vec![
+                Cheerer {
+                    user: "Botanist".to_owned(),
+                    bits: 350
+                },
+                Cheerer {
+                    user: "carkhy".to_owned(),
+                    bits: 300
+                },
+            ]
+        );
+    }
+}
@@ -1,13 +1,43 @@
 use std::time::Duration;
 
-use crate::connect::ChatBotEvent;
+use crate::connect::{ChatBotEvent, ModerationAction, TwitchRequest};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ChatBotCommand {
     SendMessage(String),
     LogTextMessage(String),
     // bot registers to be called back with the specified event
-    TimedCallback{duration: Duration, event: ChatBotEvent},
+    TimedCallback {
+        duration: Duration,
+        event: ChatBotEvent,
+    },
     // bot sends more than one command
     MultipleCommands(Vec<ChatBotCommand>),
+    // bot moderates the channel (requires the bot to be a moderator)
+    Moderate(ModerationAction),
+    // bot asks the Twitch API, answered with ChatBotEvent::TwitchResponse
+    CallTwitch(TwitchRequest),
+}
+
+impl ChatBotCommand {
+    /// Strips a leading "/" or "." from the messages, which twitch would run as a chat command
+    /// such as "/ban", so text built from user input cannot moderate the channel
+    pub fn without_chat_commands(self) -> Self {
+        match self {
+            Self::SendMessage(message) => Self::SendMessage(
+                message
+                    .trim_start_matches(|character: char| {
+                        character == '/' || character == '.' || character.is_whitespace()
+                    })
+                    .to_owned(),
+            ),
+            Self::MultipleCommands(commands) => Self::MultipleCommands(
+                commands
+                    .into_iter()
+                    .map(Self::without_chat_commands)
+                    .collect(),
+            ),
+            command => command,
+        }
+    }
 }
@@ -0,0 +1,444 @@
+use super::{ChatBot, ChatBotCommand, Storage};
+use crate::connect::{Command, UserInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+const ALIASES_BUCKET: &str = "command_aliases";
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+    // what messages start with to be commands, e.g. "!" or "?"
+    pub prefix: String,
+    // command name by alias, aliases added in chat take precedence
+    pub aliases: HashMap<String, String>,
+}
+
+impl Default for CommandConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "!".to_owned(),
+            aliases: HashMap::default(),
+        }
+    }
+}
+
+/// Who may use a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Everyone,
+    // moderators and the broadcaster
+    Moderator,
+}
+
+impl Permission {
+    pub fn allows(&self, user: &UserInfo) -> bool {
+        match self {
+            Permission::Everyone => true,
+            Permission::Moderator => user.has_elevated_rights(),
+        }
+    }
+}
+
+/// A command of the chat bot, written in chat as `!<name>` or `!<alias>`
+pub trait CommandHandler {
+    /// The name as it is written in chat (without the '!')
+    fn name(&self) -> &str;
+
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Users without the permission are denied before the command is handled
+    fn permission(&self) -> Permission {
+        Permission::Everyone
+    }
+
+    /// The options of the command as shown by !help, e.g. "<user> [reason]"
+    fn usage(&self) -> &str {
+        ""
+    }
+
+    /// A short description of what the command does
+    fn help(&self) -> &str;
+
+    /// Time between two uses of the command, moderators are not limited
+    fn cooldown(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn handle(&self, bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand>;
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RegistryError {
+    #[error("The command name {} is already taken", .0)]
+    NameTaken(String),
+    #[error("There is no command {}", .0)]
+    UnknownCommand(String),
+}
+
+/// What the registry decided about a command of a user
+pub enum Dispatch {
+    Handler(Rc<dyn CommandHandler>),
+    Denied,
+    // the command was used too recently, it is ignored
+    CoolingDown,
+    // no handler has the name
+    Unknown,
+}
+
+/// The commands of the bot by name and alias.
+/// Besides the aliases of the handlers, aliases can be added in the config and in chat,
+/// they may also name commands that are not registered here (the dynamic commands).
+/// Checks permissions and cooldowns before a command is handled.
+pub struct CommandRegistry {
+    prefix: String,
+    storage: Storage,
+    handlers: Vec<Rc<dyn CommandHandler>>,
+    // index of the handler by name and alias
+    names: HashMap<String, usize>,
+    // command name by alias added in the config or in chat
+    aliases: HashMap<String, String>,
+    last_used: HashMap<String, Instant>,
+}
+
+impl fmt::Debug for CommandRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.handlers.iter().map(|handler| handler.name()))
+            .finish()
+    }
+}
+
+impl CommandRegistry {
+    pub fn new(config: &CommandConfig, storage: Storage) -> Self {
+        let mut aliases: HashMap<String, String> = config
+            .aliases
+            .iter()
+            .map(|(alias, name)| (alias.to_lowercase(), name.to_lowercase()))
+            .collect();
+        // no command means the alias was removed in chat
+        let stored = storage
+            .entries::<Option<String>>(ALIASES_BUCKET)
+            .unwrap_or_else(|err| {
+                println!("Could not load command aliases: {:?}", err);
+                Vec::default()
+            });
+        for (alias, name) in stored {
+            match name {
+                Some(name) => aliases.insert(alias, name),
+                None => aliases.remove(&alias),
+            };
+        }
+        Self {
+            prefix: config.prefix.to_owned(),
+            storage,
+            handlers: Vec::default(),
+            names: HashMap::default(),
+            aliases,
+            last_used: HashMap::default(),
+        }
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Adds the command, its name and aliases must not be taken yet
+    pub fn register(&mut self, handler: Rc<dyn CommandHandler>) -> Result<(), RegistryError> {
+        let names: Vec<&str> = std::iter::once(handler.name())
+            .chain(handler.aliases().iter().copied())
+            .collect();
+        if let Some(name) = names.iter().find(|name| self.names.contains_key(**name)) {
+            return Err(RegistryError::NameTaken(name.to_string()));
+        }
+        for name in names {
+            // the name of a command takes precedence over an alias of the config or chat
+            self.aliases.remove(name);
+            self.names.insert(name.to_owned(), self.handlers.len());
+        }
+        self.handlers.push(handler);
+        Ok(())
+    }
+
+    /// All commands in the order they were registered
+    pub fn handlers(&self) -> impl Iterator<Item = &Rc<dyn CommandHandler>> {
+        self.handlers.iter()
+    }
+
+    /// The handler the name or alias belongs to
+    pub fn get(&self, name: &str) -> Option<&Rc<dyn CommandHandler>> {
+        self.names
+            .get(self.resolve(name))
+            .map(|index| &self.handlers[*index])
+    }
+
+    /// The name the alias added in the config or chat stands for, other names stay as they are
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// The name of the command the name or alias belongs to
+    pub fn command_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.get(name)
+            .map_or(self.resolve(name), |handler| handler.name())
+    }
+
+    /// Whether the name is taken by a handler or an alias
+    pub fn is_taken(&self, name: &str) -> bool {
+        self.names.contains_key(name) || self.aliases.contains_key(name)
+    }
+
+    /// Adds an alias for the command, which must exist according to the caller
+    pub fn add_alias(&mut self, alias: &str, name: &str) -> Result<(), RegistryError> {
+        let alias = alias.to_lowercase();
+        if self.is_taken(&alias) {
+            return Err(RegistryError::NameTaken(alias));
+        }
+        // an alias of an alias stands for the command itself
+        let name = self.command_name(&name.to_lowercase()).to_owned();
+        if let Err(err) = self
+            .storage
+            .set(ALIASES_BUCKET, &alias, Some(name.to_owned()))
+        {
+            println!("Could not save command alias: {:?}", err);
+        }
+        self.aliases.insert(alias, name);
+        Ok(())
+    }
+
+    /// Lets the aliases of the renamed command stand for its new name
+    pub fn rename_command(&mut self, name: &str, new_name: &str) {
+        for (alias, command_name) in self.aliases.iter_mut() {
+            if command_name == name {
+                *command_name = new_name.to_owned();
+                if let Err(err) = self
+                    .storage
+                    .set(ALIASES_BUCKET, alias, Some(new_name.to_owned()))
+                {
+                    println!("Could not save command alias: {:?}", err);
+                }
+            }
+        }
+    }
+
+    /// Removes an alias added in the config or chat
+    pub fn remove_alias(&mut self, alias: &str) -> Result<(), RegistryError> {
+        let alias = alias.to_lowercase();
+        if self.aliases.remove(&alias).is_none() {
+            return Err(RegistryError::UnknownCommand(alias));
+        }
+        if let Err(err) = self.storage.set(ALIASES_BUCKET, &alias, None::<String>) {
+            println!("Could not save command alias: {:?}", err);
+        }
+        Ok(())
+    }
+
+    /// The aliases added in the config or chat and their commands, sorted by alias
+    pub fn aliases(&self) -> Vec<(&str, &str)> {
+        let mut aliases: Vec<(&str, &str)> = self
+            .aliases
+            .iter()
+            .map(|(alias, name)| (alias.as_str(), name.as_str()))
+            .collect();
+        aliases.sort();
+        aliases
+    }
+
+    /// Finds the handler of the command if the user may use it now
+    pub fn dispatch(&mut self, command: &Command, now: Instant) -> Dispatch {
+        let handler = match self.get(&command.name) {
+            Some(handler) => handler.clone(),
+            None => return Dispatch::Unknown,
+        };
+        if !handler.permission().allows(&command.user) {
+            return Dispatch::Denied;
+        }
+        if !command.user.has_elevated_rights() {
+            let cooldown = handler.cooldown();
+            match self.last_used.get(handler.name()) {
+                Some(last_used) if now.duration_since(*last_used) < cooldown => {
+                    return Dispatch::CoolingDown
+                }
+                _ => {}
+            }
+        }
+        self.last_used.insert(handler.name().to_owned(), now);
+        Dispatch::Handler(handler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::Badge;
+    use std::collections::HashSet;
+
+    struct Echo;
+
+    impl CommandHandler for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn aliases(&self) -> &[&str] {
+            &["say"]
+        }
+
+        fn permission(&self) -> Permission {
+            Permission::Moderator
+        }
+
+        fn help(&self) -> &str {
+            "Repeats the text"
+        }
+
+        fn cooldown(&self) -> Duration {
+            Duration::from_secs(30)
+        }
+
+        fn handle(&self, _bot: &mut ChatBot, command: &Command) -> Option<ChatBotCommand> {
+            Some(ChatBotCommand::SendMessage(command.options.join(" ")))
+        }
+    }
+
+    struct Ping;
+
+    impl CommandHandler for Ping {
+        fn name(&self) -> &str {
+            "ping"
+        }
+
+        fn help(&self) -> &str {
+            "Answers with pong"
+        }
+
+        fn cooldown(&self) -> Duration {
+            Duration::from_secs(30)
+        }
+
+        fn handle(&self, _bot: &mut ChatBot, _command: &Command) -> Option<ChatBotCommand> {
+            Some(ChatBotCommand::SendMessage("pong".to_owned()))
+        }
+    }
+
+    fn command(name: &str, badge: Option<&str>) -> Command {
+        Command {
+            name: name.to_owned(),
+            options: vec!["hello".to_owned()],
+            user: UserInfo {
+                name: "carkhy".to_owned(),
+                badges: badge
+                    .map(|name| {
+                        HashSet::from([Badge {
+                            name: name.to_owned(),
+                            level: 1,
+                        }])
+                    })
+                    .unwrap_or_default(),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn registry() -> CommandRegistry {
+        CommandRegistry::new(&CommandConfig::default(), Storage::temporary().unwrap())
+    }
+
+    #[test]
+    fn registering_commands() {
+        let mut registry = registry();
+        registry.register(Rc::new(Echo)).unwrap();
+        registry.register(Rc::new(Ping)).unwrap();
+        assert_eq!(
+            registry.register(Rc::new(Echo)),
+            Err(RegistryError::NameTaken("echo".to_owned()))
+        );
+        assert_eq!(registry.command_name("say"), "echo");
+        assert_eq!(registry.command_name("unknown"), "unknown");
+    }
+
+    #[test]
+    fn adding_and_removing_aliases() {
+        let storage = Storage::temporary().unwrap();
+        let config: CommandConfig =
+            serde_json::from_str(r#"{"prefix": "?", "aliases": {"p": "ping", "s": "say"}}"#)
+                .unwrap();
+        let mut registry = CommandRegistry::new(&config, storage.clone());
+        registry.register(Rc::new(Echo)).unwrap();
+        registry.register(Rc::new(Ping)).unwrap();
+        assert_eq!(registry.prefix(), "?");
+        assert_eq!(registry.command_name("p"), "ping");
+
+        registry.add_alias("Shout", "s").unwrap();
+        assert_eq!(registry.command_name("shout"), "echo");
+        assert_eq!(
+            registry.add_alias("ping", "echo"),
+            Err(RegistryError::NameTaken("ping".to_owned()))
+        );
+        // aliases may name commands that are handled elsewhere
+        registry.add_alias("hi", "greeting").unwrap();
+        assert_eq!(registry.command_name("hi"), "greeting");
+        registry.remove_alias("p").unwrap();
+        assert_eq!(
+            registry.remove_alias("p"),
+            Err(RegistryError::UnknownCommand("p".to_owned()))
+        );
+
+        // the aliases of the chat are kept
+        let registry = CommandRegistry::new(&config, storage);
+        assert_eq!(
+            registry.aliases(),
+            vec![("hi", "greeting"), ("s", "say"), ("shout", "echo")]
+        );
+    }
+
+    #[test]
+    fn checking_permissions_and_cooldowns() {
+        let mut registry = registry();
+        registry.register(Rc::new(Echo)).unwrap();
+        registry.register(Rc::new(Ping)).unwrap();
+        let now = Instant::now();
+        assert!(matches!(
+            registry.dispatch(&command("say", None), now),
+            Dispatch::Denied
+        ));
+        assert!(matches!(
+            registry.dispatch(&command("say", Some("moderator")), now),
+            Dispatch::Handler(handler) if handler.name() == "echo"
+        ));
+        assert!(matches!(
+            registry.dispatch(&command("nothing", None), now),
+            Dispatch::Unknown
+        ));
+
+        // moderators are not limited by cooldowns
+        assert!(matches!(
+            registry.dispatch(&command("ping", None), now),
+            Dispatch::Handler(_)
+        ));
+        assert!(matches!(
+            registry.dispatch(&command("ping", None), now + Duration::from_secs(10)),
+            Dispatch::CoolingDown
+        ));
+        assert!(matches!(
+            registry.dispatch(
+                &command("ping", Some("broadcaster")),
+                now + Duration::from_secs(10)
+            ),
+            Dispatch::Handler(_)
+        ));
+        assert!(matches!(
+            registry.dispatch(&command("ping", None), now + Duration::from_secs(40)),
+            Dispatch::Handler(_)
+        ));
+    }
+}
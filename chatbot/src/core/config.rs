@@ -0,0 +1,104 @@
+use super::{
+    chat_log::ChatLogConfig,
+    cheers::CheerConfig,
+    command_registry::CommandConfig,
+    messages::{MessageConfig, MessageError},
+    moderation::ModerationConfig,
+    plugins::PluginConfig,
+    redemptions::RedemptionConfig,
+    responders::ResponderConfig,
+    scripts::{ScriptConfig, ScriptError},
+    shoutout::ShoutoutConfig,
+    stats::StatsConfig,
+    stream_info::StreamInfoConfig,
+    welcome::WelcomeConfig,
+};
+use serde::Deserialize;
+use std::{fs, io};
+use thiserror::Error;
+
+/// Configuration of the bot's features, loaded from a JSON file.
+/// Every field is optional, missing fields fall back to their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    pub commands: CommandConfig,
+    pub moderation: ModerationConfig,
+    pub chat_log: ChatLogConfig,
+    pub stats: StatsConfig,
+    pub welcome: WelcomeConfig,
+    pub shoutout: ShoutoutConfig,
+    pub stream_info: StreamInfoConfig,
+    pub redemptions: RedemptionConfig,
+    pub cheers: CheerConfig,
+    pub responders: ResponderConfig,
+    pub scripts: ScriptConfig,
+    pub plugins: PluginConfig,
+    pub messages: MessageConfig,
+}
+
+#[derive(Debug, Error)]
+pub enum BotConfigError {
+    #[error("Could not read bot config file [{}]", .0)]
+    Io(#[from] io::Error),
+    #[error("Invalid bot config file [{}]", .0)]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid regular expression in bot config [{}]", .0)]
+    Regex(#[from] regex::Error),
+    #[error("Invalid script [{}]", .0)]
+    Script(#[from] ScriptError),
+    #[error("Invalid messages [{}]", .0)]
+    Messages(#[from] MessageError),
+    #[error("The command prefix must not be empty, every message would be a command")]
+    EmptyPrefix,
+}
+
+impl BotConfig {
+    /// Loads the config from the given file.
+    /// A missing file results in the default config.
+    pub fn load(path: &str) -> Result<BotConfig, BotConfigError> {
+        let config: BotConfig = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => BotConfig::default(),
+            Err(err) => return Err(err.into()),
+        };
+        if config.commands.prefix.trim().is_empty() {
+            return Err(BotConfigError::EmptyPrefix);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::moderation::FilterAction;
+
+    #[test]
+    fn missing_config_file_results_in_defaults() {
+        let config = BotConfig::load("./this_file_does_not_exist.json").unwrap();
+        assert!(config.moderation.banned_phrases.phrases.is_empty());
+    }
+
+    #[test]
+    fn rejecting_an_empty_prefix() {
+        let path = std::env::temp_dir().join(format!("bot_config_{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, r#"{"commands": {"prefix": " "}}"#).unwrap();
+        let result = BotConfig::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(BotConfigError::EmptyPrefix)));
+    }
+
+    #[test]
+    fn parsing_partial_config() {
+        let config: BotConfig = serde_json::from_str(
+            r#"{"moderation": {"banned_phrases": {"phrases": ["bad"], "action": {"type": "timeout", "seconds": 60}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.moderation.banned_phrases.phrases, vec!["bad"]);
+        assert_eq!(
+            config.moderation.banned_phrases.action,
+            FilterAction::Timeout { seconds: 60 }
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs, io,
+    rc::Rc,
+};
+use thiserror::Error;
+
+// the English texts, every other locale is checked against their keys
+const BUNDLED_MESSAGES: &str = include_str!("../../messages.json");
+const BUNDLED_LOCALE: &str = "en";
+
+// texts by key, by locale
+type Catalog = BTreeMap<String, HashMap<String, String>>;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MessageConfig {
+    // JSON file with texts by locale and key, merged over the bundled English texts
+    pub file: Option<String>,
+    pub locale: String,
+    // texts missing in the locale are taken from this one
+    pub fallback_locale: String,
+    // locales by channel, other channels use `locale`
+    pub channels: HashMap<String, String>,
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            locale: BUNDLED_LOCALE.to_owned(),
+            fallback_locale: BUNDLED_LOCALE.to_owned(),
+            channels: HashMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MessageError {
+    #[error("Could not read the message file [{}]", .0)]
+    Io(#[from] io::Error),
+    #[error("Invalid message file [{}]", .0)]
+    Json(#[from] serde_json::Error),
+    #[error("There are no messages for the locale {}", .0)]
+    UnknownLocale(String),
+    #[error("The fallback locale {} lacks the messages {}", .0, .1.join(", "))]
+    MissingKeys(String, Vec<String>),
+}
+
+fn bundled_catalog() -> Catalog {
+    serde_json::from_str(BUNDLED_MESSAGES).expect("The bundled messages are valid")
+}
+
+fn load_catalog(config: &MessageConfig) -> Result<Catalog, MessageError> {
+    let mut catalog = bundled_catalog();
+    if let Some(file) = &config.file {
+        let loaded: Catalog = serde_json::from_str(&fs::read_to_string(file)?)?;
+        for (locale, texts) in loaded {
+            catalog.entry(locale).or_default().extend(texts);
+        }
+    }
+    Ok(catalog)
+}
+
+// The keys of the bundled texts the locale lacks, sorted
+fn missing_keys(catalog: &Catalog, locale: &str) -> Vec<String> {
+    let texts = &catalog[locale];
+    let mut keys: Vec<String> = catalog[BUNDLED_LOCALE]
+        .keys()
+        .filter(|key| !texts.contains_key(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys
+}
+
+// The keys of the locale the bot does not know, most likely typos
+fn unknown_keys(catalog: &Catalog, locale: &str) -> Vec<String> {
+    let bundled = &catalog[BUNDLED_LOCALE];
+    let mut keys: Vec<String> = catalog[locale]
+        .keys()
+        .filter(|key| !bundled.contains_key(*key))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// The texts the bot posts in chat, in the locale of its channel. Placeholders like
+/// `{user}` are replaced when a text is formatted.
+#[derive(Debug, Clone)]
+pub struct Messages {
+    texts: Rc<HashMap<String, String>>,
+}
+
+impl Default for Messages {
+    /// The bundled English texts
+    fn default() -> Self {
+        Self {
+            texts: Rc::new(bundled_catalog().remove(BUNDLED_LOCALE).unwrap_or_default()),
+        }
+    }
+}
+
+impl Messages {
+    /// Loads the texts of the channel's locale. Keys missing in any locale are reported,
+    /// the fallback locale has to be complete.
+    pub fn new(config: &MessageConfig, channel: &str) -> Result<Self, MessageError> {
+        let catalog = load_catalog(config)?;
+        for locale in config
+            .channels
+            .values()
+            .chain([&config.locale, &config.fallback_locale])
+        {
+            if !catalog.contains_key(locale) {
+                return Err(MessageError::UnknownLocale(locale.to_owned()));
+            }
+        }
+        let missing = missing_keys(&catalog, &config.fallback_locale);
+        if !missing.is_empty() {
+            return Err(MessageError::MissingKeys(
+                config.fallback_locale.to_owned(),
+                missing,
+            ));
+        }
+        for locale in catalog.keys() {
+            let missing = missing_keys(&catalog, locale);
+            if !missing.is_empty() {
+                println!(
+                    "The locale {} lacks the messages {}, the locale {} is used instead",
+                    locale,
+                    missing.join(", "),
+                    config.fallback_locale
+                );
+            }
+            let unknown = unknown_keys(&catalog, locale);
+            if !unknown.is_empty() {
+                println!(
+                    "The locale {} has the unknown messages {}",
+                    locale,
+                    unknown.join(", ")
+                );
+            }
+        }
+        let locale = config
+            .channels
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(channel))
+            .map_or(&config.locale, |(_, locale)| locale);
+        let mut texts = catalog[&config.fallback_locale].to_owned();
+        texts.extend(catalog[locale].to_owned());
+        Ok(Self {
+            texts: Rc::new(texts),
+        })
+    }
+
+    /// The text if there is one, for optional texts
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.texts.get(key).map(String::as_str)
+    }
+
+    /// The text without placeholders
+    pub fn text(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    /// The text with its placeholders replaced by the values of the same name
+    pub fn format(&self, key: &str, values: &[(&str, &dyn Display)]) -> String {
+        let mut text = match self.texts.get(key) {
+            Some(text) => text.to_owned(),
+            None => {
+                println!("There is no message {}", key);
+                return key.to_owned();
+            }
+        };
+        for (name, value) in values {
+            text = text.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn message_file(content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("chatbot_messages_{}.json", Uuid::new_v4()));
+        fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    fn config(file: &str, fallback_locale: &str) -> MessageConfig {
+        serde_json::from_str(&format!(
+            r#"{{"file": {:?}, "fallback_locale": {:?}, "channels": {{"KanalDe": "de"}}}}"#,
+            file, fallback_locale
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn selecting_the_locale_of_the_channel() {
+        let file = message_file(
+            r#"{"de": {"denied": "Verboten!", "usage": "Benutzung: {usage}"},
+                "en": {"denied": "Nope."}}"#,
+        );
+        let messages = Messages::new(&config(&file, "en"), "kanalde").unwrap();
+        assert_eq!(messages.text("denied"), "Verboten!");
+        assert_eq!(
+            messages.format("usage", &[("usage", &"!so <user>")]),
+            "Benutzung: !so <user>"
+        );
+        // missing texts are taken from the fallback locale
+        assert_eq!(messages.text("aliases.none"), "There are no aliases yet.");
+
+        // other channels use the default locale
+        let messages = Messages::new(&config(&file, "en"), "somechannel").unwrap();
+        assert_eq!(messages.text("denied"), "Nope.");
+        assert_eq!(messages.text("unknown_key"), "unknown_key");
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn reporting_missing_keys() {
+        let file = message_file(r#"{"de": {"denied": "Verboten!", "typo": "Hallo"}}"#);
+        let catalog = load_catalog(&config(&file, "de")).unwrap();
+        assert!(missing_keys(&catalog, "en").is_empty());
+        assert!(missing_keys(&catalog, "de").contains(&"usage".to_owned()));
+        assert_eq!(unknown_keys(&catalog, "de"), vec!["typo"]);
+        assert!(matches!(
+            Messages::new(&config(&file, "de"), "kanalde"),
+            Err(MessageError::MissingKeys(locale, _)) if locale == "de"
+        ));
+        assert!(matches!(
+            Messages::new(&config(&file, "fr"), "kanalde"),
+            Err(MessageError::UnknownLocale(locale)) if locale == "fr"
+        ));
+        fs::remove_file(file).unwrap();
+    }
+}
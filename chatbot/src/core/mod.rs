@@ -1,5 +1,35 @@
+mod audit;
 mod bot;
+mod chat_log;
+mod cheers;
 mod command;
+mod command_registry;
+mod config;
+mod messages;
+mod moderation;
+mod plugins;
+mod redemptions;
+mod responders;
+mod scripts;
+mod shoutout;
+mod stats;
+mod storage;
+mod stream_info;
+mod time_format;
+mod welcome;
 
+pub use audit::{audit_log, AuditLog, Change};
 pub use bot::ChatBot;
+pub use chat_log::ChatLog;
+pub use cheers::overlay_events;
 pub use command::ChatBotCommand;
+pub use command_registry::{CommandHandler, CommandRegistry, Permission, RegistryError};
+pub use config::{BotConfig, BotConfigError};
+pub use messages::Messages;
+pub use moderation::retrain_classifier;
+pub use responders::{
+    remove_responder, save_responder, stored_responder, stored_responders, AutoResponder,
+    ResponderError,
+};
+pub use stats::{all_stream_stats, all_user_stats, stream_stats_csv, user_stats_csv, StatsError};
+pub use storage::Storage;
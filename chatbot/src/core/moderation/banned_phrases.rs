@@ -0,0 +1,174 @@
+use super::{
+    default_exempt_roles,
+    normalize::{normalize, normalize_words},
+    FilterAction, MessageFilter, Messages, Violation,
+};
+use crate::connect::{TextMessage, UserRole};
+use regex::Regex;
+use serde::Deserialize;
+use std::time::Instant;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BannedPhrasesConfig {
+    // words or phrases matched as whole words after normalization
+    pub phrases: Vec<String>,
+    // regular expressions matched against the normalized (lower case) message
+    pub patterns: Vec<String>,
+    pub action: FilterAction,
+    // the message catalog's reason is used without one
+    pub reason: Option<String>,
+    pub exempt_roles: Vec<UserRole>,
+}
+
+impl Default for BannedPhrasesConfig {
+    fn default() -> Self {
+        Self {
+            phrases: Vec::default(),
+            patterns: Vec::default(),
+            action: FilterAction::Delete,
+            reason: None,
+            exempt_roles: default_exempt_roles(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BannedPhraseFilter {
+    phrases: Vec<String>,
+    patterns: Vec<Regex>,
+    action: FilterAction,
+    reason: String,
+    exempt_roles: Vec<UserRole>,
+}
+
+impl BannedPhraseFilter {
+    pub fn new(config: &BannedPhrasesConfig, messages: &Messages) -> Result<Self, regex::Error> {
+        Ok(Self {
+            phrases: config
+                .phrases
+                .iter()
+                .map(|phrase| normalize_words(phrase))
+                .filter(|phrase| !phrase.trim().is_empty())
+                .collect(),
+            patterns: config
+                .patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<_, _>>()?,
+            action: config.action.clone(),
+            reason: config
+                .reason
+                .to_owned()
+                .unwrap_or_else(|| messages.text("reason.banned_phrase")),
+            exempt_roles: config.exempt_roles.to_owned(),
+        })
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        let words = normalize_words(text);
+        if self.phrases.iter().any(|phrase| words.contains(phrase)) {
+            return true;
+        }
+        let normalized = normalize(text);
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.is_match(&normalized))
+    }
+}
+
+impl MessageFilter for BannedPhraseFilter {
+    fn check(&mut self, message: &TextMessage, _now: Instant) -> Option<Violation> {
+        if message.user.has_any_role(&self.exempt_roles) || !self.matches(&message.text) {
+            return None;
+        }
+        Some(Violation {
+            reason: self.reason.to_owned(),
+            action: self.action.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::{Badge, UserInfo};
+    use std::collections::HashSet;
+
+    fn filter() -> BannedPhraseFilter {
+        BannedPhraseFilter::new(
+            &BannedPhrasesConfig {
+                phrases: vec!["badword".to_owned(), "very bad phrase".to_owned()],
+                patterns: vec![r"fr[e3]+ followers".to_owned()],
+                ..Default::default()
+            },
+            &Messages::default(),
+        )
+        .unwrap()
+    }
+
+    fn message(text: &str, badges: HashSet<Badge>) -> TextMessage {
+        TextMessage {
+            id: "message-id".to_owned(),
+            text: text.to_owned(),
+            user: UserInfo {
+                name: "chatter".to_owned(),
+                badges,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_obfuscated_phrases() {
+        assert!(filter().matches("what a BADWORD"));
+        assert!(filter().matches("what a b4dw0rd"));
+        assert!(filter().matches("what a b\u{200B}adword"));
+        assert!(filter().matches("this is a Very  Bad, phrase!"));
+        assert!(filter().matches("get freee followers now"));
+    }
+
+    #[test]
+    fn does_not_match_parts_of_words() {
+        assert!(!filter().matches("badwords are not matched"));
+        assert!(!filter().matches("this is fine"));
+    }
+
+    #[test]
+    fn deletes_violating_messages() {
+        let violation = filter().check(&message("badword", HashSet::default()), Instant::now());
+        assert_eq!(
+            violation,
+            Some(Violation {
+                reason: "Please mind your language.".to_owned(),
+                action: FilterAction::Delete,
+            })
+        );
+    }
+
+    #[test]
+    fn moderators_and_vips_are_exempt() {
+        for badge_name in ["moderator", "vip", "broadcaster"] {
+            let badges = HashSet::from([Badge {
+                name: badge_name.to_owned(),
+                level: 1,
+            }]);
+            assert_eq!(
+                filter().check(&message("badword", badges), Instant::now()),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported() {
+        let result = BannedPhraseFilter::new(
+            &BannedPhrasesConfig {
+                patterns: vec!["(unclosed".to_owned()],
+                ..Default::default()
+            },
+            &Messages::default(),
+        );
+        assert!(result.is_err());
+    }
+}
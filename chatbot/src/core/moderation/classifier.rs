@@ -0,0 +1,440 @@
+use super::{
+    default_exempt_roles, normalize::normalize_words, FilterAction, MessageFilter, Messages,
+    Violation,
+};
+use crate::{
+    connect::{TextMessage, UserRole},
+    core::storage::Storage,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+const CLASSIFIER_BUCKET: &str = "classifier";
+const MODEL_KEY: &str = "model";
+// the model is saved at most this often, except after learning spam
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+// twitch reports the deletions and bans of the bot itself within this time
+const OWN_ACTION_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ClassifierConfig {
+    // score incoming messages, learning happens regardless
+    pub enabled: bool,
+    // spam probability (0.0 - 1.0) above which a message is treated as spam
+    pub threshold: f64,
+    pub action: FilterAction,
+    // the message catalog's reason is used without one
+    pub reason: Option<String>,
+    pub exempt_roles: Vec<UserRole>,
+    // messages are only scored when the model has seen this many examples
+    pub min_spam_examples: u64,
+    pub min_ham_examples: u64,
+    // messages which have not been deleted within this time are learned as ham
+    pub ham_delay_seconds: u64,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.95,
+            action: FilterAction::Flag,
+            reason: None,
+            exempt_roles: default_exempt_roles(),
+            min_spam_examples: 20,
+            min_ham_examples: 100,
+            ham_delay_seconds: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Spam,
+    Ham,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ClassCounts {
+    documents: u64,
+    tokens: u64,
+    token_counts: HashMap<String, u64>,
+}
+
+impl ClassCounts {
+    fn add(&mut self, tokens: &[String]) {
+        self.documents += 1;
+        self.tokens += tokens.len() as u64;
+        for token in tokens {
+            *self.token_counts.entry(token.to_owned()).or_insert(0) += 1;
+        }
+    }
+
+    fn log_likelihood(&self, tokens: &[String], vocabulary_size: usize) -> f64 {
+        let denominator = (self.tokens + vocabulary_size as u64) as f64;
+        tokens
+            .iter()
+            .map(|token| {
+                let count = self.token_counts.get(token).copied().unwrap_or(0);
+                ((count + 1) as f64 / denominator).ln()
+            })
+            .sum()
+    }
+}
+
+/// Multinomial naive bayes model with laplace smoothing
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SpamModel {
+    spam: ClassCounts,
+    ham: ClassCounts,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    normalize_words(text)
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+impl SpamModel {
+    pub fn train(&mut self, text: &str, label: Label) {
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return;
+        }
+        match label {
+            Label::Spam => self.spam.add(&tokens),
+            Label::Ham => self.ham.add(&tokens),
+        }
+    }
+
+    fn vocabulary_size(&self) -> usize {
+        let ham_only = self
+            .ham
+            .token_counts
+            .keys()
+            .filter(|token| !self.spam.token_counts.contains_key(*token))
+            .count();
+        self.spam.token_counts.len() + ham_only
+    }
+
+    /// Probability of the text being spam
+    pub fn spam_probability(&self, text: &str) -> f64 {
+        let tokens = tokenize(text);
+        let documents = (self.spam.documents + self.ham.documents) as f64;
+        if tokens.is_empty() || self.spam.documents == 0 || self.ham.documents == 0 {
+            return 0.0;
+        }
+        let vocabulary_size = self.vocabulary_size();
+        let spam = (self.spam.documents as f64 / documents).ln()
+            + self.spam.log_likelihood(&tokens, vocabulary_size);
+        let ham = (self.ham.documents as f64 / documents).ln()
+            + self.ham.log_likelihood(&tokens, vocabulary_size);
+        1.0 / (1.0 + (ham - spam).exp())
+    }
+
+    fn examples(&self) -> (u64, u64) {
+        (self.spam.documents, self.ham.documents)
+    }
+}
+
+#[derive(Debug)]
+struct RecentMessage {
+    id: String,
+    user_name: String,
+    text: String,
+    received: Instant,
+}
+
+/// Scores messages with a naive bayes model. The model learns from moderator actions:
+/// deleted messages and messages of banned users are spam,
+/// messages which are not deleted within some time are ham.
+/// Actions of the bot's own filters are not learned from.
+#[derive(Debug)]
+pub struct SpamClassifier {
+    enabled: bool,
+    threshold: f64,
+    action: FilterAction,
+    reason: String,
+    exempt_roles: Vec<UserRole>,
+    min_examples: (u64, u64),
+    ham_delay: Duration,
+    model: SpamModel,
+    recent_messages: VecDeque<RecentMessage>,
+    // ids of the messages and lower case names of the users the bot itself acted on
+    own_actions: VecDeque<(Instant, String)>,
+    storage: Storage,
+    last_save: Instant,
+}
+
+impl SpamClassifier {
+    pub fn new(config: &ClassifierConfig, storage: Storage, messages: &Messages) -> Self {
+        let model = storage
+            .get(CLASSIFIER_BUCKET, MODEL_KEY)
+            .unwrap_or_else(|err| {
+                println!("Could not load spam model: {:?}", err);
+                None
+            })
+            .unwrap_or_default();
+        Self {
+            enabled: config.enabled,
+            threshold: config.threshold,
+            action: config.action.clone(),
+            reason: config
+                .reason
+                .to_owned()
+                .unwrap_or_else(|| messages.text("reason.classifier")),
+            exempt_roles: config.exempt_roles.to_owned(),
+            min_examples: (config.min_spam_examples, config.min_ham_examples),
+            ham_delay: Duration::from_secs(config.ham_delay_seconds),
+            model,
+            recent_messages: VecDeque::default(),
+            own_actions: VecDeque::default(),
+            storage,
+            last_save: Instant::now(),
+        }
+    }
+
+    fn save(&mut self, now: Instant) {
+        if let Err(err) = self
+            .storage
+            .set(CLASSIFIER_BUCKET, MODEL_KEY, self.model.clone())
+        {
+            println!("Could not save spam model: {:?}", err);
+        }
+        self.last_save = now;
+    }
+
+    // Learns all messages which were not deleted in time as ham
+    fn learn_ham(&mut self, now: Instant) {
+        let mut learned = false;
+        while let Some(message) = self.recent_messages.front() {
+            if now.duration_since(message.received) < self.ham_delay {
+                break;
+            }
+            self.model.train(&message.text, Label::Ham);
+            self.recent_messages.pop_front();
+            learned = true;
+        }
+        if learned && now.duration_since(self.last_save) >= SAVE_INTERVAL {
+            self.save(now);
+        }
+    }
+
+    /// The bot itself deleted the message with the id or punished the user with the name,
+    /// the deletion or ban twitch reports for it is not learned from
+    pub fn bot_acted_on(&mut self, target: &str, now: Instant) {
+        self.own_actions.push_back((now, target.to_lowercase()));
+    }
+
+    fn is_own_action(&mut self, target: &str, now: Instant) -> bool {
+        while matches!(self.own_actions.front(),
+                       Some((time, _)) if now.duration_since(*time) > OWN_ACTION_TIME)
+        {
+            self.own_actions.pop_front();
+        }
+        let target = target.to_lowercase();
+        self.own_actions
+            .iter()
+            .any(|(_, own_target)| *own_target == target)
+    }
+
+    /// A moderator deleted the message
+    pub fn message_deleted(&mut self, message_id: &str, text: &str, now: Instant) {
+        self.recent_messages
+            .retain(|message| message.id != message_id);
+        if self.is_own_action(message_id, now) {
+            return;
+        }
+        self.model.train(text, Label::Spam);
+        self.save(now);
+    }
+
+    /// The user has been banned, the user's recent messages are learned as spam
+    pub fn user_banned(&mut self, user_name: &str, now: Instant) {
+        let (spam, ham) = self
+            .recent_messages
+            .drain(..)
+            .partition(|message| message.user_name.eq_ignore_ascii_case(user_name));
+        self.recent_messages = ham;
+        if self.is_own_action(user_name, now) {
+            return;
+        }
+        for message in spam {
+            self.model.train(&message.text, Label::Spam);
+        }
+        self.save(now);
+    }
+
+    fn is_trained(&self) -> bool {
+        let (spam, ham) = self.model.examples();
+        spam >= self.min_examples.0 && ham >= self.min_examples.1
+    }
+}
+
+impl MessageFilter for SpamClassifier {
+    fn check(&mut self, message: &TextMessage, now: Instant) -> Option<Violation> {
+        self.learn_ham(now);
+        self.recent_messages.push_back(RecentMessage {
+            id: message.id.to_owned(),
+            user_name: message.user.name.to_owned(),
+            text: message.text.to_owned(),
+            received: now,
+        });
+        if !self.enabled || !self.is_trained() || message.user.has_any_role(&self.exempt_roles) {
+            return None;
+        }
+        let probability = self.model.spam_probability(&message.text);
+        if probability <= self.threshold {
+            return None;
+        }
+        Some(Violation {
+            reason: format!("{}, {:.0}%", self.reason, probability * 100.0),
+            action: self.action.clone(),
+        })
+    }
+}
+
+/// Trains a new model from labelled lines ("spam<TAB>message" or "ham<TAB>message")
+/// and stores it, replacing the current model. Returns the number of spam and ham examples.
+pub fn retrain(storage: &Storage, labelled_lines: &str) -> Result<(u64, u64), kv::Error> {
+    let mut model = SpamModel::default();
+    for line in labelled_lines.lines() {
+        match line.split_once('\t') {
+            Some(("spam", text)) => model.train(text, Label::Spam),
+            Some(("ham", text)) => model.train(text, Label::Ham),
+            _ => println!("Skipping line without label: {}", line),
+        }
+    }
+    let examples = model.examples();
+    storage.set(CLASSIFIER_BUCKET, MODEL_KEY, model)?;
+    Ok(examples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::UserInfo;
+    use std::collections::HashSet;
+
+    fn trained_model() -> SpamModel {
+        let mut model = SpamModel::default();
+        for text in [
+            "buy cheap followers now",
+            "cheap viewers and followers at bigfollows",
+            "best viewers for your stream, buy now",
+        ] {
+            model.train(text, Label::Spam);
+        }
+        for text in [
+            "nice play",
+            "great play",
+            "how are you doing today",
+            "that boss fight was great",
+            "what game is this",
+        ] {
+            model.train(text, Label::Ham);
+        }
+        model
+    }
+
+    fn message(id: &str, user_name: &str, text: &str) -> TextMessage {
+        TextMessage {
+            id: id.to_owned(),
+            text: text.to_owned(),
+            user: UserInfo {
+                name: user_name.to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn classifier(config: ClassifierConfig) -> SpamClassifier {
+        SpamClassifier::new(&config, Storage::temporary().unwrap(), &Messages::default())
+    }
+
+    #[test]
+    fn scoring_messages() {
+        let model = trained_model();
+        assert!(model.spam_probability("Buy cheap followers") > 0.9);
+        assert!(model.spam_probability("great play") < 0.1);
+    }
+
+    #[test]
+    fn untrained_model_scores_zero() {
+        assert_eq!(SpamModel::default().spam_probability("buy followers"), 0.0);
+    }
+
+    #[test]
+    fn learning_from_moderator_actions() {
+        let mut classifier = classifier(ClassifierConfig::default());
+        let now = Instant::now();
+        classifier.check(&message("1", "spammer", "buy followers"), now);
+        classifier.check(&message("2", "viewer", "hello there"), now);
+        classifier.check(&message("3", "other", "cheap viewers"), now);
+        classifier.user_banned("Spammer", now);
+        classifier.message_deleted("3", "cheap viewers", now);
+        assert_eq!(classifier.model.examples(), (2, 0));
+        let later = now + Duration::from_secs(300);
+        classifier.check(&message("4", "viewer", "anyone there?"), later);
+        assert_eq!(classifier.model.examples(), (2, 1));
+    }
+
+    #[test]
+    fn not_learning_from_own_actions() {
+        let mut classifier = classifier(ClassifierConfig::default());
+        let now = Instant::now();
+        classifier.check(&message("1", "spammer", "buy followers"), now);
+        classifier.check(&message("2", "other", "cheap viewers"), now);
+        classifier.bot_acted_on("Spammer", now);
+        classifier.bot_acted_on("2", now);
+        classifier.user_banned("spammer", now);
+        classifier.message_deleted("2", "cheap viewers", now);
+        assert_eq!(classifier.model.examples(), (0, 0));
+        // the messages are not learned as ham either
+        let later = now + Duration::from_secs(300);
+        classifier.check(&message("3", "viewer", "anyone there?"), later);
+        assert_eq!(classifier.model.examples(), (0, 0));
+        // later actions are those of moderators
+        classifier.bot_acted_on("4", later);
+        classifier.check(&message("4", "other", "cheap viewers"), later);
+        let much_later = later + Duration::from_secs(11);
+        classifier.message_deleted("4", "cheap viewers", much_later);
+        assert_eq!(classifier.model.examples(), (1, 0));
+    }
+
+    #[test]
+    fn flagging_spam_when_trained() {
+        let mut classifier = classifier(ClassifierConfig {
+            enabled: true,
+            threshold: 0.9,
+            min_spam_examples: 3,
+            min_ham_examples: 5,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let spam = message("1", "spammer", "buy cheap followers");
+        assert_eq!(classifier.check(&spam, now), None);
+        classifier.model = trained_model();
+        let violation = classifier.check(&spam, now).unwrap();
+        assert_eq!(violation.action, FilterAction::Flag);
+        assert!(classifier
+            .check(&message("2", "viewer", "nice play"), now)
+            .is_none());
+    }
+
+    #[test]
+    fn retraining_from_labelled_lines() {
+        let storage = Storage::temporary().unwrap();
+        let examples = retrain(&storage, "spam\tbuy followers\nham\tnice play\ninvalid").unwrap();
+        assert_eq!(examples, (1, 1));
+        let classifier =
+            SpamClassifier::new(&ClassifierConfig::default(), storage, &Messages::default());
+        assert_eq!(classifier.model.examples(), (1, 1));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::connect::TextMessage;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// messages older than this are forgotten
+const MAX_AGE: Duration = Duration::from_secs(30 * 60);
+const MAX_MESSAGES: usize = 5000;
+
+#[derive(Debug)]
+struct RecentMessage {
+    id: String,
+    user_name: String,
+    // lowercase, for case insensitive searches
+    text: String,
+    exempt: bool,
+    received: Instant,
+}
+
+/// The recent messages of the chat, used for moderation commands like !nuke
+#[derive(Debug, Default)]
+pub struct MessageHistory {
+    messages: VecDeque<RecentMessage>,
+}
+
+impl MessageHistory {
+    pub fn record(&mut self, message: &TextMessage, now: Instant) {
+        while let Some(oldest) = self.messages.front() {
+            if now.duration_since(oldest.received) < MAX_AGE && self.messages.len() < MAX_MESSAGES {
+                break;
+            }
+            self.messages.pop_front();
+        }
+        self.messages.push_back(RecentMessage {
+            id: message.id.to_owned(),
+            user_name: message.user.name.to_owned(),
+            text: message.text.to_lowercase(),
+            exempt: message.user.has_elevated_rights(),
+            received: now,
+        });
+    }
+
+    /// Users (except moderators and the broadcaster) who wrote the phrase within the lookback
+    pub fn users_who_said(&self, phrase: &str, lookback: Duration, now: Instant) -> Vec<String> {
+        let phrase = phrase.to_lowercase();
+        let mut users: Vec<String> = Vec::default();
+        for message in self.messages.iter().rev() {
+            if now.duration_since(message.received) > lookback {
+                break;
+            }
+            if !message.exempt
+                && message.text.contains(&phrase)
+                && !users.contains(&message.user_name)
+            {
+                users.push(message.user_name.to_owned());
+            }
+        }
+        users
+    }
+
+    /// Id of the last message the user wrote
+    pub fn last_message_id(&self, user_name: &str) -> Option<String> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|message| message.user_name.eq_ignore_ascii_case(user_name))
+            .map(|message| message.id.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::{Badge, UserInfo};
+    use std::collections::HashSet;
+
+    fn message(id: &str, user_name: &str, text: &str, badges: HashSet<Badge>) -> TextMessage {
+        TextMessage {
+            id: id.to_owned(),
+            text: text.to_owned(),
+            user: UserInfo {
+                name: user_name.to_owned(),
+                badges,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finding_users_who_said_a_phrase() {
+        let mut history = MessageHistory::default();
+        let now = Instant::now();
+        history.record(&message("1", "early", "spoiler!", HashSet::default()), now);
+        let later = now + Duration::from_secs(120);
+        history.record(
+            &message("2", "carkhy", "SPOILER: he dies", HashSet::default()),
+            later,
+        );
+        history.record(
+            &message("3", "carkhy", "spoiler again", HashSet::default()),
+            later,
+        );
+        history.record(
+            &message("4", "viewer", "no spoilers please", HashSet::default()),
+            later,
+        );
+        history.record(
+            &message(
+                "5",
+                "mod",
+                "spoiler alert",
+                HashSet::from([Badge {
+                    name: "moderator".to_owned(),
+                    level: 1,
+                }]),
+            ),
+            later,
+        );
+        assert_eq!(
+            history.users_who_said("Spoiler", Duration::from_secs(60), later),
+            vec!["viewer", "carkhy"]
+        );
+        assert_eq!(history.last_message_id("Carkhy"), Some("3".to_owned()));
+    }
+
+    #[test]
+    fn forgetting_old_messages() {
+        let mut history = MessageHistory::default();
+        let now = Instant::now();
+        history.record(&message("1", "carkhy", "hello", HashSet::default()), now);
+        history.record(
+            &message("2", "viewer", "hi", HashSet::default()),
+            now + MAX_AGE,
+        );
+        assert_eq!(history.last_message_id("carkhy"), None);
+    }
+}
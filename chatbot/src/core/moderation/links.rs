@@ -0,0 +1,308 @@
+use super::{
+    default_exempt_roles, normalize::simplify_keeping_case, FilterAction, MessageFilter, Messages,
+    Violation,
+};
+use crate::connect::{TextMessage, UserRole};
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// Top level domains that are recognized without a leading "http(s)://"
+const TOP_LEVEL_DOMAINS: &str = "com|net|org|tv|gg|io|co|me|ly|be|to|cc|de|uk|ru|eu|us|fr|nl|info|biz|xyz|app|dev|link|live|site|online|shop|store|club|fun|stream";
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LinksConfig {
+    pub enabled: bool,
+    // domains (and optionally a path prefix) anybody may post, subdomains are included
+    pub allowed_domains: Vec<String>,
+    pub action: FilterAction,
+    // the message catalog's reason is used without one
+    pub reason: Option<String>,
+    pub exempt_roles: Vec<UserRole>,
+    // how long a one-time permit given with "!permit <user>" is valid
+    pub permit_seconds: u64,
+}
+
+impl Default for LinksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_domains: vec!["clips.twitch.tv".to_owned()],
+            action: FilterAction::Delete,
+            reason: None,
+            exempt_roles: default_exempt_roles(),
+            permit_seconds: 60,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Link {
+    host: String,
+    path: String,
+}
+
+// Hosts are lower cased, paths are kept as written since they are case sensitive,
+// e.g. the invite codes of "discord.gg/<code>"
+#[derive(Debug)]
+struct AllowedDomain {
+    host: String,
+    path: String,
+}
+
+impl AllowedDomain {
+    fn parse(entry: &str) -> Self {
+        let entry = entry.trim();
+        let entry = entry.split_once("://").map_or(entry, |(_, rest)| rest);
+        let (host, path) = entry.split_at(entry.find('/').unwrap_or(entry.len()));
+        Self {
+            host: host.to_lowercase(),
+            path: path.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    fn allows(&self, link: &Link) -> bool {
+        let host_matches =
+            link.host == self.host || link.host.ends_with(&format!(".{}", self.host));
+        host_matches && self.allows_path(&link.path)
+    }
+
+    // The path has to start with the whole segments of the allowed path
+    fn allows_path(&self, path: &str) -> bool {
+        match path.strip_prefix(&self.path) {
+            Some(rest) => rest.is_empty() || rest.starts_with(['/', '?', '#']),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Permit {
+    expires: Instant,
+    // one-time permits are used up by the first message containing a link
+    one_time: bool,
+}
+
+#[derive(Debug)]
+pub struct LinkFilter {
+    enabled: bool,
+    allowed_domains: Vec<AllowedDomain>,
+    action: FilterAction,
+    reason: String,
+    exempt_roles: Vec<UserRole>,
+    permit_duration: Duration,
+    permits: HashMap<String, Permit>,
+    obfuscated_dot: Regex,
+    link: Regex,
+}
+
+impl LinkFilter {
+    pub fn new(config: &LinksConfig, messages: &Messages) -> Self {
+        Self {
+            enabled: config.enabled,
+            allowed_domains: config
+                .allowed_domains
+                .iter()
+                .map(|entry| AllowedDomain::parse(entry))
+                .collect(),
+            action: config.action.clone(),
+            reason: config
+                .reason
+                .to_owned()
+                .unwrap_or_else(|| messages.text("reason.link")),
+            exempt_roles: config.exempt_roles.to_owned(),
+            permit_duration: Duration::from_secs(config.permit_seconds),
+            permits: HashMap::default(),
+            // "example dot com", "example (dot) com", "example[.]com", "example . com"
+            obfuscated_dot: Regex::new(
+                r"(?i)([a-z0-9])\s*(?:[(\[{<]\s*(?:dot|\.)\s*[)\]}>]|\s(?:dot|\.)\s)\s*([a-z0-9])",
+            )
+            .unwrap(),
+            link: Regex::new(&format!(
+                r"(?i)(?:https?://(?P<any_host>[a-z0-9.-]+\.[a-z0-9-]+)|(?P<host>(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+(?:{}))\b)(?::\d+)?(?P<path>/\S*)?",
+                TOP_LEVEL_DOMAINS
+            ))
+            .unwrap(),
+        }
+    }
+
+    fn find_links(&self, text: &str) -> Vec<Link> {
+        let simplified = simplify_keeping_case(text);
+        let deobfuscated = self.obfuscated_dot.replace_all(&simplified, "$1.$2");
+        self.link
+            .captures_iter(&deobfuscated)
+            .filter_map(|captures| {
+                let host = captures
+                    .name("any_host")
+                    .or_else(|| captures.name("host"))?;
+                Some(Link {
+                    host: host.as_str().to_lowercase(),
+                    path: captures
+                        .name("path")
+                        .map(|path| path.as_str().to_owned())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    fn is_allowed(&self, link: &Link) -> bool {
+        self.allowed_domains
+            .iter()
+            .any(|allowed_domain| allowed_domain.allows(link))
+    }
+
+    /// Allows the user to post links.
+    /// Without a duration, the permit is valid for a single message within the configured time.
+    pub fn permit(&mut self, user_name: &str, duration: Option<Duration>, now: Instant) {
+        let permit = Permit {
+            expires: now + duration.unwrap_or(self.permit_duration),
+            one_time: duration.is_none(),
+        };
+        self.permits.insert(user_name.to_lowercase(), permit);
+    }
+
+    // Checks for a valid permit and uses it up if it is a one-time permit
+    fn use_permit(&mut self, user_name: &str, now: Instant) -> bool {
+        let user_name = user_name.to_lowercase();
+        match self.permits.get(&user_name) {
+            Some(permit) if permit.expires > now => {
+                if permit.one_time {
+                    self.permits.remove(&user_name);
+                }
+                true
+            }
+            Some(_) => {
+                self.permits.remove(&user_name);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+impl MessageFilter for LinkFilter {
+    fn check(&mut self, message: &TextMessage, now: Instant) -> Option<Violation> {
+        if !self.enabled || message.user.has_any_role(&self.exempt_roles) {
+            return None;
+        }
+        let has_forbidden_links = self
+            .find_links(&message.text)
+            .iter()
+            .any(|link| !self.is_allowed(link));
+        if !has_forbidden_links || self.use_permit(&message.user.name, now) {
+            return None;
+        }
+        Some(Violation {
+            reason: self.reason.to_owned(),
+            action: self.action.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::UserInfo;
+    use std::collections::HashSet;
+
+    fn filter() -> LinkFilter {
+        LinkFilter::new(
+            &LinksConfig {
+                enabled: true,
+                allowed_domains: vec![
+                    "clips.twitch.tv".to_owned(),
+                    "discord.gg/qM6DTTQxDV".to_owned(),
+                ],
+                ..Default::default()
+            },
+            &Messages::default(),
+        )
+    }
+
+    fn message(text: &str) -> TextMessage {
+        TextMessage {
+            id: "message-id".to_owned(),
+            text: text.to_owned(),
+            user: UserInfo {
+                name: "chatter".to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn hosts(text: &str) -> Vec<String> {
+        filter()
+            .find_links(text)
+            .into_iter()
+            .map(|link| link.host)
+            .collect()
+    }
+
+    #[test]
+    fn finding_links() {
+        assert_eq!(hosts("visit https://example.com/page now"), ["example.com"]);
+        assert_eq!(hosts("visit www.Example.org"), ["www.example.org"]);
+        assert_eq!(hosts("http://some.weird-tld.zzz"), ["some.weird-tld.zzz"]);
+        assert!(hosts("no links. just sentences.and dots").is_empty());
+    }
+
+    #[test]
+    fn finding_obfuscated_links() {
+        assert_eq!(hosts("go to example dot com"), ["example.com"]);
+        assert_eq!(hosts("go to example (dot) com"), ["example.com"]);
+        assert_eq!(hosts("go to example[.]com"), ["example.com"]);
+        assert_eq!(hosts("go to example . com"), ["example.com"]);
+    }
+
+    #[test]
+    fn allowed_domains_pass() {
+        let mut filter = filter();
+        let now = Instant::now();
+        let allowed = message("look at https://clips.twitch.tv/SomeClip and discord.gg/qM6DTTQxDV");
+        assert_eq!(filter.check(&allowed, now), None);
+        let forbidden = message("discord.gg/someotherserver");
+        assert!(filter.check(&forbidden, now).is_some());
+        // only whole path segments match
+        let forbidden = message("discord.gg/qM6DTTQxDVmalicious");
+        assert!(filter.check(&forbidden, now).is_some());
+        let allowed = message("https://discord.gg/qM6DTTQxDV/?invite=1");
+        assert_eq!(filter.check(&allowed, now), None);
+        // hosts are case insensitive, invite codes are not
+        let allowed = message("HTTPS://Discord.GG/qM6DTTQxDV");
+        assert_eq!(filter.check(&allowed, now), None);
+        let forbidden = message("discord.gg/qm6dttqxdv");
+        assert!(filter.check(&forbidden, now).is_some());
+    }
+
+    #[test]
+    fn one_time_permits_are_used_up() {
+        let mut filter = filter();
+        let now = Instant::now();
+        filter.permit("Chatter", None, now);
+        assert_eq!(filter.check(&message("example.com"), now), None);
+        assert!(filter.check(&message("example.com"), now).is_some());
+    }
+
+    #[test]
+    fn timed_permits_expire() {
+        let mut filter = filter();
+        let now = Instant::now();
+        filter.permit("chatter", Some(Duration::from_secs(60)), now);
+        assert_eq!(filter.check(&message("example.com"), now), None);
+        assert_eq!(filter.check(&message("example.net"), now), None);
+        let later = now + Duration::from_secs(61);
+        assert!(filter.check(&message("example.com"), later).is_some());
+    }
+
+    #[test]
+    fn disabled_filter_allows_links() {
+        let mut filter = LinkFilter::new(&LinksConfig::default(), &Messages::default());
+        assert_eq!(filter.check(&message("example.com"), Instant::now()), None);
+    }
+}
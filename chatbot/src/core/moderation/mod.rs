@@ -0,0 +1,259 @@
+mod banned_phrases;
+mod classifier;
+mod history;
+mod links;
+mod normalize;
+mod raid;
+mod spam;
+mod strikes;
+
+use super::{storage::Storage, ChatBotCommand, Messages};
+use crate::connect::{ModerationAction, RoomState, TextMessage, UserRole};
+use banned_phrases::BannedPhraseFilter;
+use classifier::SpamClassifier;
+use history::MessageHistory;
+use links::LinkFilter;
+use raid::RaidProtection;
+use serde::Deserialize;
+use spam::SpamFilter;
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant, SystemTime},
+};
+use strikes::StrikeTracker;
+
+pub use banned_phrases::BannedPhrasesConfig;
+pub use classifier::{retrain as retrain_classifier, ClassifierConfig};
+pub use links::LinksConfig;
+pub use raid::RaidConfig;
+pub use spam::SpamConfig;
+pub use strikes::{Strike, StrikesConfig};
+
+/// What happens to a message (and its author) when it violates a filter
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FilterAction {
+    Delete,
+    Timeout { seconds: u64 },
+    Ban,
+    Warn,
+    // asks the moderators to have a look at the message
+    Flag,
+}
+
+impl FilterAction {
+    pub fn to_command(
+        &self,
+        message: &TextMessage,
+        reason: &str,
+        messages: &Messages,
+    ) -> ChatBotCommand {
+        use ChatBotCommand::*;
+        let user = message.user.name.to_owned();
+        match self {
+            FilterAction::Delete => {
+                Moderate(ModerationAction::DeleteMessage(message.id.to_owned()))
+            }
+            FilterAction::Timeout { seconds } => Moderate(ModerationAction::Timeout {
+                user,
+                duration: Duration::from_secs(*seconds),
+                reason: Some(reason.to_owned()),
+            }),
+            FilterAction::Ban => Moderate(ModerationAction::Ban {
+                user,
+                reason: Some(reason.to_owned()),
+            }),
+            FilterAction::Warn => {
+                SendMessage(messages.format("warning", &[("user", &user), ("reason", &reason)]))
+            }
+            FilterAction::Flag => {
+                SendMessage(messages.format("flag", &[("user", &user), ("reason", &reason)]))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub reason: String,
+    pub action: FilterAction,
+}
+
+trait MessageFilter: Debug {
+    fn check(&mut self, message: &TextMessage, now: Instant) -> Option<Violation>;
+}
+
+pub(crate) fn default_exempt_roles() -> Vec<UserRole> {
+    vec![UserRole::Broadcaster, UserRole::Moderator, UserRole::Vip]
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ModerationConfig {
+    pub banned_phrases: BannedPhrasesConfig,
+    pub links: LinksConfig,
+    pub spam: SpamConfig,
+    pub strikes: StrikesConfig,
+    pub classifier: ClassifierConfig,
+    pub raid: RaidConfig,
+}
+
+/// Checks incoming text messages against all configured filters.
+#[derive(Debug)]
+pub struct ModerationPipeline {
+    banned_phrases: BannedPhraseFilter,
+    links: LinkFilter,
+    spam: SpamFilter,
+    strikes_enabled: bool,
+    strikes: StrikeTracker,
+    classifier: SpamClassifier,
+    history: MessageHistory,
+    raid: RaidProtection,
+    messages: Messages,
+}
+
+impl ModerationPipeline {
+    pub fn new(
+        config: &ModerationConfig,
+        storage: Storage,
+        messages: Messages,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            banned_phrases: BannedPhraseFilter::new(&config.banned_phrases, &messages)?,
+            links: LinkFilter::new(&config.links, &messages),
+            spam: SpamFilter::new(&config.spam, &messages),
+            strikes_enabled: config.strikes.enabled,
+            strikes: StrikeTracker::new(&config.strikes, storage.clone()),
+            classifier: SpamClassifier::new(&config.classifier, storage, &messages),
+            history: MessageHistory::default(),
+            raid: RaidProtection::new(&config.raid, messages.clone()),
+            messages,
+        })
+    }
+
+    fn filters(&mut self) -> [&mut dyn MessageFilter; 4] {
+        [
+            &mut self.banned_phrases,
+            &mut self.links,
+            &mut self.spam,
+            &mut self.classifier,
+        ]
+    }
+
+    /// Returns the commands of the raid protection and of the first filter the message violates.
+    pub fn check(&mut self, message: &TextMessage, now: Instant) -> Option<ChatBotCommand> {
+        self.history.record(message, now);
+        let raid = self.raid.check(message, now);
+        let command = match (raid, self.filter(message, now)) {
+            (Some(raid), Some(filter)) => {
+                Some(ChatBotCommand::MultipleCommands(vec![raid, filter]))
+            }
+            (raid, filter) => raid.or(filter),
+        }?;
+        self.remember_own_actions(&command, now);
+        Some(command)
+    }
+
+    // The spam classifier does not learn from the deletions and bans of the bot itself
+    fn remember_own_actions(&mut self, command: &ChatBotCommand, now: Instant) {
+        match command {
+            ChatBotCommand::MultipleCommands(commands) => {
+                for command in commands {
+                    self.remember_own_actions(command, now);
+                }
+            }
+            ChatBotCommand::Moderate(ModerationAction::DeleteMessage(message_id)) => {
+                self.classifier.bot_acted_on(message_id, now)
+            }
+            ChatBotCommand::Moderate(
+                ModerationAction::Timeout { user, .. } | ModerationAction::Ban { user, .. },
+            ) => self.classifier.bot_acted_on(user, now),
+            _ => {}
+        }
+    }
+
+    // With strikes enabled, the message is deleted and the user is punished according to
+    // the user's strikes, otherwise the filter's action is used.
+    // Flagged messages are only reported to the moderators and never lead to a strike.
+    fn filter(&mut self, message: &TextMessage, now: Instant) -> Option<ChatBotCommand> {
+        let violation = self
+            .filters()
+            .into_iter()
+            .find_map(|filter| filter.check(message, now))?;
+        if !self.strikes_enabled || violation.action == FilterAction::Flag {
+            return Some(
+                violation
+                    .action
+                    .to_command(message, &violation.reason, &self.messages),
+            );
+        }
+        let (count, action) =
+            self.strikes
+                .record(&message.user.name, &violation.reason, SystemTime::now());
+        let reason = self.messages.format(
+            "strikes.reason",
+            &[("reason", &violation.reason), ("count", &count)],
+        );
+        Some(match action {
+            FilterAction::Delete => action.to_command(message, &reason, &self.messages),
+            _ => ChatBotCommand::MultipleCommands(vec![
+                FilterAction::Delete.to_command(message, &reason, &self.messages),
+                action.to_command(message, &reason, &self.messages),
+            ]),
+        })
+    }
+
+    /// Allows the user to post links, see `LinkFilter::permit`
+    pub fn permit(&mut self, user_name: &str, duration: Option<Duration>, now: Instant) {
+        self.links.permit(user_name, duration, now);
+    }
+
+    pub fn strikes(&self, user_name: &str, now: SystemTime) -> Vec<Strike> {
+        self.strikes.strikes(user_name, now)
+    }
+
+    pub fn clear_strikes(&self, user_name: &str, now: SystemTime) -> bool {
+        self.strikes.clear(user_name, now)
+    }
+
+    /// A user joined the chat, many joins at once trigger the raid protection
+    pub fn joined(&mut self, now: Instant) -> Option<ChatBotCommand> {
+        self.raid.joined(now)
+    }
+
+    /// Another channel raided, its viewers do not trigger the raid protection
+    pub fn raid_announced(&mut self, viewers: u64, now: Instant) {
+        self.raid.raid_announced(viewers, now)
+    }
+
+    pub fn room_state(&mut self, room_state: &RoomState) {
+        self.raid.room_state(room_state)
+    }
+
+    pub fn lockdown(&mut self) -> Option<ChatBotCommand> {
+        self.raid.lockdown()
+    }
+
+    pub fn end_lockdown(&mut self) -> Option<ChatBotCommand> {
+        self.raid.end_lockdown()
+    }
+
+    /// Users who recently wrote the phrase, see `MessageHistory::users_who_said`
+    pub fn users_who_said(&self, phrase: &str, lookback: Duration, now: Instant) -> Vec<String> {
+        self.history.users_who_said(phrase, lookback, now)
+    }
+
+    pub fn last_message_id(&self, user_name: &str) -> Option<String> {
+        self.history.last_message_id(user_name)
+    }
+
+    /// A message has been deleted by a moderator, the spam classifier learns from it
+    pub fn message_deleted(&mut self, message_id: &str, text: &str, now: Instant) {
+        self.classifier.message_deleted(message_id, text, now);
+    }
+
+    /// A user has been banned, the spam classifier learns from the user's recent messages
+    pub fn user_banned(&mut self, user_name: &str, now: Instant) {
+        self.classifier.user_banned(user_name, now);
+    }
+}
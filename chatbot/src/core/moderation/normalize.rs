@@ -0,0 +1,184 @@
+// Normalization of chat messages so that simple obfuscations like
+// "ВАD" (cyrillic), "b4d", "ｂａｄ" or "b\u{200B}ad" are matched like "bad".
+
+const ZERO_WIDTH_CHARACTERS: [char; 7] = [
+    '\u{00AD}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{180E}', '\u{FEFF}',
+];
+
+fn is_zero_width(character: char) -> bool {
+    ZERO_WIDTH_CHARACTERS.contains(&character)
+}
+
+// Characters that look like latin letters, mostly cyrillic and greek ones
+fn homoglyph(character: char) -> Option<char> {
+    let replacement = match character {
+        'а' | 'α' => 'a',
+        'в' | 'β' => 'b',
+        'с' | 'ϲ' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ё' | 'ε' => 'e',
+        'һ' => 'h',
+        'і' | 'ї' | 'ι' => 'i',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'м' => 'm',
+        'п' | 'η' => 'n',
+        'о' | 'ο' | 'σ' => 'o',
+        'р' | 'ρ' => 'p',
+        'ԛ' => 'q',
+        'г' => 'r',
+        'ѕ' => 's',
+        'т' | 'τ' => 't',
+        'υ' => 'u',
+        'ν' => 'v',
+        'ѡ' | 'ω' => 'w',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        'ᴢ' => 'z',
+        // fullwidth forms of the printable ascii characters
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(character as u32 - 0xFEE0)?,
+        _ => return None,
+    };
+    Some(replacement)
+}
+
+fn leet_digit(character: char) -> Option<char> {
+    match character {
+        '0' => Some('o'),
+        '1' => Some('i'),
+        '3' => Some('e'),
+        '4' => Some('a'),
+        '5' => Some('s'),
+        '7' => Some('t'),
+        '8' => Some('b'),
+        '9' => Some('g'),
+        _ => None,
+    }
+}
+
+fn leet_symbol(character: char) -> Option<char> {
+    match character {
+        '@' => Some('a'),
+        '$' => Some('s'),
+        '!' | '|' => Some('i'),
+        '+' => Some('t'),
+        _ => None,
+    }
+}
+
+/// Removes zero width characters, replaces homoglyphs by their latin counterpart
+/// and lower cases everything.
+pub fn simplify(text: &str) -> String {
+    text.chars()
+        .filter(|character| !is_zero_width(*character))
+        .flat_map(char::to_lowercase)
+        .map(|character| homoglyph(character).unwrap_or(character))
+        .map(|character| character.to_ascii_lowercase())
+        .collect()
+}
+
+/// Like [`simplify`], but keeps the case of the text where it is not needed to
+/// replace a homoglyph, for matching case sensitive parts like link paths.
+pub fn simplify_keeping_case(text: &str) -> String {
+    text.chars()
+        .filter(|character| !is_zero_width(*character))
+        .map(|character| {
+            homoglyph(character)
+                .or_else(|| character.to_lowercase().next().and_then(homoglyph))
+                .unwrap_or(character)
+        })
+        .collect()
+}
+
+/// Normalizes a message for matching against banned phrases:
+/// zero width characters are removed, homoglyphs are replaced by their latin
+/// counterpart, everything is lower cased and leetspeak is translated.
+///
+/// Leetspeak is only translated within words, so that "2 of 3" or "hi!" stay intact:
+/// digits need a neighboring letter and symbols need alphanumeric characters on both sides.
+pub fn normalize(text: &str) -> String {
+    let characters: Vec<char> = simplify(text).chars().collect();
+    let is_letter = |index: Option<usize>| {
+        index
+            .and_then(|index| characters.get(index))
+            .is_some_and(|character| character.is_alphabetic())
+    };
+    let is_alphanumeric = |index: Option<usize>| {
+        index
+            .and_then(|index| characters.get(index))
+            .is_some_and(|character| character.is_alphanumeric())
+    };
+    characters
+        .iter()
+        .enumerate()
+        .map(|(index, character)| {
+            let previous = index.checked_sub(1);
+            let next = Some(index + 1);
+            if let Some(replacement) = leet_digit(*character) {
+                if is_letter(previous) || is_letter(next) {
+                    return replacement;
+                }
+            } else if let Some(replacement) = leet_symbol(*character) {
+                if is_alphanumeric(previous) && is_alphanumeric(next) {
+                    return replacement;
+                }
+            }
+            *character
+        })
+        .collect()
+}
+
+/// Normalizes a text and replaces all runs of non alphanumeric characters by a single space.
+/// The result starts and ends with a space so that whole words can be found with `contains`.
+pub fn normalize_words(text: &str) -> String {
+    let normalized = normalize(text);
+    let words: Vec<&str> = normalized
+        .split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lower_cases_text() {
+        assert_eq!(normalize("HeLLo"), "hello");
+    }
+
+    #[test]
+    fn removes_zero_width_characters() {
+        assert_eq!(normalize("b\u{200B}a\u{200D}d"), "bad");
+    }
+
+    #[test]
+    fn replaces_homoglyphs() {
+        assert_eq!(normalize("ВАD"), "bad");
+        assert_eq!(normalize("ｂａｄ"), "bad");
+    }
+
+    #[test]
+    fn keeping_the_case() {
+        assert_eq!(simplify_keeping_case("Ｂа\u{200B}D"), "BaD");
+    }
+
+    #[test]
+    fn translates_leetspeak_within_words() {
+        assert_eq!(normalize("h4t3"), "hate");
+        assert_eq!(normalize("$h!t"), "$hit");
+        assert_eq!(normalize("b@d"), "bad");
+    }
+
+    #[test]
+    fn keeps_digits_and_punctuation_outside_of_words() {
+        assert_eq!(normalize("2 of 3"), "2 of 3");
+        assert_eq!(normalize("hi!"), "hi!");
+    }
+
+    #[test]
+    fn normalizing_words() {
+        assert_eq!(normalize_words("Hello,  W0rld!!"), " hello world ");
+    }
+}
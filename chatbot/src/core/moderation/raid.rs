@@ -0,0 +1,529 @@
+use super::{default_exempt_roles, normalize::normalize_words};
+use crate::{
+    connect::{ModerationAction, RoomState, TextMessage, UserRole},
+    core::{ChatBotCommand, Messages},
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RaidConfig {
+    pub enabled: bool,
+    // the rates below are measured within this window
+    pub window_seconds: u64,
+    pub max_joins: usize,
+    pub max_first_messages: usize,
+    // number of similar messages by first time chatters that triggers the lockdown
+    pub max_similar_messages: usize,
+    // similarity (0.0 - 1.0) above which two messages are considered similar
+    pub similarity: f64,
+    // lockdown switches the chat to followers-only mode for followers of at least this time
+    pub followers_only: bool,
+    pub followers_only_minutes: u64,
+    pub emote_only: bool,
+    // timeout of the accounts which took part in the raid
+    pub timeout_seconds: u64,
+    pub exempt_roles: Vec<UserRole>,
+    // after a raid of another channel, its viewers' joins and first messages are ignored this long
+    pub raid_grace_seconds: u64,
+}
+
+impl Default for RaidConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_seconds: 30,
+            max_joins: 50,
+            max_first_messages: 10,
+            max_similar_messages: 5,
+            similarity: 0.7,
+            followers_only: true,
+            followers_only_minutes: 10,
+            emote_only: false,
+            timeout_seconds: 600,
+            exempt_roles: default_exempt_roles(),
+            raid_grace_seconds: 120,
+        }
+    }
+}
+
+// Character trigrams of the normalized message, used to compare messages
+fn trigrams(text: &str) -> HashSet<String> {
+    let characters: Vec<char> = normalize_words(text).trim().chars().collect();
+    if characters.len() < 3 {
+        return HashSet::from([characters.iter().collect()]);
+    }
+    characters
+        .windows(3)
+        .map(|trigram| trigram.iter().collect())
+        .collect()
+}
+
+// Jaccard similarity of the trigrams
+fn similarity(first: &HashSet<String>, second: &HashSet<String>) -> f64 {
+    let union = first.union(second).count();
+    if union == 0 {
+        return 1.0;
+    }
+    first.intersection(second).count() as f64 / union as f64
+}
+
+#[derive(Debug)]
+struct FirstMessage {
+    user_name: String,
+    trigrams: HashSet<String>,
+    received: Instant,
+}
+
+// The joins and first messages expected from the viewers of an announced raid
+#[derive(Debug)]
+struct RaidGrace {
+    until: Instant,
+    joins: u64,
+    first_messages: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lockdown {
+    // messages of the raid, further first time chatters writing similar messages are timed out
+    raid_messages: Vec<HashSet<String>>,
+    timed_out_users: Vec<String>,
+    // restore the chat modes the lockdown changed
+    reverts: Vec<ModerationAction>,
+}
+
+/// Detects raids of new accounts by the rate of joins and first time chatters
+/// and the similarity of their messages, and locks the chat down until a moderator ends it.
+#[derive(Debug)]
+pub struct RaidProtection {
+    enabled: bool,
+    window: Duration,
+    max_joins: usize,
+    max_first_messages: usize,
+    max_similar_messages: usize,
+    similarity: f64,
+    followers_only: Option<Duration>,
+    emote_only: bool,
+    timeout: Duration,
+    exempt_roles: Vec<UserRole>,
+    raid_grace: Duration,
+    announced_raid: Option<RaidGrace>,
+    joins: VecDeque<Instant>,
+    first_messages: VecDeque<FirstMessage>,
+    lockdown: Option<Lockdown>,
+    // the chat modes of the channel as reported by twitch
+    room_followers_only: Option<Duration>,
+    room_emote_only: bool,
+    messages: Messages,
+}
+
+impl RaidProtection {
+    pub fn new(config: &RaidConfig, messages: Messages) -> Self {
+        Self {
+            enabled: config.enabled,
+            window: Duration::from_secs(config.window_seconds),
+            max_joins: config.max_joins,
+            max_first_messages: config.max_first_messages,
+            max_similar_messages: config.max_similar_messages,
+            similarity: config.similarity,
+            followers_only: Some(Duration::from_secs(config.followers_only_minutes * 60))
+                .filter(|_| config.followers_only),
+            emote_only: config.emote_only,
+            timeout: Duration::from_secs(config.timeout_seconds),
+            exempt_roles: config.exempt_roles.to_owned(),
+            raid_grace: Duration::from_secs(config.raid_grace_seconds),
+            announced_raid: None,
+            joins: VecDeque::default(),
+            first_messages: VecDeque::default(),
+            lockdown: None,
+            room_followers_only: None,
+            room_emote_only: false,
+            messages,
+        }
+    }
+
+    pub fn is_locked_down(&self) -> bool {
+        self.lockdown.is_some()
+    }
+
+    /// Remembers the chat modes, a lockdown restores them when it ends
+    pub fn room_state(&mut self, room_state: &RoomState) {
+        if let Some(followers_only) = room_state.followers_only {
+            self.room_followers_only = followers_only;
+        }
+        if let Some(emote_only) = room_state.emote_only {
+            self.room_emote_only = emote_only;
+        }
+    }
+
+    /// Another channel raided with the given number of viewers, as many joins and
+    /// first messages are ignored within the grace time so the raid does not trigger a lockdown
+    pub fn raid_announced(&mut self, viewers: u64, now: Instant) {
+        self.announced_raid = Some(RaidGrace {
+            until: now + self.raid_grace,
+            joins: viewers,
+            first_messages: viewers,
+        });
+    }
+
+    // The announced raid which is still within its grace time
+    fn announced_raid(&mut self, now: Instant) -> Option<&mut RaidGrace> {
+        self.announced_raid.as_mut().filter(|raid| raid.until > now)
+    }
+
+    fn forget_old_events(&mut self, now: Instant) {
+        while matches!(self.joins.front(), Some(join) if now.duration_since(*join) > self.window) {
+            self.joins.pop_front();
+        }
+        while matches!(self.first_messages.front(),
+                       Some(message) if now.duration_since(message.received) > self.window)
+        {
+            self.first_messages.pop_front();
+        }
+    }
+
+    fn timeout_command(&self, user_name: &str) -> ChatBotCommand {
+        ChatBotCommand::Moderate(ModerationAction::Timeout {
+            user: user_name.to_owned(),
+            duration: self.timeout,
+            reason: Some(self.messages.text("lockdown.timeout_reason")),
+        })
+    }
+
+    pub fn joined(&mut self, now: Instant) -> Option<ChatBotCommand> {
+        if !self.enabled || self.is_locked_down() {
+            return None;
+        }
+        if let Some(raid) = self.announced_raid(now).filter(|raid| raid.joins > 0) {
+            raid.joins -= 1;
+            return None;
+        }
+        self.forget_old_events(now);
+        self.joins.push_back(now);
+        if self.joins.len() >= self.max_joins {
+            return Some(
+                self.start_lockdown(
+                    self.messages
+                        .format("lockdown.joins", &[("count", &self.joins.len())]),
+                    Vec::default(),
+                    Vec::default(),
+                ),
+            );
+        }
+        None
+    }
+
+    pub fn check(&mut self, message: &TextMessage, now: Instant) -> Option<ChatBotCommand> {
+        if !self.enabled || !message.first_message || message.user.has_any_role(&self.exempt_roles)
+        {
+            return None;
+        }
+        let trigrams = trigrams(&message.text);
+        if let Some(lockdown) = &mut self.lockdown {
+            let similar = lockdown
+                .raid_messages
+                .iter()
+                .any(|raid_message| similarity(raid_message, &trigrams) >= self.similarity);
+            if !similar {
+                return None;
+            }
+            lockdown.timed_out_users.push(message.user.name.to_owned());
+            return Some(self.timeout_command(&message.user.name));
+        }
+        if let Some(raid) = self
+            .announced_raid(now)
+            .filter(|raid| raid.first_messages > 0)
+        {
+            raid.first_messages -= 1;
+            return None;
+        }
+        self.forget_old_events(now);
+        self.first_messages.push_back(FirstMessage {
+            user_name: message.user.name.to_owned(),
+            trigrams,
+            received: now,
+        });
+        let latest = &self.first_messages[self.first_messages.len() - 1].trigrams;
+        let similar: Vec<&FirstMessage> = self
+            .first_messages
+            .iter()
+            .filter(|message| similarity(&message.trigrams, latest) >= self.similarity)
+            .collect();
+        if similar.len() >= self.max_similar_messages {
+            let mut users: Vec<String> = Vec::default();
+            for message in &similar {
+                if !users.contains(&message.user_name) {
+                    users.push(message.user_name.to_owned());
+                }
+            }
+            let raid_messages = similar
+                .iter()
+                .map(|message| message.trigrams.to_owned())
+                .collect();
+            return Some(
+                self.start_lockdown(
+                    self.messages
+                        .format("lockdown.similar_messages", &[("count", &similar.len())]),
+                    users,
+                    raid_messages,
+                ),
+            );
+        }
+        if self.first_messages.len() >= self.max_first_messages {
+            return Some(self.start_lockdown(
+                self.messages.format(
+                    "lockdown.first_messages",
+                    &[("count", &self.first_messages.len())],
+                ),
+                Vec::default(),
+                Vec::default(),
+            ));
+        }
+        None
+    }
+
+    /// Locks the chat down on request of a moderator
+    pub fn lockdown(&mut self) -> Option<ChatBotCommand> {
+        if self.is_locked_down() {
+            return None;
+        }
+        Some(self.start_lockdown(
+            self.messages.text("lockdown.requested"),
+            Vec::default(),
+            Vec::default(),
+        ))
+    }
+
+    // Switches the chat modes, times out the raiders and alerts the moderators
+    fn start_lockdown(
+        &mut self,
+        cause: String,
+        users: Vec<String>,
+        raid_messages: Vec<HashSet<String>>,
+    ) -> ChatBotCommand {
+        use ChatBotCommand::*;
+        let mut commands = vec![SendMessage(
+            self.messages.format("lockdown", &[("cause", &cause)]),
+        )];
+        let mut reverts = Vec::default();
+        if let Some(follow_time) = self.followers_only {
+            if self.room_followers_only != Some(follow_time) {
+                commands.push(Moderate(ModerationAction::FollowersOnly(Some(follow_time))));
+                reverts.push(ModerationAction::FollowersOnly(self.room_followers_only));
+            }
+        }
+        if self.emote_only && !self.room_emote_only {
+            commands.push(Moderate(ModerationAction::EmoteOnly(true)));
+            reverts.push(ModerationAction::EmoteOnly(false));
+        }
+        commands.extend(users.iter().map(|user| self.timeout_command(user)));
+        self.joins.clear();
+        self.first_messages.clear();
+        self.lockdown = Some(Lockdown {
+            raid_messages,
+            timed_out_users: users,
+            reverts,
+        });
+        MultipleCommands(commands)
+    }
+
+    /// Reverts the chat modes and timeouts of the lockdown
+    pub fn end_lockdown(&mut self) -> Option<ChatBotCommand> {
+        use ChatBotCommand::*;
+        let lockdown = self.lockdown.take()?;
+        let mut commands = vec![SendMessage(self.messages.text("lockdown.ended"))];
+        commands.extend(lockdown.reverts.into_iter().map(Moderate));
+        commands.extend(
+            lockdown
+                .timed_out_users
+                .into_iter()
+                .map(|user| Moderate(ModerationAction::Unban(user))),
+        );
+        Some(MultipleCommands(commands))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::UserInfo;
+
+    fn first_message(user_name: &str, text: &str) -> TextMessage {
+        TextMessage {
+            text: text.to_owned(),
+            user: UserInfo {
+                name: user_name.to_owned(),
+                ..Default::default()
+            },
+            first_message: true,
+            ..Default::default()
+        }
+    }
+
+    fn protection() -> RaidProtection {
+        RaidProtection::new(
+            &RaidConfig {
+                enabled: true,
+                max_joins: 3,
+                max_first_messages: 4,
+                max_similar_messages: 3,
+                ..Default::default()
+            },
+            Messages::default(),
+        )
+    }
+
+    fn timed_out_users(command: &ChatBotCommand) -> Vec<String> {
+        match command {
+            ChatBotCommand::MultipleCommands(commands) => commands
+                .iter()
+                .filter_map(|command| match command {
+                    ChatBotCommand::Moderate(ModerationAction::Timeout { user, .. }) => {
+                        Some(user.to_owned())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::default(),
+        }
+    }
+
+    #[test]
+    fn similar_messages_trigger_lockdown() {
+        let mut protection = protection();
+        let now = Instant::now();
+        assert!(protection
+            .check(&first_message("raider1", "HATE MESSAGE xyz"), now)
+            .is_none());
+        assert!(protection
+            .check(&first_message("newbie", "hello everyone"), now)
+            .is_none());
+        assert!(protection
+            .check(&first_message("raider2", "hate message xy"), now)
+            .is_none());
+        let command = protection
+            .check(&first_message("raider3", "hate message xyz!"), now)
+            .unwrap();
+        assert_eq!(
+            timed_out_users(&command),
+            vec!["raider1", "raider2", "raider3"]
+        );
+        assert!(protection.is_locked_down());
+        assert!(matches!(
+            protection.check(&first_message("raider4", "hate messages xyz"), now),
+            Some(ChatBotCommand::Moderate(ModerationAction::Timeout { user, .. })) if user == "raider4"
+        ));
+        assert!(protection
+            .check(&first_message("newbie2", "what happened?"), now)
+            .is_none());
+    }
+
+    #[test]
+    fn joins_trigger_lockdown() {
+        let mut protection = protection();
+        let now = Instant::now();
+        assert!(protection.joined(now).is_none());
+        assert!(protection.joined(now + Duration::from_secs(60)).is_none());
+        assert!(protection.joined(now + Duration::from_secs(61)).is_none());
+        assert!(protection.joined(now + Duration::from_secs(62)).is_some());
+        assert!(protection.is_locked_down());
+    }
+
+    #[test]
+    fn announced_raids_are_ignored() {
+        let mut protection = protection();
+        let now = Instant::now();
+        protection.raid_announced(3, now);
+        for user in ["raider1", "raider2", "raider3"] {
+            assert!(protection.joined(now).is_none());
+            assert!(protection
+                .check(&first_message(user, "raid hype"), now)
+                .is_none());
+        }
+        assert!(!protection.is_locked_down());
+        // more users than the raid brought along
+        assert!(protection.joined(now).is_none());
+        assert!(protection.joined(now).is_none());
+        assert!(protection.joined(now).is_some());
+    }
+
+    #[test]
+    fn raid_grace_expires() {
+        let mut protection = protection();
+        let now = Instant::now();
+        protection.raid_announced(100, now);
+        let later = now + Duration::from_secs(121);
+        assert!(protection.joined(later).is_none());
+        assert!(protection.joined(later).is_none());
+        assert!(protection.joined(later).is_some());
+    }
+
+    #[test]
+    fn ending_lockdown_reverts_changes() {
+        let mut protection = protection();
+        let now = Instant::now();
+        for user in ["raider1", "raider2", "raider3"] {
+            protection.check(&first_message(user, "same text"), now);
+        }
+        match protection.end_lockdown() {
+            Some(ChatBotCommand::MultipleCommands(commands)) => assert!(matches!(
+                &commands[..],
+                [
+                    ChatBotCommand::SendMessage(_),
+                    ChatBotCommand::Moderate(ModerationAction::FollowersOnly(None)),
+                    ChatBotCommand::Moderate(ModerationAction::Unban(_)),
+                    ChatBotCommand::Moderate(ModerationAction::Unban(_)),
+                    ChatBotCommand::Moderate(ModerationAction::Unban(_)),
+                ]
+            )),
+            other => panic!("Unexpected lockdown end {:?}", other),
+        }
+        assert!(!protection.is_locked_down());
+        assert!(protection.end_lockdown().is_none());
+
+        // the followers-only mode the channel had before is restored
+        protection.room_state(&RoomState {
+            followers_only: Some(Some(Duration::from_secs(60))),
+            emote_only: Some(false),
+        });
+        protection.lockdown();
+        match protection.end_lockdown() {
+            Some(ChatBotCommand::MultipleCommands(commands)) => assert!(matches!(
+                &commands[..],
+                [
+                    ChatBotCommand::SendMessage(_),
+                    ChatBotCommand::Moderate(ModerationAction::FollowersOnly(Some(follow_time))),
+                ] if *follow_time == Duration::from_secs(60)
+            )),
+            other => panic!("Unexpected lockdown end {:?}", other),
+        }
+
+        // modes the channel already had are left alone
+        protection.room_state(&RoomState {
+            followers_only: Some(Some(Duration::from_secs(600))),
+            emote_only: None,
+        });
+        protection.lockdown();
+        assert!(matches!(
+            protection.end_lockdown(),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands.len() == 1
+        ));
+    }
+
+    #[test]
+    fn regular_messages_are_ignored() {
+        let mut protection = protection();
+        let now = Instant::now();
+        for user in ["a", "b", "c", "d", "e"] {
+            let message = TextMessage {
+                first_message: false,
+                ..first_message(user, "same text")
+            };
+            assert!(protection.check(&message, now).is_none());
+        }
+    }
+}
@@ -0,0 +1,542 @@
+use super::{
+    default_exempt_roles, normalize::simplify, FilterAction, MessageFilter, Messages, Violation,
+};
+use crate::connect::{TextMessage, UserRole};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpamCheckKind {
+    Caps,
+    Symbols,
+    Emotes,
+    Length,
+    RepeatedCharacters,
+    RepeatedMessages,
+}
+
+impl SpamCheckKind {
+    fn default_limit(&self) -> f64 {
+        match self {
+            SpamCheckKind::Caps => 0.7,
+            SpamCheckKind::Symbols => 0.5,
+            SpamCheckKind::Emotes => 10.0,
+            SpamCheckKind::Length => 400.0,
+            SpamCheckKind::RepeatedCharacters => 10.0,
+            SpamCheckKind::RepeatedMessages => 3.0,
+        }
+    }
+
+    fn default_min_length(&self) -> usize {
+        match self {
+            SpamCheckKind::Caps | SpamCheckKind::Symbols => 10,
+            _ => 0,
+        }
+    }
+
+    // the key of the reason in the message catalog
+    fn reason_key(&self) -> &'static str {
+        match self {
+            SpamCheckKind::Caps => "reason.caps",
+            SpamCheckKind::Symbols => "reason.symbols",
+            SpamCheckKind::Emotes => "reason.emotes",
+            SpamCheckKind::Length => "reason.length",
+            SpamCheckKind::RepeatedCharacters => "reason.repeated_characters",
+            SpamCheckKind::RepeatedMessages => "reason.repeated_messages",
+        }
+    }
+}
+
+/// Settings of a single spam check. The meaning of `limit` depends on the check:
+/// - caps: maximum ratio of upper case letters (0.0 - 1.0)
+/// - symbols: maximum ratio of symbols to all non whitespace characters (0.0 - 1.0)
+/// - emotes: maximum number of emotes
+/// - length: maximum number of characters
+/// - repeated_characters: maximum number of the same character in a row
+/// - repeated_messages: maximum number of identical messages of a user within `window_seconds`
+///
+/// The n-th offence of a user (within `reset_seconds` of the previous one)
+/// is punished with the n-th penalty, the last penalty is used for all further offences.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SpamCheckConfig {
+    pub enabled: bool,
+    pub limit: Option<f64>,
+    pub min_length: Option<usize>,
+    pub exempt_roles: Option<Vec<UserRole>>,
+    pub penalties: Option<Vec<FilterAction>>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SpamConfig {
+    pub caps: SpamCheckConfig,
+    pub symbols: SpamCheckConfig,
+    pub emotes: SpamCheckConfig,
+    pub length: SpamCheckConfig,
+    pub repeated_characters: SpamCheckConfig,
+    pub repeated_messages: SpamCheckConfig,
+    // time span in which identical messages count as repetitions
+    pub window_seconds: u64,
+    // time after which a user's offences are forgotten
+    pub reset_seconds: u64,
+}
+
+impl Default for SpamConfig {
+    fn default() -> Self {
+        Self {
+            caps: SpamCheckConfig::default(),
+            symbols: SpamCheckConfig::default(),
+            emotes: SpamCheckConfig::default(),
+            length: SpamCheckConfig::default(),
+            repeated_characters: SpamCheckConfig::default(),
+            repeated_messages: SpamCheckConfig::default(),
+            window_seconds: 60,
+            reset_seconds: 3600,
+        }
+    }
+}
+
+fn default_penalties() -> Vec<FilterAction> {
+    vec![
+        FilterAction::Delete,
+        FilterAction::Timeout { seconds: 60 },
+        FilterAction::Timeout { seconds: 600 },
+    ]
+}
+
+#[derive(Debug)]
+struct Offences {
+    count: usize,
+    last: Instant,
+}
+
+#[derive(Debug)]
+struct SpamCheck {
+    kind: SpamCheckKind,
+    limit: f64,
+    min_length: usize,
+    exempt_roles: Vec<UserRole>,
+    penalties: Vec<FilterAction>,
+    reason: String,
+    offences: HashMap<String, Offences>,
+}
+
+impl SpamCheck {
+    fn new(kind: SpamCheckKind, config: &SpamCheckConfig, messages: &Messages) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        Some(Self {
+            kind,
+            limit: config.limit.unwrap_or_else(|| kind.default_limit()),
+            min_length: config
+                .min_length
+                .unwrap_or_else(|| kind.default_min_length()),
+            exempt_roles: config
+                .exempt_roles
+                .to_owned()
+                .unwrap_or_else(default_exempt_roles),
+            penalties: config
+                .penalties
+                .to_owned()
+                .filter(|penalties| !penalties.is_empty())
+                .unwrap_or_else(default_penalties),
+            reason: config
+                .reason
+                .to_owned()
+                .unwrap_or_else(|| messages.text(kind.reason_key())),
+            offences: HashMap::default(),
+        })
+    }
+
+    fn applies_to(&self, message: &TextMessage) -> bool {
+        !message.user.has_any_role(&self.exempt_roles)
+            && message.text.chars().count() >= self.min_length
+    }
+
+    fn is_violated_by(&self, message: &TextMessage, repetitions: usize) -> bool {
+        let limit = self.limit;
+        match self.kind {
+            SpamCheckKind::Caps => caps_ratio(&message.text_without_emotes()) > limit,
+            SpamCheckKind::Symbols => symbol_ratio(&message.text_without_emotes()) > limit,
+            SpamCheckKind::Emotes => message.emotes.len() as f64 > limit,
+            SpamCheckKind::Length => message.text.chars().count() as f64 > limit,
+            SpamCheckKind::RepeatedCharacters => {
+                longest_character_run(&message.text) as f64 > limit
+            }
+            SpamCheckKind::RepeatedMessages => repetitions as f64 > limit,
+        }
+    }
+
+    fn penalty(&mut self, user_name: &str, now: Instant, reset_after: Duration) -> FilterAction {
+        let offences = self
+            .offences
+            .entry(user_name.to_owned())
+            .or_insert(Offences {
+                count: 0,
+                last: now,
+            });
+        if now.duration_since(offences.last) > reset_after {
+            offences.count = 0;
+        }
+        offences.count += 1;
+        offences.last = now;
+        let index = (offences.count - 1).min(self.penalties.len() - 1);
+        self.penalties[index].clone()
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+fn caps_ratio(text: &str) -> f64 {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    let upper_case = letters.iter().filter(|c| c.is_uppercase()).count();
+    ratio(upper_case, letters.len())
+}
+
+fn symbol_ratio(text: &str) -> f64 {
+    let characters: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let symbols = characters.iter().filter(|c| !c.is_alphanumeric()).count();
+    ratio(symbols, characters.len())
+}
+
+fn longest_character_run(text: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous = None;
+    for character in text.to_lowercase().chars() {
+        if character.is_whitespace() {
+            previous = None;
+            current = 0;
+            continue;
+        }
+        current = if previous == Some(character) {
+            current + 1
+        } else {
+            1
+        };
+        previous = Some(character);
+        longest = longest.max(current);
+    }
+    longest
+}
+
+/// Heuristic spam checks: caps, symbols, emotes, message length,
+/// repeated characters and repeated messages.
+#[derive(Debug)]
+pub struct SpamFilter {
+    checks: Vec<SpamCheck>,
+    window: Duration,
+    reset_after: Duration,
+    // the recent (simplified) messages of every user, for detecting repetitions
+    recent_messages: HashMap<String, VecDeque<(Instant, String)>>,
+    // old messages and offences of all users are forgotten once per window
+    last_forgotten: Option<Instant>,
+}
+
+impl SpamFilter {
+    pub fn new(config: &SpamConfig, messages: &Messages) -> Self {
+        use SpamCheckKind::*;
+        let checks = [
+            (Caps, &config.caps),
+            (Symbols, &config.symbols),
+            (Emotes, &config.emotes),
+            (Length, &config.length),
+            (RepeatedCharacters, &config.repeated_characters),
+            (RepeatedMessages, &config.repeated_messages),
+        ]
+        .into_iter()
+        .filter_map(|(kind, check_config)| SpamCheck::new(kind, check_config, messages))
+        .collect();
+        Self {
+            checks,
+            window: Duration::from_secs(config.window_seconds),
+            reset_after: Duration::from_secs(config.reset_seconds),
+            recent_messages: HashMap::default(),
+            last_forgotten: None,
+        }
+    }
+
+    // Forgets the messages out of the window and the offences after the reset time,
+    // e.g. of users who left, at most once per window
+    fn forget_old_events(&mut self, now: Instant) {
+        if matches!(self.last_forgotten, Some(last) if now.duration_since(last) < self.window) {
+            return;
+        }
+        self.last_forgotten = Some(now);
+        let window = self.window;
+        self.recent_messages.retain(|_, recent_messages| {
+            recent_messages
+                .back()
+                .is_some_and(|(time, _)| now.duration_since(*time) <= window)
+        });
+        let reset_after = self.reset_after;
+        for check in &mut self.checks {
+            check
+                .offences
+                .retain(|_, offences| now.duration_since(offences.last) <= reset_after);
+        }
+    }
+
+    // Remembers the message and returns how often the user sent it within the window
+    fn count_repetitions(&mut self, message: &TextMessage, now: Instant) -> usize {
+        let text = simplify(message.text.trim());
+        let window = self.window;
+        let recent_messages = self
+            .recent_messages
+            .entry(message.user.name.to_owned())
+            .or_default();
+        while let Some((time, _)) = recent_messages.front() {
+            if now.duration_since(*time) > window {
+                recent_messages.pop_front();
+            } else {
+                break;
+            }
+        }
+        recent_messages.push_back((now, text.to_owned()));
+        recent_messages
+            .iter()
+            .filter(|(_, recent_text)| *recent_text == text)
+            .count()
+    }
+}
+
+impl MessageFilter for SpamFilter {
+    fn check(&mut self, message: &TextMessage, now: Instant) -> Option<Violation> {
+        if self.checks.is_empty() {
+            return None;
+        }
+        self.forget_old_events(now);
+        let repetitions = self.count_repetitions(message, now);
+        let violated_check = self.checks.iter().position(|check| {
+            check.applies_to(message) && check.is_violated_by(message, repetitions)
+        })?;
+        let reset_after = self.reset_after;
+        let check = &mut self.checks[violated_check];
+        Some(Violation {
+            reason: check.reason.to_owned(),
+            action: check.penalty(&message.user.name, now, reset_after),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::{Emote, UserInfo};
+    use std::collections::HashSet;
+
+    fn enabled() -> SpamCheckConfig {
+        SpamCheckConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn message(text: &str) -> TextMessage {
+        TextMessage {
+            id: "message-id".to_owned(),
+            text: text.to_owned(),
+            user: UserInfo {
+                name: "chatter".to_owned(),
+                badges: HashSet::default(),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn is_violation(config: SpamConfig, message: &TextMessage) -> bool {
+        SpamFilter::new(&config, &Messages::default())
+            .check(message, Instant::now())
+            .is_some()
+    }
+
+    #[test]
+    fn detecting_caps() {
+        let config = || SpamConfig {
+            caps: enabled(),
+            ..Default::default()
+        };
+        assert!(is_violation(config(), &message("WHY IS NOBODY ANSWERING")));
+        assert!(!is_violation(config(), &message("Why is nobody answering")));
+        // too short to be checked
+        assert!(!is_violation(config(), &message("LOL")));
+    }
+
+    #[test]
+    fn caps_in_emotes_are_ignored() {
+        let config = SpamConfig {
+            caps: enabled(),
+            ..Default::default()
+        };
+        let mut with_emotes = message("LUL LUL LUL that was funny");
+        with_emotes.emotes = (0..3)
+            .map(|index| Emote {
+                id: "425618".to_owned(),
+                start: index * 4,
+                end: index * 4 + 2,
+            })
+            .collect();
+        assert!(!is_violation(config, &with_emotes));
+    }
+
+    #[test]
+    fn detecting_symbols() {
+        let config = || SpamConfig {
+            symbols: enabled(),
+            ..Default::default()
+        };
+        assert!(is_violation(config(), &message("$$$ ### !!! hi ???")));
+        assert!(!is_violation(
+            config(),
+            &message("Hello there, how are you?")
+        ));
+    }
+
+    #[test]
+    fn detecting_emote_spam() {
+        let config = SpamConfig {
+            emotes: SpamCheckConfig {
+                limit: Some(2.0),
+                ..enabled()
+            },
+            ..Default::default()
+        };
+        let mut emote_spam = message("Kappa Kappa Kappa");
+        emote_spam.emotes = (0..3)
+            .map(|index| Emote {
+                id: "25".to_owned(),
+                start: index * 6,
+                end: index * 6 + 4,
+            })
+            .collect();
+        assert!(is_violation(config, &emote_spam));
+    }
+
+    #[test]
+    fn detecting_long_messages() {
+        let config = || SpamConfig {
+            length: SpamCheckConfig {
+                limit: Some(20.0),
+                ..enabled()
+            },
+            ..Default::default()
+        };
+        assert!(is_violation(
+            config(),
+            &message("This message is way too long")
+        ));
+        assert!(!is_violation(config(), &message("Short message")));
+    }
+
+    #[test]
+    fn detecting_repeated_characters() {
+        let config = || SpamConfig {
+            repeated_characters: enabled(),
+            ..Default::default()
+        };
+        assert!(is_violation(config(), &message("NOOOOOOOOOOOOOOOO")));
+        assert!(!is_violation(config(), &message("Noooo")));
+    }
+
+    #[test]
+    fn detecting_repeated_messages() {
+        let mut filter = SpamFilter::new(
+            &SpamConfig {
+                repeated_messages: SpamCheckConfig {
+                    limit: Some(2.0),
+                    ..enabled()
+                },
+                ..Default::default()
+            },
+            &Messages::default(),
+        );
+        let now = Instant::now();
+        assert!(filter.check(&message("buy stuff"), now).is_none());
+        assert!(filter.check(&message("Buy stuff"), now).is_none());
+        assert!(filter.check(&message("buy stuff"), now).is_some());
+        // the earlier messages are out of the window
+        let later = now + Duration::from_secs(61);
+        assert!(filter.check(&message("buy stuff"), later).is_none());
+
+        let mut other_message = message("hello");
+        other_message.user.name = "other".to_owned();
+        filter.check(&other_message, later + Duration::from_secs(61));
+        assert_eq!(
+            filter.recent_messages.keys().collect::<Vec<_>>(),
+            vec!["other"]
+        );
+    }
+
+    #[test]
+    fn penalties_escalate() {
+        let mut filter = SpamFilter::new(
+            &SpamConfig {
+                caps: enabled(),
+                ..Default::default()
+            },
+            &Messages::default(),
+        );
+        let now = Instant::now();
+        let penalties: Vec<FilterAction> = (0..4)
+            .filter_map(|_| filter.check(&message("STOP SHOUTING PLEASE"), now))
+            .map(|violation| violation.action)
+            .collect();
+        assert_eq!(
+            penalties,
+            vec![
+                FilterAction::Delete,
+                FilterAction::Timeout { seconds: 60 },
+                FilterAction::Timeout { seconds: 600 },
+                FilterAction::Timeout { seconds: 600 },
+            ]
+        );
+        // offences are forgotten after some time
+        let later = now + Duration::from_secs(3601);
+        let violation = filter.check(&message("STOP SHOUTING PLEASE"), later);
+        assert_eq!(violation.unwrap().action, FilterAction::Delete);
+    }
+
+    #[test]
+    fn forgetting_old_offences() {
+        let mut filter = SpamFilter::new(
+            &SpamConfig {
+                caps: enabled(),
+                ..Default::default()
+            },
+            &Messages::default(),
+        );
+        let now = Instant::now();
+        filter.check(&message("STOP SHOUTING PLEASE"), now);
+        assert_eq!(filter.checks[0].offences.len(), 1);
+        let mut other_message = message("hello");
+        other_message.user.name = "other".to_owned();
+        filter.check(&other_message, now + Duration::from_secs(3601));
+        assert!(filter.checks[0].offences.is_empty());
+    }
+
+    #[test]
+    fn exempt_roles_are_not_checked() {
+        let config = SpamConfig {
+            caps: enabled(),
+            ..Default::default()
+        };
+        let mut vip_message = message("WHY IS NOBODY ANSWERING");
+        vip_message.user.badges.insert(crate::connect::Badge {
+            name: "vip".to_owned(),
+            level: 1,
+        });
+        assert!(!is_violation(config, &vip_message));
+    }
+}
@@ -0,0 +1,159 @@
+use super::FilterAction;
+use crate::core::storage::Storage;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+const STRIKES_BUCKET: &str = "strikes";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Strike {
+    pub reason: String,
+    pub time: SystemTime,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StrikesConfig {
+    // when enabled, filter violations are punished according to the user's strikes
+    // instead of the filter's own action
+    pub enabled: bool,
+    // punishment for the first, second, ... strike, the last one is used for all further strikes
+    pub ladder: Vec<FilterAction>,
+    // time after which a strike is no longer counted
+    pub decay_seconds: u64,
+}
+
+impl Default for StrikesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ladder: vec![
+                FilterAction::Warn,
+                FilterAction::Timeout { seconds: 60 },
+                FilterAction::Timeout { seconds: 3600 },
+                FilterAction::Ban,
+            ],
+            decay_seconds: 7 * 86400,
+        }
+    }
+}
+
+/// Keeps track of the strikes of every user across all filters.
+/// Strikes are persisted in the bot's storage.
+#[derive(Debug)]
+pub struct StrikeTracker {
+    ladder: Vec<FilterAction>,
+    decay: Duration,
+    storage: Storage,
+}
+
+impl StrikeTracker {
+    pub fn new(config: &StrikesConfig, storage: Storage) -> Self {
+        Self {
+            ladder: if config.ladder.is_empty() {
+                StrikesConfig::default().ladder
+            } else {
+                config.ladder.to_owned()
+            },
+            decay: Duration::from_secs(config.decay_seconds),
+            storage,
+        }
+    }
+
+    /// The strikes of the user which have not decayed yet
+    pub fn strikes(&self, user_name: &str, now: SystemTime) -> Vec<Strike> {
+        let strikes: Vec<Strike> = self
+            .storage
+            .get(STRIKES_BUCKET, &user_name.to_lowercase())
+            .unwrap_or_else(|err| {
+                println!("Could not load strikes: {:?}", err);
+                None
+            })
+            .unwrap_or_default();
+        strikes
+            .into_iter()
+            .filter(|strike| strike.time + self.decay > now)
+            .collect()
+    }
+
+    /// Adds a strike and returns the number of active strikes and the resulting punishment
+    pub fn record(&self, user_name: &str, reason: &str, now: SystemTime) -> (usize, FilterAction) {
+        let mut strikes = self.strikes(user_name, now);
+        strikes.push(Strike {
+            reason: reason.to_owned(),
+            time: now,
+        });
+        let count = strikes.len();
+        if let Err(err) = self
+            .storage
+            .set(STRIKES_BUCKET, &user_name.to_lowercase(), strikes)
+        {
+            println!("Could not store strikes: {:?}", err);
+        }
+        let action = self.ladder[(count - 1).min(self.ladder.len() - 1)].clone();
+        (count, action)
+    }
+
+    /// Removes all strikes of the user, returns whether the user had any
+    pub fn clear(&self, user_name: &str, now: SystemTime) -> bool {
+        let had_strikes = !self.strikes(user_name, now).is_empty();
+        if let Err(err) = self
+            .storage
+            .remove(STRIKES_BUCKET, &user_name.to_lowercase())
+        {
+            println!("Could not remove strikes: {:?}", err);
+        }
+        had_strikes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> StrikeTracker {
+        StrikeTracker::new(&StrikesConfig::default(), Storage::temporary().unwrap())
+    }
+
+    #[test]
+    fn punishments_escalate() {
+        let tracker = tracker();
+        let now = SystemTime::now();
+        let actions: Vec<(usize, FilterAction)> = (0..5)
+            .map(|_| tracker.record("Carkhy", "spam", now))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (1, FilterAction::Warn),
+                (2, FilterAction::Timeout { seconds: 60 }),
+                (3, FilterAction::Timeout { seconds: 3600 }),
+                (4, FilterAction::Ban),
+                (5, FilterAction::Ban),
+            ]
+        );
+    }
+
+    #[test]
+    fn strikes_decay() {
+        let tracker = tracker();
+        let now = SystemTime::now();
+        tracker.record("carkhy", "spam", now);
+        let later = now + Duration::from_secs(7 * 86400 + 1);
+        assert!(tracker.strikes("carkhy", later).is_empty());
+        assert_eq!(
+            tracker.record("carkhy", "spam", later),
+            (1, FilterAction::Warn)
+        );
+    }
+
+    #[test]
+    fn clearing_strikes() {
+        let tracker = tracker();
+        let now = SystemTime::now();
+        tracker.record("carkhy", "spam", now);
+        assert!(tracker.clear("Carkhy", now));
+        assert!(tracker.strikes("carkhy", now).is_empty());
+        assert!(!tracker.clear("carkhy", now));
+    }
+}
@@ -0,0 +1,704 @@
+use super::{ChatBotCommand, Storage};
+use crate::connect::{ChatBotEvent, ModerationAction, UserInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use thiserror::Error;
+use wasmi::{
+    Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, TypedFunc,
+};
+
+const PLUGIN_EXTENSION: &str = "wasm";
+const IMPORT_MODULE: &str = "chatbot";
+// how often the plugin directory is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+// a plugin failing this often in a row is stopped until its file changes
+const MAX_FAILURES: u32 = 3;
+// the largest output read from a plugin
+const MAX_OUTPUT_BYTES: u32 = 1024 * 1024;
+
+/// What a plugin may do besides receiving events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    SendMessages,
+    Moderate,
+    Storage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PluginConfig {
+    // directory of `<name>.wasm` modules, changed files are reloaded while the bot runs
+    pub directory: Option<String>,
+    // instructions a plugin may run per event
+    pub fuel: u64,
+    pub max_memory_bytes: usize,
+    // capabilities by plugin name, plugins without capabilities only receive events
+    pub permissions: HashMap<String, Vec<Capability>>,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            fuel: 10_000_000,
+            max_memory_bytes: 16 * 1024 * 1024,
+            permissions: HashMap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("Could not read the plugin [{}]", .0)]
+    Io(#[from] io::Error),
+    #[error("The plugin failed [{}]", .0)]
+    Wasm(#[from] wasmi::Error),
+    #[error("The plugin does not export {}", .0)]
+    MissingExport(&'static str),
+    #[error("Invalid output of the plugin [{}]", .0)]
+    Output(String),
+}
+
+/// An event as it is passed to plugins, serialized as JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginEvent {
+    Message {
+        id: String,
+        user: String,
+        badges: Vec<String>,
+        text: String,
+        bits: u64,
+    },
+    Command {
+        user: String,
+        badges: Vec<String>,
+        name: String,
+        options: Vec<String>,
+    },
+    Join {
+        user: String,
+    },
+    Part {
+        user: String,
+    },
+    Follow {
+        user: String,
+    },
+    Raid {
+        user: String,
+        viewers: u64,
+    },
+    Redemption {
+        user: String,
+        reward: String,
+        input: String,
+    },
+    StreamOnline,
+    StreamOffline,
+}
+
+fn badges(user: &UserInfo) -> Vec<String> {
+    let mut badges: Vec<String> = user
+        .badges
+        .iter()
+        .map(|badge| badge.name.to_owned())
+        .collect();
+    badges.sort();
+    badges
+}
+
+impl PluginEvent {
+    /// The event plugins receive, internal events are not passed on
+    pub fn from_event(event: &ChatBotEvent) -> Option<Self> {
+        Some(match event {
+            ChatBotEvent::TextMessage(message) => PluginEvent::Message {
+                id: message.id.to_owned(),
+                user: message.user.name.to_owned(),
+                badges: badges(&message.user),
+                text: message.text.to_owned(),
+                bits: message.bits,
+            },
+            ChatBotEvent::Command(command) => PluginEvent::Command {
+                user: command.user.name.to_owned(),
+                badges: badges(&command.user),
+                name: command.name.to_owned(),
+                options: command.options.to_owned(),
+            },
+            ChatBotEvent::Join(user) => PluginEvent::Join {
+                user: user.to_owned(),
+            },
+            ChatBotEvent::Part(user) => PluginEvent::Part {
+                user: user.to_owned(),
+            },
+            ChatBotEvent::Follow { user_name } => PluginEvent::Follow {
+                user: user_name.to_owned(),
+            },
+            ChatBotEvent::Raid { user_name, viewers } => PluginEvent::Raid {
+                user: user_name.to_owned(),
+                viewers: *viewers,
+            },
+            ChatBotEvent::Redemption {
+                user_name,
+                reward_title,
+                input,
+                ..
+            } => PluginEvent::Redemption {
+                user: user_name.to_owned(),
+                reward: reward_title.to_owned(),
+                input: input.to_owned(),
+            },
+            ChatBotEvent::StreamOnline { .. } => PluginEvent::StreamOnline,
+            ChatBotEvent::StreamOffline => PluginEvent::StreamOffline,
+            _ => return None,
+        })
+    }
+}
+
+/// What a plugin asks the bot to do, deserialized from its JSON output
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginCommand {
+    SendMessage {
+        text: String,
+    },
+    DeleteMessage {
+        id: String,
+    },
+    Timeout {
+        user: String,
+        seconds: u64,
+        reason: Option<String>,
+    },
+    Ban {
+        user: String,
+        reason: Option<String>,
+    },
+}
+
+impl PluginCommand {
+    fn capability(&self) -> Capability {
+        match self {
+            PluginCommand::SendMessage { .. } => Capability::SendMessages,
+            _ => Capability::Moderate,
+        }
+    }
+
+    fn into_command(self) -> ChatBotCommand {
+        match self {
+            // chat messages are a single line
+            PluginCommand::SendMessage { text } => {
+                ChatBotCommand::SendMessage(text.replace(['\r', '\n'], " "))
+            }
+            PluginCommand::DeleteMessage { id } => {
+                ChatBotCommand::Moderate(ModerationAction::DeleteMessage(id))
+            }
+            PluginCommand::Timeout {
+                user,
+                seconds,
+                reason,
+            } => ChatBotCommand::Moderate(ModerationAction::Timeout {
+                user,
+                duration: Duration::from_secs(seconds.max(1)),
+                reason,
+            }),
+            PluginCommand::Ban { user, reason } => {
+                ChatBotCommand::Moderate(ModerationAction::Ban { user, reason })
+            }
+        }
+    }
+}
+
+// the data of a plugin's store, available to the host functions
+#[derive(Debug)]
+struct PluginState {
+    capabilities: Vec<Capability>,
+    storage: Storage,
+    bucket: String,
+    limits: StoreLimits,
+}
+
+// pointer and length of a buffer in the plugin's memory in one value
+fn pack(ptr: i32, len: usize) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as i64
+}
+
+fn unpack(value: i64) -> (u32, u32) {
+    ((value >> 32) as u32, value as u32)
+}
+
+fn read(
+    memory: &Memory,
+    store: impl wasmi::AsContext,
+    ptr: u32,
+    len: u32,
+) -> Result<Vec<u8>, PluginError> {
+    if len > MAX_OUTPUT_BYTES {
+        return Err(PluginError::Output(format!("{} bytes are too many", len)));
+    }
+    let mut buffer = vec![0; len as usize];
+    memory
+        .read(store, ptr as usize, &mut buffer)
+        .map_err(|err| PluginError::Output(err.to_string()))?;
+    Ok(buffer)
+}
+
+fn memory_of(caller: &Caller<'_, PluginState>) -> Result<Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("the plugin does not export its memory"))
+}
+
+fn read_string(
+    caller: &Caller<'_, PluginState>,
+    ptr: i32,
+    len: i32,
+) -> Result<String, wasmi::Error> {
+    let bytes = read(&memory_of(caller)?, caller, ptr as u32, len as u32)
+        .map_err(|err| wasmi::Error::new(err.to_string()))?;
+    String::from_utf8(bytes).map_err(|err| wasmi::Error::new(err.to_string()))
+}
+
+fn check_storage(caller: &Caller<'_, PluginState>) -> Result<(), wasmi::Error> {
+    if caller.data().capabilities.contains(&Capability::Storage) {
+        Ok(())
+    } else {
+        Err(wasmi::Error::new("the plugin may not use the storage"))
+    }
+}
+
+// `storage_get(key_ptr, key_len)` returns the value written to memory allocated with the
+// plugin's `alloc`, or -1 if there is none
+fn storage_get(
+    mut caller: Caller<'_, PluginState>,
+    key_ptr: i32,
+    key_len: i32,
+) -> Result<i64, wasmi::Error> {
+    check_storage(&caller)?;
+    let key = read_string(&caller, key_ptr, key_len)?;
+    let state = caller.data();
+    let value = match state.storage.get::<String>(&state.bucket, &key) {
+        Ok(Some(value)) => value,
+        Ok(None) => return Ok(-1),
+        Err(err) => return Err(wasmi::Error::new(err.to_string())),
+    };
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmi::Error::new("the plugin does not export alloc"))?
+        .typed::<i32, i32>(&caller)?;
+    let ptr = alloc.call(&mut caller, value.len() as i32)?;
+    memory_of(&caller)?
+        .write(&mut caller, ptr as u32 as usize, value.as_bytes())
+        .map_err(|err| wasmi::Error::new(err.to_string()))?;
+    Ok(pack(ptr, value.len()))
+}
+
+// `storage_set(key_ptr, key_len, value_ptr, value_len)`
+fn storage_set(
+    caller: Caller<'_, PluginState>,
+    key_ptr: i32,
+    key_len: i32,
+    value_ptr: i32,
+    value_len: i32,
+) -> Result<(), wasmi::Error> {
+    check_storage(&caller)?;
+    let key = read_string(&caller, key_ptr, key_len)?;
+    let value = read_string(&caller, value_ptr, value_len)?;
+    let state = caller.data();
+    state
+        .storage
+        .set(&state.bucket, &key, value)
+        .map_err(|err| wasmi::Error::new(err.to_string()))
+}
+
+// an instantiated plugin
+struct Loaded {
+    store: Store<PluginState>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    handle_event: TypedFunc<(i32, i32), i64>,
+}
+
+impl std::fmt::Debug for Loaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Loaded")
+            .field("state", self.store.data())
+            .finish()
+    }
+}
+
+impl Loaded {
+    fn handle(&mut self, event: &[u8], fuel: u64) -> Result<Vec<PluginCommand>, PluginError> {
+        self.store
+            .set_fuel(fuel)
+            .map_err(|err| PluginError::Output(err.to_string()))?;
+        let ptr = self.alloc.call(&mut self.store, event.len() as i32)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, event)
+            .map_err(|err| PluginError::Output(err.to_string()))?;
+        let output = self
+            .handle_event
+            .call(&mut self.store, (ptr, event.len() as i32))?;
+        if output == 0 {
+            return Ok(Vec::default());
+        }
+        let (ptr, len) = unpack(output);
+        let output = read(&self.memory, &self.store, ptr, len)?;
+        serde_json::from_slice(&output).map_err(|err| PluginError::Output(err.to_string()))
+    }
+}
+
+#[derive(Debug)]
+struct Plugin {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // None if the plugin could not be loaded or failed too often
+    loaded: Option<Loaded>,
+    failures: u32,
+}
+
+/// Runs WebAssembly plugins from the plugin directory. A plugin exports its `memory`,
+/// `alloc(len) -> ptr` and `handle_event(ptr, len) -> i64`, which receives an event as
+/// JSON and returns 0 or the pointer (high 32 bits) and length (low 32 bits) of a JSON array
+/// of commands. Plugins with the storage capability may import `storage_get` and
+/// `storage_set` from the `chatbot` module.
+///
+/// Every call is limited by fuel, so a plugin cannot stall the bot; a plugin that fails
+/// repeatedly is stopped until its file changes.
+#[derive(Debug)]
+pub struct PluginHost {
+    engine: Engine,
+    linker: Linker<PluginState>,
+    storage: Storage,
+    directory: Option<PathBuf>,
+    fuel: u64,
+    max_memory_bytes: usize,
+    permissions: HashMap<String, Vec<Capability>>,
+    plugins: BTreeMap<String, Plugin>,
+    last_scan: Option<Instant>,
+}
+
+impl PluginHost {
+    pub fn new(config: &PluginConfig, storage: Storage) -> Self {
+        let mut engine_config = Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap(IMPORT_MODULE, "storage_get", storage_get)
+            .and_then(|linker| linker.func_wrap(IMPORT_MODULE, "storage_set", storage_set))
+            .expect("The host functions are defined once");
+        Self {
+            engine,
+            linker,
+            storage,
+            directory: config.directory.as_ref().map(PathBuf::from),
+            fuel: config.fuel,
+            max_memory_bytes: config.max_memory_bytes,
+            permissions: config
+                .permissions
+                .iter()
+                .map(|(name, capabilities)| (name.to_lowercase(), capabilities.to_owned()))
+                .collect(),
+            plugins: BTreeMap::default(),
+            last_scan: None,
+        }
+    }
+
+    fn load(&self, name: &str, path: &Path) -> Result<Loaded, PluginError> {
+        let module = Module::new(&self.engine, &fs::read(path)?[..])?;
+        let state = PluginState {
+            capabilities: self.permissions.get(name).cloned().unwrap_or_default(),
+            storage: self.storage.clone(),
+            bucket: format!("plugin_{}", name),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(self.max_memory_bytes)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(self.fuel)
+            .map_err(|err| PluginError::Output(err.to_string()))?;
+        let instance: Instance = self
+            .linker
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or(PluginError::MissingExport("memory"))?;
+        let alloc = instance
+            .get_typed_func(&store, "alloc")
+            .map_err(|_| PluginError::MissingExport("alloc"))?;
+        let handle_event = instance
+            .get_typed_func(&store, "handle_event")
+            .map_err(|_| PluginError::MissingExport("handle_event"))?;
+        Ok(Loaded {
+            store,
+            memory,
+            alloc,
+            handle_event,
+        })
+    }
+
+    /// Loads new and changed plugins and drops removed ones, at most every few seconds
+    pub fn reload(&mut self, now: Instant) {
+        let directory = match &self.directory {
+            Some(directory) => directory.to_owned(),
+            None => return,
+        };
+        if matches!(self.last_scan, Some(last_scan) if now.duration_since(last_scan) < RELOAD_INTERVAL)
+        {
+            return;
+        }
+        self.last_scan = Some(now);
+        let files: BTreeMap<String, (PathBuf, Option<SystemTime>)> = match fs::read_dir(&directory)
+        {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension().and_then(|extension| extension.to_str())
+                        == Some(PLUGIN_EXTENSION)
+                })
+                .filter_map(|path| {
+                    let name = path.file_stem()?.to_str()?.to_lowercase();
+                    let modified = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok();
+                    Some((name, (path, modified)))
+                })
+                .collect(),
+            Err(err) => {
+                println!("Could not read the plugin directory: {:?}", err);
+                return;
+            }
+        };
+        self.plugins.retain(|name, _| files.contains_key(name));
+        for (name, (path, modified)) in files {
+            if matches!(self.plugins.get(&name), Some(plugin) if plugin.path == path && plugin.modified == modified)
+            {
+                continue;
+            }
+            let loaded = match self.load(&name, &path) {
+                Ok(loaded) => {
+                    println!("Loaded the plugin {}", name);
+                    Some(loaded)
+                }
+                Err(err) => {
+                    println!("Could not load the plugin {}: {}", name, err);
+                    None
+                }
+            };
+            self.plugins.insert(
+                name,
+                Plugin {
+                    path,
+                    modified,
+                    loaded,
+                    failures: 0,
+                },
+            );
+        }
+    }
+
+    /// Passes the event to every plugin, returns the commands they may run
+    pub fn handle(&mut self, event: &PluginEvent) -> Vec<ChatBotCommand> {
+        let event = match serde_json::to_vec(event) {
+            Ok(event) => event,
+            Err(err) => {
+                println!("Could not serialize the plugin event: {:?}", err);
+                return Vec::default();
+            }
+        };
+        let mut commands = Vec::default();
+        for (name, plugin) in self.plugins.iter_mut() {
+            let loaded = match &mut plugin.loaded {
+                Some(loaded) => loaded,
+                None => continue,
+            };
+            match loaded.handle(&event, self.fuel) {
+                Ok(plugin_commands) => {
+                    plugin.failures = 0;
+                    for command in plugin_commands {
+                        if loaded
+                            .store
+                            .data()
+                            .capabilities
+                            .contains(&command.capability())
+                        {
+                            commands.push(command.into_command());
+                        } else {
+                            println!("The plugin {} may not run {:?}", name, command);
+                        }
+                    }
+                }
+                Err(err) => {
+                    plugin.failures += 1;
+                    println!("The plugin {} failed: {}", name, err);
+                    if plugin.failures >= MAX_FAILURES {
+                        println!("Stopped the plugin {} until it changes", name);
+                        plugin.loaded = None;
+                    }
+                }
+            }
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::TextMessage;
+
+    // answers messages with the commands in its data segment and stores another answer,
+    // which it returns for commands
+    const ECHO_PLUGIN: &str = r#"
+        (module
+          (import "chatbot" "storage_get" (func $get (param i32 i32) (result i64)))
+          (import "chatbot" "storage_set" (func $set (param i32 i32 i32 i32)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "key")
+          (data (i32.const 16) "[{\"type\":\"send_message\",\"text\":\"Hello\"},{\"type\":\"ban\",\"user\":\"carkhy\"}]")
+          (data (i32.const 128) "[{\"type\":\"send_message\",\"text\":\"Stored\"}]")
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "handle_event") (param $ptr i32) (param $len i32) (result i64)
+            ;; events starting with {"type":"c are commands
+            (if (result i64) (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 9))) (i32.const 99))
+              (then (call $get (i32.const 0) (i32.const 3)))
+              (else
+                (call $set (i32.const 0) (i32.const 3) (i32.const 128) (i32.const 41))
+                (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 71))))))
+    "#;
+
+    const LOOPING_PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "handle_event") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    fn message_event() -> PluginEvent {
+        PluginEvent::from_event(&ChatBotEvent::TextMessage(TextMessage {
+            id: "message-id".to_owned(),
+            text: "Hi".to_owned(),
+            user: UserInfo {
+                name: "carkhy".to_owned(),
+                badges: Default::default(),
+            },
+            ..Default::default()
+        }))
+        .unwrap()
+    }
+
+    fn command_event() -> PluginEvent {
+        PluginEvent::Command {
+            user: "carkhy".to_owned(),
+            badges: Vec::default(),
+            name: "hello".to_owned(),
+            options: Vec::default(),
+        }
+    }
+
+    fn plugin_directory(plugins: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("chatbot_plugins_{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&directory).unwrap();
+        for (name, wat) in plugins {
+            fs::write(
+                directory.join(format!("{}.wasm", name)),
+                wat::parse_str(wat).unwrap(),
+            )
+            .unwrap();
+        }
+        directory
+    }
+
+    fn host(directory: &Path, permissions: &str) -> PluginHost {
+        let config: PluginConfig = serde_json::from_str(&format!(
+            r#"{{"directory": {:?}, "fuel": 100000, "permissions": {}}}"#,
+            directory.to_str().unwrap(),
+            permissions
+        ))
+        .unwrap();
+        PluginHost::new(&config, Storage::temporary().unwrap())
+    }
+
+    #[test]
+    fn running_plugins_with_their_capabilities() {
+        let directory = plugin_directory(&[("echo", ECHO_PLUGIN)]);
+        let mut host = host(&directory, r#"{"Echo": ["send_messages", "storage"]}"#);
+        host.reload(Instant::now());
+        // banning is not allowed
+        assert_eq!(
+            host.handle(&message_event()),
+            vec![ChatBotCommand::SendMessage("Hello".to_owned())]
+        );
+        assert_eq!(
+            host.handle(&command_event()),
+            vec![ChatBotCommand::SendMessage("Stored".to_owned())]
+        );
+
+        // without the storage capability the plugin fails and is stopped
+        let mut host = self::host(&directory, r#"{"echo": ["send_messages"]}"#);
+        host.reload(Instant::now());
+        for _ in 0..MAX_FAILURES {
+            assert!(host.handle(&message_event()).is_empty());
+        }
+        assert!(host.plugins["echo"].loaded.is_none());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn reloading_changed_plugins() {
+        let directory = plugin_directory(&[("loop", LOOPING_PLUGIN)]);
+        let mut host = host(&directory, "{}");
+        let now = Instant::now();
+        host.reload(now);
+        // the fuel stops endless loops
+        assert!(host.handle(&message_event()).is_empty());
+        assert_eq!(host.plugins["loop"].failures, 1);
+
+        fs::write(directory.join("broken.wasm"), "not wasm").unwrap();
+        fs::remove_file(directory.join("loop.wasm")).unwrap();
+        host.reload(now + Duration::from_secs(1));
+        assert!(host.plugins.contains_key("loop"));
+        host.reload(now + RELOAD_INTERVAL);
+        assert!(!host.plugins.contains_key("loop"));
+        assert!(host.plugins["broken"].loaded.is_none());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn serializing_events() {
+        assert_eq!(
+            serde_json::to_string(&message_event()).unwrap(),
+            r#"{"type":"message","id":"message-id","user":"carkhy","badges":[],"text":"Hi","bits":0}"#
+        );
+        assert_eq!(
+            PluginEvent::from_event(&ChatBotEvent::StreamOffline),
+            Some(PluginEvent::StreamOffline)
+        );
+        assert_eq!(
+            PluginEvent::from_event(&ChatBotEvent::BotModeratorStatus(true)),
+            None
+        );
+    }
+}
@@ -0,0 +1,561 @@
+use super::{
+    time_format::{human_duration, parse_duration},
+    ChatBotCommand, Messages, Storage,
+};
+use crate::connect::{ChatBotEvent, ModerationAction, TwitchRequest, TwitchResponse};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+const REWARDS_BUCKET: &str = "rewards";
+const QUOTES_BUCKET: &str = "quotes";
+const VIPS_BUCKET: &str = "reward_vips";
+const DEFAULT_TIMEOUT_SECONDS: u64 = 60;
+const DEFAULT_VIP_SECONDS: u64 = 86400;
+
+/// What the bot does when a channel point reward is redeemed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RewardAction {
+    // posts the text, which supports the variables {user} and {input}
+    Message { text: String },
+    // times out the user named in the input
+    Timeout { seconds: u64 },
+    // stores the input as a quote
+    AddQuote,
+    // makes the redeeming user a VIP until the time is up
+    Vip { seconds: u64 },
+}
+
+impl RewardAction {
+    /// Parses the actions as written in chat: "message <text>", "timeout [duration]",
+    /// "quote" and "vip [duration]"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (name, argument) = text.split_once(' ').unwrap_or((text, ""));
+        let argument = argument.trim();
+        let seconds = |default| match argument {
+            "" => Some(default),
+            argument => parse_duration(argument).map(|duration| duration.as_secs()),
+        };
+        match name.to_lowercase().as_str() {
+            "message" if !argument.is_empty() => Some(RewardAction::Message {
+                text: argument.to_owned(),
+            }),
+            "timeout" => Some(RewardAction::Timeout {
+                seconds: seconds(DEFAULT_TIMEOUT_SECONDS)?,
+            }),
+            "quote" => Some(RewardAction::AddQuote),
+            "vip" => Some(RewardAction::Vip {
+                seconds: seconds(DEFAULT_VIP_SECONDS)?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self, messages: &Messages) -> String {
+        let duration = |seconds: u64| human_duration(Duration::from_secs(seconds), messages);
+        match self {
+            RewardAction::Message { text } => messages.format("reward.message", &[("text", text)]),
+            RewardAction::Timeout { seconds } => {
+                messages.format("reward.timeout", &[("duration", &duration(*seconds))])
+            }
+            RewardAction::AddQuote => messages.text("reward.quote"),
+            RewardAction::Vip { seconds } => {
+                messages.format("reward.vip", &[("duration", &duration(*seconds))])
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RedemptionConfig {
+    // actions by reward title, rewards bound in chat take precedence
+    pub rewards: HashMap<String, RewardAction>,
+}
+
+// a reward bound in chat, no action means the reward was unbound
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RewardBinding {
+    title: String,
+    action: Option<RewardAction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    pub text: String,
+    // who added the quote
+    pub user: String,
+    pub time: DateTime<Utc>,
+}
+
+/// A redemption as it arrives from twitch
+#[derive(Debug)]
+pub struct Redemption<'a> {
+    pub id: &'a str,
+    pub reward_id: &'a str,
+    pub reward_title: &'a str,
+    pub user_name: &'a str,
+    pub input: &'a str,
+}
+
+#[derive(Debug)]
+struct PendingVip {
+    id: String,
+    reward_id: String,
+    seconds: u64,
+}
+
+/// Runs the actions bound to channel point rewards and fulfills or refunds the redemptions.
+/// Rewards bound in chat, quotes and granted VIP statuses are persisted in the bot's storage.
+#[derive(Debug)]
+pub struct Redemptions {
+    // titles and actions of the configured rewards by lowercase title
+    rewards: HashMap<String, (String, RewardAction)>,
+    storage: Storage,
+    // redemptions waiting for twitch to grant the VIP status, by user
+    pending_vips: HashMap<String, PendingVip>,
+    messages: Messages,
+}
+
+fn update(redemption: &Redemption, fulfilled: bool) -> ChatBotCommand {
+    ChatBotCommand::CallTwitch(TwitchRequest::UpdateRedemption {
+        id: redemption.id.to_owned(),
+        reward_id: redemption.reward_id.to_owned(),
+        fulfilled,
+    })
+}
+
+impl Redemptions {
+    pub fn new(config: &RedemptionConfig, storage: Storage, messages: Messages) -> Self {
+        Self {
+            rewards: config
+                .rewards
+                .iter()
+                .map(|(title, action)| {
+                    (title.to_lowercase(), (title.to_owned(), action.to_owned()))
+                })
+                .collect(),
+            storage,
+            pending_vips: HashMap::default(),
+            messages,
+        }
+    }
+
+    // Tells the user why the redemption failed and refunds it
+    fn refund(&self, redemption: &Redemption, reason: &str) -> ChatBotCommand {
+        ChatBotCommand::MultipleCommands(vec![
+            ChatBotCommand::SendMessage(self.messages.format(
+                "redemption.refund",
+                &[
+                    ("user", &redemption.user_name),
+                    ("reason", &self.messages.text(reason)),
+                ],
+            )),
+            update(redemption, false),
+        ])
+    }
+
+    fn binding(&self, title: &str) -> Option<RewardBinding> {
+        self.storage
+            .get(REWARDS_BUCKET, &title.to_lowercase())
+            .unwrap_or_else(|err| {
+                println!("Could not load reward: {:?}", err);
+                None
+            })
+    }
+
+    /// The action bound to the reward with the title
+    pub fn action(&self, title: &str) -> Option<RewardAction> {
+        match self.binding(title) {
+            Some(binding) => binding.action,
+            None => self
+                .rewards
+                .get(&title.to_lowercase())
+                .map(|(_, action)| action.to_owned()),
+        }
+    }
+
+    /// Binds the reward to the action, None unbinds it
+    pub fn bind(&self, title: &str, action: Option<RewardAction>) {
+        let binding = RewardBinding {
+            title: title.to_owned(),
+            action,
+        };
+        if let Err(err) = self
+            .storage
+            .set(REWARDS_BUCKET, &title.to_lowercase(), binding)
+        {
+            println!("Could not store reward: {:?}", err);
+        }
+    }
+
+    /// All rewards with an action, sorted by title
+    pub fn bindings(&self) -> Vec<(String, RewardAction)> {
+        let stored: Vec<(String, RewardBinding)> =
+            self.storage.entries(REWARDS_BUCKET).unwrap_or_else(|err| {
+                println!("Could not load rewards: {:?}", err);
+                Vec::default()
+            });
+        let mut bindings: HashMap<String, (String, Option<RewardAction>)> = self
+            .rewards
+            .iter()
+            .map(|(key, (title, action))| {
+                (key.to_owned(), (title.to_owned(), Some(action.to_owned())))
+            })
+            .collect();
+        for (key, binding) in stored {
+            bindings.insert(key, (binding.title, binding.action));
+        }
+        let mut bindings: Vec<(String, RewardAction)> = bindings
+            .into_values()
+            .filter_map(|(title, action)| action.map(|action| (title, action)))
+            .collect();
+        bindings.sort_by_key(|(title, _)| title.to_lowercase());
+        bindings
+    }
+
+    fn add_quote(&self, text: &str, user: &str, now: DateTime<Utc>) -> usize {
+        let number = self.quote_count() + 1;
+        let quote = Quote {
+            text: text.to_owned(),
+            user: user.to_owned(),
+            time: now,
+        };
+        if let Err(err) = self.storage.set(QUOTES_BUCKET, &number.to_string(), quote) {
+            println!("Could not store quote: {:?}", err);
+        }
+        number
+    }
+
+    pub fn quote_count(&self) -> usize {
+        self.storage
+            .entries::<Quote>(QUOTES_BUCKET)
+            .map(|quotes| quotes.len())
+            .unwrap_or_else(|err| {
+                println!("Could not load quotes: {:?}", err);
+                0
+            })
+    }
+
+    /// The quote with the number, quotes are numbered from 1
+    pub fn quote(&self, number: usize) -> Option<Quote> {
+        self.storage
+            .get(QUOTES_BUCKET, &number.to_string())
+            .unwrap_or_else(|err| {
+                println!("Could not load quote: {:?}", err);
+                None
+            })
+    }
+
+    /// Runs the action bound to the reward. Redemptions of rewards without an action are
+    /// left to the streamer.
+    pub fn redeem(
+        &mut self,
+        redemption: &Redemption,
+        is_moderator: bool,
+        now: DateTime<Utc>,
+    ) -> Option<ChatBotCommand> {
+        use ChatBotCommand::*;
+        let input = redemption.input.trim();
+        Some(match self.action(redemption.reward_title)? {
+            RewardAction::Message { text } => MultipleCommands(vec![
+                SendMessage(
+                    text.replace("{user}", redemption.user_name)
+                        .replace("{input}", input),
+                ),
+                update(redemption, true),
+            ]),
+            RewardAction::Timeout { seconds } => {
+                let target = input
+                    .split(' ')
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches('@');
+                if target.is_empty() {
+                    self.refund(redemption, "redemption.no_user")
+                } else if !is_moderator {
+                    self.refund(redemption, "redemption.not_moderator")
+                } else {
+                    let duration = Duration::from_secs(seconds);
+                    MultipleCommands(vec![
+                        Moderate(ModerationAction::Timeout {
+                            user: target.to_owned(),
+                            duration,
+                            reason: Some(self.messages.format(
+                                "redemption.timeout_reason",
+                                &[("user", &redemption.user_name)],
+                            )),
+                        }),
+                        SendMessage(self.messages.format(
+                            "redemption.timed_out",
+                            &[
+                                ("user", &redemption.user_name),
+                                ("target", &target),
+                                ("duration", &human_duration(duration, &self.messages)),
+                            ],
+                        )),
+                        update(redemption, true),
+                    ])
+                }
+            }
+            RewardAction::AddQuote => {
+                if input.is_empty() {
+                    self.refund(redemption, "redemption.no_quote")
+                } else {
+                    let number = self.add_quote(input, redemption.user_name, now);
+                    MultipleCommands(vec![
+                        SendMessage(
+                            self.messages
+                                .format("redemption.quote_added", &[("number", &number)]),
+                        ),
+                        update(redemption, true),
+                    ])
+                }
+            }
+            RewardAction::Vip { seconds } => {
+                // the redemption is fulfilled once twitch granted the status
+                let user_name = redemption.user_name.to_lowercase();
+                self.pending_vips.insert(
+                    user_name.to_owned(),
+                    PendingVip {
+                        id: redemption.id.to_owned(),
+                        reward_id: redemption.reward_id.to_owned(),
+                        seconds,
+                    },
+                );
+                CallTwitch(TwitchRequest::AddVip(user_name))
+            }
+        })
+    }
+
+    /// Completes a VIP redemption once twitch answered
+    pub fn vip_added(
+        &mut self,
+        user_name: &str,
+        response: &TwitchResponse,
+        now: DateTime<Utc>,
+    ) -> Option<ChatBotCommand> {
+        use ChatBotCommand::*;
+        let pending = self.pending_vips.remove(user_name)?;
+        let redemption = Redemption {
+            id: &pending.id,
+            reward_id: &pending.reward_id,
+            reward_title: "",
+            user_name,
+            input: "",
+        };
+        if *response != TwitchResponse::Done {
+            return Some(self.refund(&redemption, "redemption.vip_failed"));
+        }
+        let duration = Duration::from_secs(pending.seconds);
+        let expires_at = now + ChronoDuration::seconds(pending.seconds as i64);
+        if let Err(err) = self.storage.set(VIPS_BUCKET, user_name, expires_at) {
+            println!("Could not store VIP: {:?}", err);
+        }
+        Some(MultipleCommands(vec![
+            SendMessage(self.messages.format(
+                "redemption.vip_added",
+                &[
+                    ("user", &user_name),
+                    ("duration", &human_duration(duration, &self.messages)),
+                ],
+            )),
+            update(&redemption, true),
+            TimedCallback {
+                duration,
+                event: ChatBotEvent::VipExpired(user_name.to_owned()),
+            },
+        ]))
+    }
+
+    /// Revokes the VIP status granted by a reward
+    pub fn vip_expired(&self, user_name: &str) -> ChatBotCommand {
+        if let Err(err) = self.storage.remove(VIPS_BUCKET, user_name) {
+            println!("Could not remove VIP: {:?}", err);
+        }
+        ChatBotCommand::CallTwitch(TwitchRequest::RemoveVip(user_name.to_owned()))
+    }
+
+    /// Revokes the VIP statuses which expired while the bot was not running
+    pub fn expired_vips(&self, now: DateTime<Utc>) -> Vec<ChatBotCommand> {
+        self.storage
+            .entries::<DateTime<Utc>>(VIPS_BUCKET)
+            .unwrap_or_else(|err| {
+                println!("Could not load VIPs: {:?}", err);
+                Vec::default()
+            })
+            .into_iter()
+            .filter(|(_, expires_at)| *expires_at <= now)
+            .map(|(user_name, _)| self.vip_expired(&user_name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    fn redemptions() -> Redemptions {
+        let config: RedemptionConfig = serde_json::from_str(
+            r#"{"rewards": {
+                "Hydrate": {"type": "message", "text": "{user} reminds everyone to drink some water!"},
+                "Timeout a friend": {"type": "timeout", "seconds": 300},
+                "Add quote": {"type": "add_quote"},
+                "VIP for a day": {"type": "vip", "seconds": 86400}
+            }}"#,
+        )
+        .unwrap();
+        Redemptions::new(&config, Storage::temporary().unwrap(), Messages::default())
+    }
+
+    fn redemption<'a>(reward_title: &'a str, input: &'a str) -> Redemption<'a> {
+        Redemption {
+            id: "17fa2df1",
+            reward_id: "92af127c",
+            reward_title,
+            user_name: "carkhy",
+            input,
+        }
+    }
+
+    fn fulfilled(fulfilled: bool) -> ChatBotCommand {
+        ChatBotCommand::CallTwitch(TwitchRequest::UpdateRedemption {
+            id: "17fa2df1".to_owned(),
+            reward_id: "92af127c".to_owned(),
+            fulfilled,
+        })
+    }
+
+    #[test]
+    fn parsing_actions() {
+        assert_eq!(
+            RewardAction::parse("message Stay hydrated, {user}!"),
+            Some(RewardAction::Message {
+                text: "Stay hydrated, {user}!".to_owned()
+            })
+        );
+        assert_eq!(
+            RewardAction::parse("timeout 5m"),
+            Some(RewardAction::Timeout { seconds: 300 })
+        );
+        assert_eq!(
+            RewardAction::parse("vip"),
+            Some(RewardAction::Vip { seconds: 86400 })
+        );
+        assert_eq!(RewardAction::parse("quote"), Some(RewardAction::AddQuote));
+        assert_eq!(RewardAction::parse("message"), None);
+        assert_eq!(RewardAction::parse("dance"), None);
+    }
+
+    #[test]
+    fn posting_messages_and_adding_quotes() {
+        let mut redemptions = redemptions();
+        assert_eq!(
+            redemptions.redeem(&redemption("hydrate", ""), false, now()),
+            Some(ChatBotCommand::MultipleCommands(vec![
+                ChatBotCommand::SendMessage(
+                    "carkhy reminds everyone to drink some water!".to_owned()
+                ),
+                fulfilled(true),
+            ]))
+        );
+        assert_eq!(
+            redemptions.redeem(&redemption("Add quote", "  I am not a bot "), false, now()),
+            Some(ChatBotCommand::MultipleCommands(vec![
+                ChatBotCommand::SendMessage("Quote #1 added.".to_owned()),
+                fulfilled(true),
+            ]))
+        );
+        assert_eq!(redemptions.quote(1).unwrap().text, "I am not a bot");
+        assert!(matches!(
+            redemptions.redeem(&redemption("Add quote", ""), false, now()),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands[1] == fulfilled(false)
+        ));
+        assert_eq!(redemptions.quote_count(), 1);
+        assert_eq!(
+            redemptions.redeem(&redemption("Unknown reward", ""), false, now()),
+            None
+        );
+    }
+
+    #[test]
+    fn timing_out_friends() {
+        let mut redemptions = redemptions();
+        assert!(matches!(
+            redemptions.redeem(&redemption("Timeout a friend", "@CaptainCallback"), true, now()),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands[0] == ChatBotCommand::Moderate(ModerationAction::Timeout {
+                user: "CaptainCallback".to_owned(),
+                duration: Duration::from_secs(300),
+                reason: Some("Channel point reward redeemed by carkhy".to_owned()),
+            }) && commands[2] == fulfilled(true)
+        ));
+        // without moderator status the points are refunded
+        assert!(matches!(
+            redemptions.redeem(&redemption("Timeout a friend", "CaptainCallback"), false, now()),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands[1] == fulfilled(false)
+        ));
+    }
+
+    #[test]
+    fn granting_and_revoking_vip() {
+        let mut redemptions = redemptions();
+        assert_eq!(
+            redemptions.redeem(&redemption("VIP for a day", ""), false, now()),
+            Some(ChatBotCommand::CallTwitch(TwitchRequest::AddVip(
+                "carkhy".to_owned()
+            )))
+        );
+        assert!(matches!(
+            redemptions.vip_added("carkhy", &TwitchResponse::Done, now()),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands[1] == fulfilled(true)
+        ));
+        assert!(redemptions
+            .expired_vips(now() + ChronoDuration::hours(23))
+            .is_empty());
+        assert_eq!(
+            redemptions.expired_vips(now() + ChronoDuration::hours(24)),
+            vec![ChatBotCommand::CallTwitch(TwitchRequest::RemoveVip(
+                "carkhy".to_owned()
+            ))]
+        );
+        assert!(redemptions
+            .expired_vips(now() + ChronoDuration::hours(25))
+            .is_empty());
+
+        // twitch refused, e.g. because the user is a VIP already
+        redemptions.redeem(&redemption("VIP for a day", ""), false, now());
+        assert!(matches!(
+            redemptions.vip_added("carkhy", &TwitchResponse::Failed("422".to_owned()), now()),
+            Some(ChatBotCommand::MultipleCommands(commands)) if commands[1] == fulfilled(false)
+        ));
+    }
+
+    #[test]
+    fn binding_rewards_in_chat() {
+        let redemptions = redemptions();
+        redemptions.bind("Hydrate", None);
+        redemptions.bind(
+            "Dance",
+            Some(RewardAction::Message {
+                text: "*dances*".to_owned(),
+            }),
+        );
+        assert_eq!(redemptions.action("hydrate"), None);
+        let titles: Vec<String> = redemptions
+            .bindings()
+            .into_iter()
+            .map(|(title, _)| title)
+            .collect();
+        assert_eq!(
+            titles,
+            vec!["Add quote", "Dance", "Timeout a friend", "VIP for a day"]
+        );
+    }
+}
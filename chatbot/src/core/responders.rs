@@ -0,0 +1,433 @@
+use super::{command_registry::Permission, ChatBotCommand, Storage};
+use crate::connect::TextMessage;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+const RESPONDERS_BUCKET: &str = "auto_responders";
+const SETTINGS_BUCKET: &str = "auto_responder_settings";
+// counts the changes of the stored responders, the API changes them from another thread
+const VERSION_KEY: &str = "version";
+// the version is checked at most this often, changes made in chat are seen right away
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How the pattern of a responder is looked for in a message, case is ignored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    Contains,
+    WholeWord,
+    StartsWith,
+    Regex,
+}
+
+impl MatchMode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "contains" => Some(MatchMode::Contains),
+            "word" | "whole_word" => Some(MatchMode::WholeWord),
+            "starts" | "starts_with" => Some(MatchMode::StartsWith),
+            "regex" => Some(MatchMode::Regex),
+            _ => None,
+        }
+    }
+}
+
+fn default_permission() -> Permission {
+    Permission::Everyone
+}
+
+/// Answers chat messages matching the pattern
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoResponder {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub mode: MatchMode,
+    // supports the variables {user} and {message}
+    pub response: String,
+    #[serde(default)]
+    pub cooldown_seconds: u64,
+    #[serde(default = "default_permission")]
+    pub permission: Permission,
+}
+
+impl AutoResponder {
+    fn regex(&self) -> Result<Regex, regex::Error> {
+        let pattern = match self.mode {
+            MatchMode::Contains => regex::escape(&self.pattern),
+            MatchMode::WholeWord => format!(r"\b{}\b", regex::escape(&self.pattern)),
+            MatchMode::StartsWith => format!("^{}", regex::escape(&self.pattern)),
+            MatchMode::Regex => self.pattern.to_owned(),
+        };
+        RegexBuilder::new(&pattern).case_insensitive(true).build()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ResponderConfig {
+    // responders added in chat or over the API take precedence
+    pub responders: Vec<AutoResponder>,
+}
+
+#[derive(Debug, Error)]
+pub enum ResponderError {
+    #[error("Invalid regular expression [{}]", .0)]
+    Regex(#[from] regex::Error),
+    #[error("Could not store responder [{}]", .0)]
+    Storage(#[from] kv::Error),
+}
+
+/// Answers plain chat messages with the first responder that matches, by name.
+/// Responders added in chat or over the API are persisted, removing a configured
+/// responder is persisted as well.
+#[derive(Debug)]
+pub struct AutoResponders {
+    configured: HashMap<String, AutoResponder>,
+    storage: Storage,
+    // the responders with their compiled patterns, loaded again once the stored ones changed
+    loaded: Vec<(AutoResponder, Regex)>,
+    loaded_version: Option<u64>,
+    last_reload: Option<Instant>,
+    // when each responder fired last, by name
+    fired: HashMap<String, Instant>,
+}
+
+impl AutoResponders {
+    pub fn new(config: &ResponderConfig, storage: Storage) -> Result<Self, regex::Error> {
+        for responder in &config.responders {
+            responder.regex()?;
+        }
+        Ok(Self {
+            configured: config
+                .responders
+                .iter()
+                .map(|responder| (responder.name.to_lowercase(), responder.to_owned()))
+                .collect(),
+            storage,
+            loaded: Vec::default(),
+            loaded_version: None,
+            last_reload: None,
+            fired: HashMap::default(),
+        })
+    }
+
+    /// All responders, sorted by name
+    pub fn responders(&self) -> Vec<AutoResponder> {
+        let stored: Vec<(String, Option<AutoResponder>)> = self
+            .storage
+            .entries(RESPONDERS_BUCKET)
+            .unwrap_or_else(|err| {
+                println!("Could not load auto responders: {:?}", err);
+                Vec::default()
+            });
+        let mut responders = self.configured.clone();
+        for (name, responder) in stored {
+            match responder {
+                Some(responder) => responders.insert(name, responder),
+                None => responders.remove(&name),
+            };
+        }
+        let mut responders: Vec<AutoResponder> = responders.into_values().collect();
+        responders.sort_by_key(|responder| responder.name.to_lowercase());
+        responders
+    }
+
+    pub fn get(&self, name: &str) -> Option<AutoResponder> {
+        self.responders()
+            .into_iter()
+            .find(|responder| responder.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds the responder or replaces the one with the same name
+    pub fn set(&mut self, responder: AutoResponder) -> Result<(), ResponderError> {
+        save_responder(&self.storage, responder)?;
+        self.last_reload = None;
+        Ok(())
+    }
+
+    /// Removes the responder, returns whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        if self.get(name).is_none() {
+            return false;
+        }
+        if let Err(err) = remove_responder(&self.storage, name) {
+            println!("Could not remove auto responder: {:?}", err);
+        }
+        self.last_reload = None;
+        true
+    }
+
+    fn reload(&mut self, now: Instant) {
+        if matches!(self.last_reload, Some(last_reload) if now.duration_since(last_reload) < RELOAD_INTERVAL)
+        {
+            return;
+        }
+        self.last_reload = Some(now);
+        let version = stored_version(&self.storage);
+        if self.loaded_version == Some(version) {
+            return;
+        }
+        self.loaded = self
+            .responders()
+            .into_iter()
+            .filter_map(|responder| match responder.regex() {
+                Ok(regex) => Some((responder, regex)),
+                Err(err) => {
+                    println!("Invalid auto responder {}: {:?}", responder.name, err);
+                    None
+                }
+            })
+            .collect();
+        self.loaded_version = Some(version);
+    }
+
+    /// The response of the first responder matching the message, responders answer
+    /// again after their cooldown
+    pub fn respond(&mut self, message: &TextMessage, now: Instant) -> Option<ChatBotCommand> {
+        self.reload(now);
+        for (responder, regex) in &self.loaded {
+            let key = responder.name.to_lowercase();
+            if !responder.permission.allows(&message.user) {
+                continue;
+            }
+            let cooldown = Duration::from_secs(responder.cooldown_seconds);
+            if matches!(self.fired.get(&key), Some(fired) if now.duration_since(*fired) < cooldown)
+            {
+                continue;
+            }
+            if regex.is_match(&message.text) {
+                self.fired.insert(key, now);
+                return Some(ChatBotCommand::SendMessage(
+                    responder
+                        .response
+                        .replace("{user}", &message.user.name)
+                        .replace("{message}", &message.text),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// The responders added in chat or over the API
+pub fn stored_responders(storage: &Storage) -> Result<Vec<AutoResponder>, kv::Error> {
+    Ok(storage
+        .entries::<Option<AutoResponder>>(RESPONDERS_BUCKET)?
+        .into_iter()
+        .filter_map(|(_, responder)| responder)
+        .collect())
+}
+
+/// The responder added in chat or over the API with the name
+pub fn stored_responder(storage: &Storage, name: &str) -> Result<Option<AutoResponder>, kv::Error> {
+    Ok(storage
+        .get::<Option<AutoResponder>>(RESPONDERS_BUCKET, &name.to_lowercase())?
+        .flatten())
+}
+
+fn stored_version(storage: &Storage) -> u64 {
+    storage
+        .get(SETTINGS_BUCKET, VERSION_KEY)
+        .unwrap_or_else(|err| {
+            println!(
+                "Could not load the version of the auto responders: {:?}",
+                err
+            );
+            None
+        })
+        .unwrap_or_default()
+}
+
+fn count_change(storage: &Storage) -> Result<(), kv::Error> {
+    storage
+        .update(SETTINGS_BUCKET, VERSION_KEY, |version: Option<u64>| {
+            version.unwrap_or_default() + 1
+        })
+        .map(|_| ())
+}
+
+/// Stores the responder after checking its pattern
+pub fn save_responder(storage: &Storage, responder: AutoResponder) -> Result<(), ResponderError> {
+    responder.regex()?;
+    storage.set(
+        RESPONDERS_BUCKET,
+        &responder.name.to_lowercase(),
+        Some(responder),
+    )?;
+    count_change(storage)?;
+    Ok(())
+}
+
+pub fn remove_responder(storage: &Storage, name: &str) -> Result<(), kv::Error> {
+    storage.set(
+        RESPONDERS_BUCKET,
+        &name.to_lowercase(),
+        None::<AutoResponder>,
+    )?;
+    count_change(storage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::{Badge, UserInfo};
+    use std::collections::HashSet;
+
+    fn message(text: &str, badge: Option<&str>) -> TextMessage {
+        TextMessage {
+            id: "message-id".to_owned(),
+            text: text.to_owned(),
+            user: UserInfo {
+                name: "Carkhy".to_owned(),
+                badges: badge
+                    .map(|badge| {
+                        HashSet::from([Badge {
+                            name: badge.to_owned(),
+                            level: 1,
+                        }])
+                    })
+                    .unwrap_or_default(),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn responders(storage: Storage) -> AutoResponders {
+        let config: ResponderConfig = serde_json::from_str(
+            r#"{"responders": [
+                {"name": "game", "pattern": "what game", "response": "@{user} we play Rust"},
+                {"name": "schedule", "pattern": "next stream", "mode": "whole_word",
+                 "response": "Tomorrow at 8pm", "cooldown_seconds": 60},
+                {"name": "mods", "pattern": "^!?mods?$", "mode": "regex",
+                 "response": "Hello moderator", "permission": "moderator"}
+            ]}"#,
+        )
+        .unwrap();
+        AutoResponders::new(&config, storage).unwrap()
+    }
+
+    fn response(result: Option<ChatBotCommand>) -> Option<String> {
+        match result {
+            Some(ChatBotCommand::SendMessage(message)) => Some(message),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn matching_messages() {
+        let mut responders = responders(Storage::temporary().unwrap());
+        let now = Instant::now();
+        assert_eq!(
+            response(responders.respond(&message("Hey, WHAT GAME is this?", None), now)),
+            Some("@Carkhy we play Rust".to_owned())
+        );
+        assert_eq!(
+            response(responders.respond(&message("when is the next stream", None), now)),
+            Some("Tomorrow at 8pm".to_owned())
+        );
+        // the cooldown and whole words
+        assert_eq!(
+            responders.respond(&message("next stream?", None), now),
+            None
+        );
+        assert_eq!(
+            response(responders.respond(
+                &message("next stream?", None),
+                now + Duration::from_secs(60)
+            )),
+            Some("Tomorrow at 8pm".to_owned())
+        );
+        assert_eq!(
+            responders.respond(
+                &message("the next streamer", None),
+                now + Duration::from_secs(120)
+            ),
+            None
+        );
+        // the permission
+        assert_eq!(responders.respond(&message("mod", None), now), None);
+        assert_eq!(
+            response(responders.respond(&message("Mods", Some("moderator")), now)),
+            Some("Hello moderator".to_owned())
+        );
+    }
+
+    #[test]
+    fn managing_responders() {
+        let storage = Storage::temporary().unwrap();
+        let mut responders = responders(storage.clone());
+        assert!(matches!(
+            responders.set(AutoResponder {
+                name: "broken".to_owned(),
+                pattern: "(".to_owned(),
+                mode: MatchMode::Regex,
+                response: "Oops".to_owned(),
+                cooldown_seconds: 0,
+                permission: Permission::Everyone,
+            }),
+            Err(ResponderError::Regex(_))
+        ));
+        assert_eq!(
+            responders.respond(&message("which game is it", None), Instant::now()),
+            None
+        );
+        responders
+            .set(AutoResponder {
+                name: "Game".to_owned(),
+                pattern: "which game".to_owned(),
+                mode: MatchMode::StartsWith,
+                response: "Rust!".to_owned(),
+                cooldown_seconds: 0,
+                permission: Permission::Everyone,
+            })
+            .unwrap();
+        assert!(responders.remove("schedule"));
+        assert!(!responders.remove("schedule"));
+        let names: Vec<String> = responders
+            .responders()
+            .into_iter()
+            .map(|responder| responder.name)
+            .collect();
+        assert_eq!(names, vec!["Game", "mods"]);
+        assert_eq!(stored_responders(&storage).unwrap().len(), 1);
+
+        // the responders are loaded again once they changed, also over the API
+        let now = Instant::now();
+        assert_eq!(
+            response(responders.respond(&message("which game is it", None), now)),
+            Some("Rust!".to_owned())
+        );
+        assert_eq!(
+            responders.respond(&message("what game is it", None), now),
+            None
+        );
+        save_responder(
+            &storage,
+            AutoResponder {
+                name: "game".to_owned(),
+                pattern: "which game".to_owned(),
+                mode: MatchMode::StartsWith,
+                response: "Still Rust!".to_owned(),
+                cooldown_seconds: 0,
+                permission: Permission::Everyone,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            response(responders.respond(&message("which game is it", None), now)),
+            Some("Rust!".to_owned())
+        );
+        assert_eq!(
+            response(responders.respond(&message("which game is it", None), now + RELOAD_INTERVAL)),
+            Some("Still Rust!".to_owned())
+        );
+        assert_eq!(stored_version(&storage), 3);
+    }
+}
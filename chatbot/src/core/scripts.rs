@@ -0,0 +1,463 @@
+use super::{ChatBotCommand, Storage};
+use crate::connect::{ModerationAction, UserInfo};
+use rhai::{
+    module_resolvers::DummyModuleResolver, Array, Dynamic, Engine, EvalAltResult, ImmutableString,
+    Map, ParseError, Scope, AST,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fs, io,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant, SystemTime},
+};
+use thiserror::Error;
+
+const SCRIPT_EXTENSION: &str = "rhai";
+const SCRIPT_REASON: &str = "Moderated by a script";
+// compiled scripts kept before the cache is cleared
+const MAX_COMPILED: usize = 100;
+
+/// Marks the text of a custom command as a script
+pub const SCRIPT_FLAG: &str = "--script";
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ScriptConfig {
+    // directory with a `<command name>.rhai` file per command, loaded at startup
+    pub directory: Option<String>,
+    // operations a script may run, every expression, loop iteration and call counts
+    pub max_operations: u64,
+    pub time_limit_ms: u64,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            max_operations: 100_000,
+            time_limit_ms: 200,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error("Could not read the scripts [{}]", .0)]
+    Io(#[from] io::Error),
+    #[error("Syntax error in the script {} [{}]", .0, .1)]
+    Syntax(String, ParseError),
+}
+
+/// The script of a custom command's text, if it is one
+pub fn script_source(text: &str) -> Option<&str> {
+    text.strip_prefix(SCRIPT_FLAG)
+        .filter(|source| source.is_empty() || source.starts_with(char::is_whitespace))
+        .map(str::trim_start)
+}
+
+// chat messages are a single line
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+fn to_json(value: Dynamic) -> Option<Value> {
+    if value.is_string() {
+        value.into_string().ok().map(Value::from)
+    } else if value.is_int() {
+        value.as_int().ok().map(Value::from)
+    } else if value.is_float() {
+        value.as_float().ok().map(Value::from)
+    } else if value.is_bool() {
+        value.as_bool().ok().map(Value::from)
+    } else {
+        None
+    }
+}
+
+fn from_json(value: Value) -> Dynamic {
+    match value {
+        Value::String(text) => text.into(),
+        Value::Bool(value) => value.into(),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => number.into(),
+            None => number.as_f64().unwrap_or_default().into(),
+        },
+        _ => Dynamic::UNIT,
+    }
+}
+
+/// Runs the scripts of custom commands in a sandbox: scripts cannot import modules or
+/// access files and stop after the configured operations and time.
+///
+/// Scripts see the caller as `user` (`name`, `moderator`, `badges`) and the options of the
+/// command as `args`. They can `say(text)`, `timeout(user, seconds)`, `ban(user)`,
+/// `get(key)` and `set(key, value)` values in the channel's storage, separately for every
+/// command, and pick values with `random(max)` and `pick(array)`.
+/// A value the script ends with is posted as well.
+#[derive(Debug)]
+pub struct ScriptEngine {
+    engine: Engine,
+    // the commands of the running script
+    commands: Rc<RefCell<Vec<ChatBotCommand>>>,
+    started: Rc<Cell<Instant>>,
+    // the storage bucket of the running script's command, so commands do not share values
+    bucket: Rc<RefCell<String>>,
+    // compiled scripts by source
+    compiled: HashMap<String, AST>,
+}
+
+impl ScriptEngine {
+    pub fn new(config: &ScriptConfig, storage: Storage) -> Self {
+        let commands: Rc<RefCell<Vec<ChatBotCommand>>> = Rc::default();
+        let started = Rc::new(Cell::new(Instant::now()));
+        let bucket: Rc<RefCell<String>> = Rc::default();
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .disable_symbol("eval")
+            .set_max_operations(config.max_operations)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(10_000)
+            .set_max_array_size(1_000)
+            .set_max_map_size(1_000);
+
+        let time_limit = Duration::from_millis(config.time_limit_ms);
+        let start = started.clone();
+        engine.on_progress(move |_| {
+            (start.get().elapsed() > time_limit).then(|| Dynamic::from("time limit exceeded"))
+        });
+
+        let output = commands.clone();
+        engine.register_fn("say", move |text: ImmutableString| {
+            output
+                .borrow_mut()
+                .push(ChatBotCommand::SendMessage(single_line(&text)));
+        });
+        let output = commands.clone();
+        engine.register_fn("timeout", move |user: ImmutableString, seconds: i64| {
+            output
+                .borrow_mut()
+                .push(ChatBotCommand::Moderate(ModerationAction::Timeout {
+                    user: user.trim_start_matches('@').to_owned(),
+                    duration: Duration::from_secs(seconds.max(1) as u64),
+                    reason: Some(SCRIPT_REASON.to_owned()),
+                }));
+        });
+        let output = commands.clone();
+        engine.register_fn("ban", move |user: ImmutableString| {
+            output
+                .borrow_mut()
+                .push(ChatBotCommand::Moderate(ModerationAction::Ban {
+                    user: user.trim_start_matches('@').to_owned(),
+                    reason: Some(SCRIPT_REASON.to_owned()),
+                }));
+        });
+
+        let data = storage.clone();
+        let data_bucket = bucket.clone();
+        engine.register_fn("get", move |key: ImmutableString| {
+            data.get::<Value>(&data_bucket.borrow(), &key)
+                .unwrap_or_else(|err| {
+                    println!("Could not load script value: {:?}", err);
+                    None
+                })
+                .map_or(Dynamic::UNIT, from_json)
+        });
+        let data_bucket = bucket.clone();
+        engine.register_fn(
+            "set",
+            move |key: ImmutableString, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                let value =
+                    to_json(value).ok_or("only strings, numbers and booleans can be stored")?;
+                storage
+                    .set(&data_bucket.borrow(), &key, value)
+                    .map_err(|err| format!("could not store the value [{}]", err).into())
+            },
+        );
+
+        // pseudo random numbers are good enough for chat
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(1, |time| time.as_nanos() as u64)
+            | 1;
+        let state = Rc::new(Cell::new(seed));
+        let next = move || {
+            let mut x = state.get();
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            state.set(x);
+            x
+        };
+        let random = Rc::new(next);
+        let next = random.clone();
+        engine.register_fn("random", move |max: i64| -> i64 {
+            match max {
+                max if max > 0 => (next() % max as u64) as i64,
+                _ => 0,
+            }
+        });
+        engine.register_fn("pick", move |values: Array| -> Dynamic {
+            match values.len() {
+                0 => Dynamic::UNIT,
+                len => values[(random() % len as u64) as usize].clone(),
+            }
+        });
+
+        Self {
+            engine,
+            commands,
+            started,
+            bucket,
+            compiled: HashMap::default(),
+        }
+    }
+
+    /// Compiles the script to report syntax errors
+    pub fn check(&self, source: &str) -> Result<(), ParseError> {
+        self.engine.compile(source).map(|_| ())
+    }
+
+    /// Runs the script of the command for the user, returns the messages and moderation actions
+    pub fn run(
+        &mut self,
+        name: &str,
+        source: &str,
+        user: &UserInfo,
+        args: &[String],
+    ) -> Result<Vec<ChatBotCommand>, Box<EvalAltResult>> {
+        let ast = match self.compiled.get(source) {
+            Some(ast) => ast.clone(),
+            None => {
+                let ast = self.engine.compile(source)?;
+                if self.compiled.len() >= MAX_COMPILED {
+                    self.compiled.clear();
+                }
+                self.compiled.insert(source.to_owned(), ast.clone());
+                ast
+            }
+        };
+        let mut caller = Map::new();
+        caller.insert("name".into(), user.name.to_owned().into());
+        caller.insert("moderator".into(), user.has_elevated_rights().into());
+        caller.insert(
+            "badges".into(),
+            user.badges
+                .iter()
+                .map(|badge| Dynamic::from(badge.name.to_owned()))
+                .collect::<Array>()
+                .into(),
+        );
+        let mut scope = Scope::new();
+        scope.push_constant("user", caller);
+        scope.push_constant(
+            "args",
+            args.iter()
+                .map(|arg| Dynamic::from(arg.to_owned()))
+                .collect::<Array>(),
+        );
+
+        self.commands.borrow_mut().clear();
+        self.bucket
+            .replace(format!("script_{}", name.to_lowercase()));
+        self.started.set(Instant::now());
+        let result = self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &ast);
+        let mut commands = self.commands.take();
+        let value = result?;
+        if !value.is_unit() {
+            let text = single_line(&value.to_string());
+            if !text.is_empty() {
+                commands.push(ChatBotCommand::SendMessage(text));
+            }
+        }
+        Ok(commands)
+    }
+}
+
+/// Reads the scripts in the directory, by command name
+pub fn load_scripts(directory: &str) -> Result<Vec<(String, String)>, ScriptError> {
+    let mut scripts = Vec::default();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some(SCRIPT_EXTENSION) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+            scripts.push((name.to_lowercase(), fs::read_to_string(&path)?));
+        }
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// Checks the scripts in the directory, a missing directory has no scripts
+pub fn checked_scripts(
+    config: &ScriptConfig,
+    engine: &ScriptEngine,
+) -> Result<Vec<(String, String)>, ScriptError> {
+    let directory = match &config.directory {
+        Some(directory) if Path::new(directory).is_dir() => directory,
+        _ => return Ok(Vec::default()),
+    };
+    let scripts = load_scripts(directory)?;
+    for (name, source) in &scripts {
+        engine
+            .check(source)
+            .map_err(|err| ScriptError::Syntax(name.to_owned(), err))?;
+    }
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::Badge;
+    use std::collections::HashSet;
+
+    fn user(badge: Option<&str>) -> UserInfo {
+        UserInfo {
+            name: "carkhy".to_owned(),
+            badges: badge
+                .map(|badge| {
+                    HashSet::from([Badge {
+                        name: badge.to_owned(),
+                        level: 1,
+                    }])
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn running_scripts() {
+        let mut scripts =
+            ScriptEngine::new(&ScriptConfig::default(), Storage::temporary().unwrap());
+        assert_eq!(
+            scripts
+                .run(
+                    "test",
+                    r#"say("Hi " + user.name); if args.len() > 0 { timeout(args[0], 60) } "done""#,
+                    &user(None),
+                    &args(&["@botanist"]),
+                )
+                .unwrap(),
+            vec![
+                ChatBotCommand::SendMessage("Hi carkhy".to_owned()),
+                ChatBotCommand::Moderate(ModerationAction::Timeout {
+                    user: "botanist".to_owned(),
+                    duration: Duration::from_secs(60),
+                    reason: Some(SCRIPT_REASON.to_owned()),
+                }),
+                ChatBotCommand::SendMessage("done".to_owned()),
+            ]
+        );
+        assert_eq!(
+            scripts
+                .run(
+                    "test",
+                    "if user.moderator { \"mod\" }",
+                    &user(Some("moderator")),
+                    &[]
+                )
+                .unwrap(),
+            vec![ChatBotCommand::SendMessage("mod".to_owned())]
+        );
+        let roll = scripts
+            .run("test", "random(6) + 1", &user(None), &[])
+            .unwrap();
+        assert!(matches!(&roll[..], [ChatBotCommand::SendMessage(roll)]
+                         if (1..=6).contains(&roll.parse::<i32>().unwrap())));
+        assert!(scripts
+            .run("test", "pick([])", &user(None), &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn storing_values() {
+        let storage = Storage::temporary().unwrap();
+        let mut scripts = ScriptEngine::new(&ScriptConfig::default(), storage.clone());
+        let count = "let count = get(\"deaths\") ?? 0; set(\"deaths\", count + 1); count + 1";
+        scripts.run("test", count, &user(None), &[]).unwrap();
+        assert_eq!(
+            scripts.run("test", count, &user(None), &[]).unwrap(),
+            vec![ChatBotCommand::SendMessage("2".to_owned())]
+        );
+        assert!(scripts
+            .run("test", "set(\"list\", [1])", &user(None), &[])
+            .is_err());
+        // every command has its own values
+        assert_eq!(
+            scripts.run("other", count, &user(None), &[]).unwrap(),
+            vec![ChatBotCommand::SendMessage("1".to_owned())]
+        );
+    }
+
+    #[test]
+    fn limiting_scripts() {
+        let mut scripts = ScriptEngine::new(
+            &ScriptConfig {
+                max_operations: 1_000,
+                ..Default::default()
+            },
+            Storage::temporary().unwrap(),
+        );
+        assert!(scripts.run("test", "loop { }", &user(None), &[]).is_err());
+        assert!(scripts
+            .run("test", "import \"file\" as file;", &user(None), &[])
+            .is_err());
+        assert!(scripts.check("let x = ;").is_err());
+
+        let mut scripts = ScriptEngine::new(
+            &ScriptConfig {
+                max_operations: 0,
+                time_limit_ms: 10,
+                ..Default::default()
+            },
+            Storage::temporary().unwrap(),
+        );
+        assert!(scripts.run("test", "loop { }", &user(None), &[]).is_err());
+    }
+
+    #[test]
+    fn loading_scripts_from_a_directory() {
+        let directory =
+            std::env::temp_dir().join(format!("chatbot_scripts_{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&directory).unwrap();
+        fs::write(directory.join("Roll.rhai"), "random(6) + 1").unwrap();
+        fs::write(directory.join("notes.txt"), "not a script").unwrap();
+        let mut config = ScriptConfig {
+            directory: directory.to_str().map(str::to_owned),
+            ..Default::default()
+        };
+        let engine = ScriptEngine::new(&config, Storage::temporary().unwrap());
+        assert_eq!(
+            checked_scripts(&config, &engine).unwrap(),
+            vec![("roll".to_owned(), "random(6) + 1".to_owned())]
+        );
+        fs::write(directory.join("broken.rhai"), "let x = ;").unwrap();
+        assert!(matches!(
+            checked_scripts(&config, &engine),
+            Err(ScriptError::Syntax(name, _)) if name == "broken"
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+        config.directory = Some("./this_directory_does_not_exist".to_owned());
+        assert!(checked_scripts(&config, &engine).unwrap().is_empty());
+    }
+
+    #[test]
+    fn marking_scripts() {
+        assert_eq!(script_source("--script say(\"Hi\")"), Some("say(\"Hi\")"));
+        assert_eq!(script_source("--scripts are fun"), None);
+        assert_eq!(script_source("Hello"), None);
+    }
+}
@@ -0,0 +1,228 @@
+use super::{ChatBotCommand, Messages};
+use crate::connect::{ChannelInfo, TwitchRequest};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ShoutoutConfig {
+    // supports the variables {name} (display name), {login}, {game} and {title}
+    pub message: String,
+    // twitch allows an official shoutout every 2 minutes and for the same channel every hour
+    pub cooldown_seconds: u64,
+    pub channel_cooldown_seconds: u64,
+    // how long fetched channel information is reused
+    pub cache_seconds: u64,
+    // whether raiding channels get a shoutout automatically
+    pub on_raid: bool,
+}
+
+impl Default for ShoutoutConfig {
+    fn default() -> Self {
+        Self {
+            message:
+                "Go check out {name} at https://twitch.tv/{login} - they were last playing {game}!"
+                    .to_owned(),
+            cooldown_seconds: 120,
+            channel_cooldown_seconds: 3600,
+            cache_seconds: 3600,
+            on_raid: true,
+        }
+    }
+}
+
+/// Shoutouts for other channels, rate limited and with cached channel information
+#[derive(Debug)]
+pub struct Shoutouts {
+    message: String,
+    cooldown: Duration,
+    channel_cooldown: Duration,
+    cache_duration: Duration,
+    on_raid: bool,
+    cache: HashMap<String, (ChannelInfo, Instant)>,
+    // channels waiting for their information to be fetched
+    pending: HashSet<String>,
+    last_shoutout: Option<Instant>,
+    last_channel_shoutouts: HashMap<String, Instant>,
+    messages: Messages,
+}
+
+impl Shoutouts {
+    pub fn new(config: &ShoutoutConfig, messages: Messages) -> Self {
+        Self {
+            message: config.message.to_owned(),
+            cooldown: Duration::from_secs(config.cooldown_seconds),
+            channel_cooldown: Duration::from_secs(config.channel_cooldown_seconds),
+            cache_duration: Duration::from_secs(config.cache_seconds),
+            on_raid: config.on_raid,
+            cache: HashMap::default(),
+            pending: HashSet::default(),
+            last_shoutout: None,
+            last_channel_shoutouts: HashMap::default(),
+            messages,
+        }
+    }
+
+    pub fn on_raid(&self) -> bool {
+        self.on_raid
+    }
+
+    fn shoutout_message(&self, info: &ChannelInfo) -> String {
+        let game = if info.game_name.is_empty() {
+            self.messages.text("shoutout.no_game")
+        } else {
+            info.game_name.to_owned()
+        };
+        self.message
+            .replace("{name}", &info.display_name)
+            .replace("{login}", &info.login)
+            .replace("{game}", &game)
+            .replace("{title}", &info.title)
+    }
+
+    /// Shouts out the channel, either right away from the cache or after fetching its
+    /// information. Err is the remaining cooldown.
+    pub fn shoutout(&mut self, login: &str, now: Instant) -> Result<ChatBotCommand, Duration> {
+        let login = login.to_lowercase();
+        let remaining = |last: Option<&Instant>, cooldown: Duration| {
+            last.map(|last| cooldown.saturating_sub(now.duration_since(*last)))
+                .unwrap_or_default()
+        };
+        let remaining = remaining(self.last_shoutout.as_ref(), self.cooldown).max(remaining(
+            self.last_channel_shoutouts.get(&login),
+            self.channel_cooldown,
+        ));
+        if !remaining.is_zero() {
+            return Err(remaining);
+        }
+        self.last_shoutout = Some(now);
+        self.last_channel_shoutouts.insert(login.to_owned(), now);
+        match self.cache.get(&login) {
+            Some((info, fetched)) if now.duration_since(*fetched) < self.cache_duration => {
+                Ok(ChatBotCommand::SendMessage(self.shoutout_message(info)))
+            }
+            _ => {
+                self.pending.insert(login.to_owned());
+                Ok(ChatBotCommand::CallTwitch(TwitchRequest::ChannelInfo(
+                    login,
+                )))
+            }
+        }
+    }
+
+    /// Caches the fetched information and completes a pending shoutout
+    pub fn channel_info(
+        &mut self,
+        login: &str,
+        info: Option<ChannelInfo>,
+        now: Instant,
+    ) -> Option<ChatBotCommand> {
+        let login = login.to_lowercase();
+        let pending = self.pending.remove(&login);
+        match info {
+            Some(info) => {
+                let message = self.shoutout_message(&info);
+                self.cache.insert(login, (info, now));
+                pending.then_some(ChatBotCommand::SendMessage(message))
+            }
+            None => {
+                // a shoutout that could not happen does not count towards the channel's cooldown
+                self.last_channel_shoutouts.remove(&login);
+                pending.then(|| {
+                    ChatBotCommand::SendMessage(
+                        self.messages
+                            .format("twitch.unknown_channel", &[("channel", &login)]),
+                    )
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn twitch_dev() -> ChannelInfo {
+        ChannelInfo {
+            login: "twitchdev".to_owned(),
+            display_name: "TwitchDev".to_owned(),
+            game_name: "Science & Technology".to_owned(),
+            title: "Monthly Update".to_owned(),
+        }
+    }
+
+    #[test]
+    fn fetching_and_caching_channel_info() {
+        let mut shoutouts = Shoutouts::new(
+            &ShoutoutConfig {
+                cooldown_seconds: 0,
+                channel_cooldown_seconds: 0,
+                ..Default::default()
+            },
+            Messages::default(),
+        );
+        let now = Instant::now();
+        assert!(matches!(
+            shoutouts.shoutout("TwitchDev", now),
+            Ok(ChatBotCommand::CallTwitch(TwitchRequest::ChannelInfo(login))) if login == "twitchdev"
+        ));
+        let expected_message =
+            "Go check out TwitchDev at https://twitch.tv/twitchdev - they were last playing Science & Technology!";
+        assert!(matches!(
+            shoutouts.channel_info("twitchdev", Some(twitch_dev()), now),
+            Some(ChatBotCommand::SendMessage(message)) if message == expected_message
+        ));
+        assert!(matches!(
+            shoutouts.shoutout("twitchdev", now + Duration::from_secs(60)),
+            Ok(ChatBotCommand::SendMessage(message)) if message == expected_message
+        ));
+        assert!(matches!(
+            shoutouts.shoutout("twitchdev", now + Duration::from_secs(3600)),
+            Ok(ChatBotCommand::CallTwitch(TwitchRequest::ChannelInfo(_)))
+        ));
+    }
+
+    #[test]
+    fn rate_limiting_shoutouts() {
+        let mut shoutouts = Shoutouts::new(&ShoutoutConfig::default(), Messages::default());
+        let now = Instant::now();
+        assert!(shoutouts.shoutout("twitchdev", now).is_ok());
+        assert_eq!(
+            shoutouts
+                .shoutout("other", now + Duration::from_secs(20))
+                .err(),
+            Some(Duration::from_secs(100))
+        );
+        assert!(shoutouts
+            .shoutout("other", now + Duration::from_secs(120))
+            .is_ok());
+        assert_eq!(
+            shoutouts
+                .shoutout("twitchdev", now + Duration::from_secs(600))
+                .err(),
+            Some(Duration::from_secs(3000))
+        );
+    }
+
+    #[test]
+    fn unknown_channels() {
+        let mut shoutouts = Shoutouts::new(
+            &ShoutoutConfig {
+                cooldown_seconds: 0,
+                ..Default::default()
+            },
+            Messages::default(),
+        );
+        let now = Instant::now();
+        assert!(shoutouts.shoutout("nobody", now).is_ok());
+        assert!(matches!(
+            shoutouts.channel_info("nobody", None, now),
+            Some(ChatBotCommand::SendMessage(message)) if message == "I could not find the channel nobody."
+        ));
+        assert!(shoutouts.shoutout("nobody", now).is_ok());
+    }
+}
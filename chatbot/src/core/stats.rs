@@ -0,0 +1,614 @@
+use super::storage::Storage;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet};
+use thiserror::Error;
+
+const USERS_BUCKET: &str = "user_stats";
+const STREAMS_BUCKET: &str = "stream_stats";
+const SAVE_INTERVAL_SECONDS: i64 = 60;
+const TOP_CHATTERS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    pub enabled: bool,
+    // address of the HTTP API serving the statistics, e.g. "127.0.0.1:3031", none disables it
+    pub api_address: Option<String>,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            api_address: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("Could not load statistics [{}]", .0)]
+    Storage(#[from] kv::Error),
+    #[error("Could not write CSV [{}]", .0)]
+    Csv(#[from] csv::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserStats {
+    pub user: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub watch_time_seconds: u64,
+    pub message_count: u64,
+    // number of uses per command name
+    pub commands: BTreeMap<String, u64>,
+    pub streams_attended: u64,
+    // start of the last stream the user attended
+    pub last_stream: DateTime<Utc>,
+    // number of consecutive streams attended, including the last one
+    #[serde(default)]
+    pub stream_streak: u64,
+}
+
+impl UserStats {
+    fn new(user: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            user: user.to_owned(),
+            first_seen: now,
+            last_seen: now,
+            watch_time_seconds: 0,
+            message_count: 0,
+            commands: BTreeMap::default(),
+            streams_attended: 0,
+            last_stream: DateTime::<Utc>::MIN_UTC,
+            stream_streak: 0,
+        }
+    }
+
+    pub fn command_count(&self) -> u64 {
+        self.commands.values().sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopChatter {
+    pub user: String,
+    pub messages: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamStats {
+    pub started: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub peak_chatters: usize,
+    pub unique_chatters: usize,
+    pub message_count: u64,
+    pub messages_per_minute: f64,
+    pub top_chatters: Vec<TopChatter>,
+}
+
+// Flat rows for the CSV export
+#[derive(Debug, Serialize)]
+struct UserStatsRow<'a> {
+    user: &'a str,
+    first_seen: String,
+    last_seen: String,
+    watch_time_seconds: u64,
+    message_count: u64,
+    command_count: u64,
+    // "name:count" pairs separated by spaces
+    commands: String,
+    streams_attended: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamStatsRow {
+    started: String,
+    last_activity: String,
+    peak_chatters: usize,
+    unique_chatters: usize,
+    message_count: u64,
+    messages_per_minute: f64,
+    // "user:messages" pairs separated by spaces
+    top_chatters: String,
+}
+
+/// Collects per user and per stream statistics from joins, parts, messages and commands.
+/// A stream lasts from the start of the bot or the stream going online until it goes online again.
+/// Statistics are kept in memory and saved to the storage at most once a minute.
+#[derive(Debug)]
+pub struct ViewerStats {
+    enabled: bool,
+    storage: Storage,
+    users: HashMap<String, UserStats>,
+    // the statistics of the users as they were when the bot started, none for new users
+    earlier_users: HashMap<String, Option<UserStats>>,
+    changed_users: HashSet<String>,
+    // users currently watching and since when their watch time has not been counted
+    present: HashMap<String, DateTime<Utc>>,
+    stream_started: DateTime<Utc>,
+    // start of the stream before this one, for counting streaks
+    previous_stream: Option<DateTime<Utc>>,
+    last_activity: DateTime<Utc>,
+    peak_chatters: usize,
+    chatter_messages: HashMap<String, u64>,
+    last_save: DateTime<Utc>,
+}
+
+impl ViewerStats {
+    pub fn new(config: &StatsConfig, storage: Storage, now: DateTime<Utc>) -> Self {
+        let previous_stream = all_stream_stats(&storage)
+            .unwrap_or_else(|err| {
+                println!("Could not load stream statistics: {:?}", err);
+                Vec::default()
+            })
+            .into_iter()
+            .map(|stream| stream.started)
+            .filter(|started| *started < now)
+            .max();
+        Self {
+            enabled: config.enabled,
+            storage,
+            users: HashMap::default(),
+            earlier_users: HashMap::default(),
+            changed_users: HashSet::default(),
+            present: HashMap::default(),
+            stream_started: now,
+            previous_stream,
+            last_activity: now,
+            peak_chatters: 0,
+            chatter_messages: HashMap::default(),
+            last_save: now,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn stored_user(storage: &Storage, key: &str) -> Option<UserStats> {
+        storage.get(USERS_BUCKET, key).unwrap_or_else(|err| {
+            println!("Could not load user statistics: {:?}", err);
+            None
+        })
+    }
+
+    fn user_mut(&mut self, user_name: &str, now: DateTime<Utc>) -> &mut UserStats {
+        let key = user_name.to_lowercase();
+        self.changed_users.insert(key.to_owned());
+        let user = match self.users.entry(key.to_owned()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let stored = Self::stored_user(&self.storage, &key);
+                self.earlier_users.insert(key, stored.to_owned());
+                entry.insert(stored.unwrap_or_else(|| UserStats::new(user_name, now)))
+            }
+        };
+        user.last_seen = now;
+        if user.last_stream != self.stream_started {
+            user.stream_streak = if Some(user.last_stream) == self.previous_stream {
+                user.stream_streak + 1
+            } else {
+                1
+            };
+            user.last_stream = self.stream_started;
+            user.streams_attended += 1;
+        }
+        user
+    }
+
+    // Users are present from their join or their first activity until they part
+    fn mark_present(&mut self, user_name: &str, now: DateTime<Utc>) {
+        self.present.entry(user_name.to_lowercase()).or_insert(now);
+        self.peak_chatters = self.peak_chatters.max(self.present.len());
+    }
+
+    fn count_watch_time(&mut self, user_name: &str, now: DateTime<Utc>) {
+        let key = user_name.to_lowercase();
+        if let Some(since) = self.present.get(&key).copied() {
+            let seconds = (now - since).num_seconds().max(0) as u64;
+            self.user_mut(user_name, now).watch_time_seconds += seconds;
+            self.present.insert(key, now);
+        }
+    }
+
+    fn activity(&mut self, now: DateTime<Utc>) {
+        self.last_activity = now;
+        if now - self.last_save >= ChronoDuration::seconds(SAVE_INTERVAL_SECONDS) {
+            self.save(now);
+        }
+    }
+
+    pub fn joined(&mut self, user_name: &str, now: DateTime<Utc>) {
+        if !self.enabled {
+            return;
+        }
+        self.user_mut(user_name, now);
+        self.mark_present(user_name, now);
+        self.activity(now);
+    }
+
+    pub fn parted(&mut self, user_name: &str, now: DateTime<Utc>) {
+        if !self.enabled {
+            return;
+        }
+        self.count_watch_time(user_name, now);
+        self.present.remove(&user_name.to_lowercase());
+        self.activity(now);
+    }
+
+    pub fn message(&mut self, user_name: &str, now: DateTime<Utc>) {
+        if !self.enabled {
+            return;
+        }
+        self.mark_present(user_name, now);
+        self.user_mut(user_name, now).message_count += 1;
+        *self
+            .chatter_messages
+            .entry(user_name.to_lowercase())
+            .or_insert(0) += 1;
+        self.activity(now);
+    }
+
+    pub fn command(&mut self, user_name: &str, command_name: &str, now: DateTime<Utc>) {
+        if !self.enabled {
+            return;
+        }
+        self.mark_present(user_name, now);
+        *self
+            .user_mut(user_name, now)
+            .commands
+            .entry(command_name.to_owned())
+            .or_insert(0) += 1;
+        self.activity(now);
+    }
+
+    /// Statistics of the user including the current watch time
+    pub fn user_stats(&self, user_name: &str, now: DateTime<Utc>) -> Option<UserStats> {
+        let key = user_name.to_lowercase();
+        let mut stats = match self.users.get(&key) {
+            Some(stats) => stats.to_owned(),
+            None => Self::stored_user(&self.storage, &key)?,
+        };
+        if let Some(since) = self.present.get(&key) {
+            stats.watch_time_seconds += (now - *since).num_seconds().max(0) as u64;
+        }
+        Some(stats)
+    }
+
+    /// Statistics of the user from before the bot started, unaffected by joins and messages
+    /// since then. None for users the bot has not seen before.
+    pub fn earlier_user_stats(&self, user_name: &str) -> Option<UserStats> {
+        let key = user_name.to_lowercase();
+        match self.earlier_users.get(&key) {
+            Some(stats) => stats.to_owned(),
+            None => Self::stored_user(&self.storage, &key),
+        }
+    }
+
+    pub fn stream_stats(&self, now: DateTime<Utc>) -> StreamStats {
+        let message_count = self.chatter_messages.values().sum();
+        let minutes = ((now - self.stream_started).num_seconds() as f64 / 60.0).max(1.0);
+        let mut top_chatters: Vec<TopChatter> = self
+            .chatter_messages
+            .iter()
+            .map(|(user, messages)| TopChatter {
+                user: user.to_owned(),
+                messages: *messages,
+            })
+            .collect();
+        top_chatters.sort_by(|first, second| {
+            second
+                .messages
+                .cmp(&first.messages)
+                .then_with(|| first.user.cmp(&second.user))
+        });
+        top_chatters.truncate(TOP_CHATTERS);
+        StreamStats {
+            started: self.stream_started,
+            last_activity: self.last_activity,
+            peak_chatters: self.peak_chatters,
+            unique_chatters: self.chatter_messages.len(),
+            message_count,
+            messages_per_minute: message_count as f64 / minutes,
+            top_chatters,
+        }
+    }
+
+    /// Starts counting a new stream when Twitch reports the stream online.
+    /// The time since the bot started belongs to the new stream if nobody chatted yet.
+    pub fn stream_started(&mut self, started_at: DateTime<Utc>) {
+        if !self.enabled || started_at <= self.stream_started {
+            return;
+        }
+        if self.chatter_messages.is_empty() {
+            if let Err(err) = self
+                .storage
+                .remove(STREAMS_BUCKET, &self.stream_started.to_rfc3339())
+            {
+                println!("Could not remove stream statistics: {:?}", err);
+            }
+            for user in self.users.values_mut() {
+                if user.last_stream == self.stream_started {
+                    user.last_stream = started_at;
+                }
+            }
+        } else {
+            self.save(started_at);
+            self.previous_stream = Some(self.stream_started);
+        }
+        self.stream_started = started_at;
+        self.last_activity = started_at;
+        self.peak_chatters = self.present.len();
+        self.chatter_messages.clear();
+        // users in the chat attend the new stream as well
+        let present: Vec<String> = self.present.keys().cloned().collect();
+        for user_name in present {
+            self.user_mut(&user_name, started_at);
+        }
+    }
+
+    /// Saves the changed statistics, counting the watch time of present users so far
+    pub fn save(&mut self, now: DateTime<Utc>) {
+        self.last_save = now;
+        let present: Vec<String> = self.present.keys().cloned().collect();
+        for user_name in present {
+            self.count_watch_time(&user_name, now);
+        }
+        for key in self.changed_users.drain() {
+            if let Some(stats) = self.users.get(&key) {
+                if let Err(err) = self.storage.set(USERS_BUCKET, &key, stats.to_owned()) {
+                    println!("Could not save user statistics: {:?}", err);
+                }
+            }
+        }
+        let stream_stats = self.stream_stats(now);
+        if let Err(err) = self.storage.set(
+            STREAMS_BUCKET,
+            &self.stream_started.to_rfc3339(),
+            stream_stats,
+        ) {
+            println!("Could not save stream statistics: {:?}", err);
+        }
+    }
+}
+
+pub fn all_user_stats(storage: &Storage) -> Result<Vec<UserStats>, StatsError> {
+    Ok(storage
+        .entries::<UserStats>(USERS_BUCKET)?
+        .into_iter()
+        .map(|(_, stats)| stats)
+        .collect())
+}
+
+pub fn all_stream_stats(storage: &Storage) -> Result<Vec<StreamStats>, StatsError> {
+    Ok(storage
+        .entries::<StreamStats>(STREAMS_BUCKET)?
+        .into_iter()
+        .map(|(_, stats)| stats)
+        .collect())
+}
+
+fn to_csv<T: Serialize>(rows: impl Iterator<Item = T>) -> Result<String, StatsError> {
+    let mut writer = csv::Writer::from_writer(Vec::default());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| csv::Error::from(err.into_error()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub fn user_stats_csv(storage: &Storage) -> Result<String, StatsError> {
+    let stats = all_user_stats(storage)?;
+    to_csv(stats.iter().map(|stats| {
+        UserStatsRow {
+            user: &stats.user,
+            first_seen: stats.first_seen.to_rfc3339(),
+            last_seen: stats.last_seen.to_rfc3339(),
+            watch_time_seconds: stats.watch_time_seconds,
+            message_count: stats.message_count,
+            command_count: stats.command_count(),
+            commands: stats
+                .commands
+                .iter()
+                .map(|(name, count)| format!("{}:{}", name, count))
+                .collect::<Vec<String>>()
+                .join(" "),
+            streams_attended: stats.streams_attended,
+        }
+    }))
+}
+
+pub fn stream_stats_csv(storage: &Storage) -> Result<String, StatsError> {
+    let stats = all_stream_stats(storage)?;
+    to_csv(stats.into_iter().map(|stats| {
+        StreamStatsRow {
+            started: stats.started.to_rfc3339(),
+            last_activity: stats.last_activity.to_rfc3339(),
+            peak_chatters: stats.peak_chatters,
+            unique_chatters: stats.unique_chatters,
+            message_count: stats.message_count,
+            messages_per_minute: stats.messages_per_minute,
+            top_chatters: stats
+                .top_chatters
+                .iter()
+                .map(|chatter| format!("{}:{}", chatter.user, chatter.messages))
+                .collect::<Vec<String>>()
+                .join(" "),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn time(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, 1, 1, 12, 0, 0).unwrap() + ChronoDuration::minutes(minutes)
+    }
+
+    fn viewer_stats(storage: Storage) -> ViewerStats {
+        ViewerStats::new(&StatsConfig::default(), storage, time(0))
+    }
+
+    #[test]
+    fn tracking_user_activity() {
+        let mut stats = viewer_stats(Storage::temporary().unwrap());
+        stats.joined("Carkhy", time(1));
+        stats.message("carkhy", time(2));
+        stats.command("carkhy", "help", time(3));
+        stats.command("carkhy", "help", time(4));
+        stats.parted("carkhy", time(11));
+        let user = stats.user_stats("CARKHY", time(20)).unwrap();
+        assert_eq!(user.user, "Carkhy");
+        assert_eq!(user.first_seen, time(1));
+        assert_eq!(user.last_seen, time(11));
+        assert_eq!(user.watch_time_seconds, 600);
+        assert_eq!(user.message_count, 1);
+        assert_eq!(user.command_count(), 2);
+        assert_eq!(user.streams_attended, 1);
+        assert!(stats.user_stats("nobody", time(20)).is_none());
+    }
+
+    #[test]
+    fn keeping_earlier_user_stats() {
+        let storage = Storage::temporary().unwrap();
+        let mut stats = viewer_stats(storage.clone());
+        stats.joined("carkhy", time(1));
+        assert!(stats.earlier_user_stats("carkhy").is_none());
+        stats.save(time(2));
+
+        let mut stats = ViewerStats::new(&StatsConfig::default(), storage, time(100));
+        stats.joined("carkhy", time(101));
+        stats.message("carkhy", time(102));
+        assert_eq!(
+            stats.earlier_user_stats("Carkhy").unwrap().last_seen,
+            time(2)
+        );
+        assert_eq!(
+            stats.user_stats("carkhy", time(103)).unwrap().last_seen,
+            time(102)
+        );
+    }
+
+    #[test]
+    fn counting_current_watch_time() {
+        let mut stats = viewer_stats(Storage::temporary().unwrap());
+        stats.message("carkhy", time(0));
+        let user = stats.user_stats("carkhy", time(5)).unwrap();
+        assert_eq!(user.watch_time_seconds, 300);
+    }
+
+    #[test]
+    fn statistics_persist_across_streams() {
+        let storage = Storage::temporary().unwrap();
+        let mut stats = viewer_stats(storage.clone());
+        stats.message("carkhy", time(1));
+        stats.save(time(2));
+        let mut next_stream = ViewerStats::new(&StatsConfig::default(), storage, time(60));
+        next_stream.message("carkhy", time(61));
+        let user = next_stream.user_stats("carkhy", time(61)).unwrap();
+        assert_eq!(user.message_count, 2);
+        assert_eq!(user.streams_attended, 2);
+        assert_eq!(user.stream_streak, 2);
+        assert_eq!(user.first_seen, time(1));
+    }
+
+    #[test]
+    fn starting_new_streams() {
+        let storage = Storage::temporary().unwrap();
+        let mut stats = viewer_stats(storage.clone());
+        stats.joined("carkhy", time(1));
+        // nobody chatted before the stream went online, so the time belongs to the stream
+        stats.stream_started(time(5));
+        stats.message("carkhy", time(6));
+        stats.message("botanist", time(6));
+        assert_eq!(stats.stream_stats(time(7)).started, time(5));
+        assert_eq!(stats.stream_stats(time(7)).peak_chatters, 2);
+        assert_eq!(
+            stats
+                .user_stats("carkhy", time(7))
+                .unwrap()
+                .streams_attended,
+            1
+        );
+
+        stats.parted("botanist", time(8));
+        stats.stream_started(time(60));
+        let stream = stats.stream_stats(time(61));
+        assert_eq!(stream.started, time(60));
+        assert_eq!(stream.message_count, 0);
+        assert_eq!(stream.peak_chatters, 1);
+        let user = stats.user_stats("carkhy", time(61)).unwrap();
+        assert_eq!(user.streams_attended, 2);
+        assert_eq!(user.stream_streak, 2);
+        stats.message("botanist", time(62));
+        assert_eq!(
+            stats
+                .user_stats("botanist", time(62))
+                .unwrap()
+                .stream_streak,
+            2
+        );
+        assert_eq!(
+            all_stream_stats(&storage)
+                .unwrap()
+                .into_iter()
+                .map(|stream| stream.started)
+                .collect::<Vec<_>>(),
+            vec![time(5), time(60)]
+        );
+    }
+
+    #[test]
+    fn tracking_stream_statistics() {
+        let mut stats = viewer_stats(Storage::temporary().unwrap());
+        stats.joined("a", time(0));
+        stats.joined("b", time(0));
+        stats.joined("c", time(0));
+        stats.parted("c", time(1));
+        for _ in 0..3 {
+            stats.message("a", time(2));
+        }
+        stats.message("b", time(3));
+        let stream = stats.stream_stats(time(4));
+        assert_eq!(stream.peak_chatters, 3);
+        assert_eq!(stream.unique_chatters, 2);
+        assert_eq!(stream.message_count, 4);
+        assert_eq!(stream.messages_per_minute, 1.0);
+        assert_eq!(
+            stream.top_chatters,
+            vec![
+                TopChatter {
+                    user: "a".to_owned(),
+                    messages: 3
+                },
+                TopChatter {
+                    user: "b".to_owned(),
+                    messages: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn exporting_csv() {
+        let storage = Storage::temporary().unwrap();
+        let mut stats = viewer_stats(storage.clone());
+        stats.command("carkhy", "help", time(0));
+        stats.command("carkhy", "so", time(0));
+        stats.save(time(1));
+        assert_eq!(
+            user_stats_csv(&storage).unwrap(),
+            "user,first_seen,last_seen,watch_time_seconds,message_count,command_count,commands,streams_attended\n\
+             carkhy,2022-01-01T12:00:00+00:00,2022-01-01T12:01:00+00:00,60,0,2,help:1 so:1,1\n"
+        );
+        assert!(stream_stats_csv(&storage)
+            .unwrap()
+            .starts_with("started,last_activity,peak_chatters"));
+    }
+}
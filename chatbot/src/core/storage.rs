@@ -0,0 +1,135 @@
+use kv::{Config, Error, Json, Store};
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(test)]
+use std::env;
+#[cfg(test)]
+use uuid::Uuid;
+
+/// Persistent key value storage of the bot.
+/// Values are stored as JSON in named buckets.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    store: Store,
+}
+
+impl Storage {
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Ok(Self {
+            store: Store::new(Config::new(path))?,
+        })
+    }
+
+    /// Storage that is deleted when it is dropped
+    #[cfg(test)]
+    pub fn temporary() -> Result<Self, Error> {
+        let path = env::temp_dir().join(format!("chatbot_store_{}", Uuid::new_v4()));
+        Ok(Self {
+            store: Store::new(Config::new(path).temporary(true))?,
+        })
+    }
+
+    pub fn get<T: Serialize + DeserializeOwned>(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        let bucket = self.store.bucket::<String, Json<T>>(Some(bucket))?;
+        Ok(bucket.get(key.to_owned())?.map(|value| value.0))
+    }
+
+    pub fn set<T: Serialize + DeserializeOwned>(
+        &self,
+        bucket: &str,
+        key: &str,
+        value: T,
+    ) -> Result<(), Error> {
+        let bucket = self.store.bucket::<String, Json<T>>(Some(bucket))?;
+        bucket.set(key.to_owned(), Json(value))?;
+        bucket.flush()?;
+        Ok(())
+    }
+
+    /// Replaces the value with the one computed from the current value and returns it.
+    /// The update is atomic, other threads changing the value at the same time are retried.
+    pub fn update<T, F>(&self, bucket: &str, key: &str, update: F) -> Result<T, Error>
+    where
+        T: Serialize + DeserializeOwned + Clone,
+        F: Fn(Option<T>) -> T,
+    {
+        let bucket = self.store.bucket::<String, Json<T>>(Some(bucket))?;
+        let value = bucket.transaction(|transaction| {
+            let value = update(transaction.get(key.to_owned())?.map(|value| value.0));
+            transaction.set(key.to_owned(), Json(value.clone()))?;
+            Ok(value)
+        })?;
+        bucket.flush()?;
+        Ok(value)
+    }
+
+    /// All keys and values of the bucket
+    pub fn entries<T: Serialize + DeserializeOwned>(
+        &self,
+        bucket: &str,
+    ) -> Result<Vec<(String, T)>, Error> {
+        let bucket = self.store.bucket::<String, Json<T>>(Some(bucket))?;
+        bucket
+            .iter()
+            .map(|item| {
+                let item = item?;
+                Ok((item.key()?, item.value::<Json<T>>()?.0))
+            })
+            .collect()
+    }
+
+    pub fn remove(&self, bucket: &str, key: &str) -> Result<(), Error> {
+        let bucket = self.store.bucket::<String, kv::Raw>(Some(bucket))?;
+        bucket.remove(key.to_owned())?;
+        bucket.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storing_values() {
+        let storage = Storage::temporary().unwrap();
+        storage.set("bucket", "key", vec![1, 2, 3]).unwrap();
+        assert_eq!(
+            storage.get::<Vec<u32>>("bucket", "key").unwrap(),
+            Some(vec![1, 2, 3])
+        );
+        storage.set("bucket", "other", vec![4]).unwrap();
+        assert_eq!(
+            storage.entries::<Vec<u32>>("bucket").unwrap(),
+            vec![
+                ("key".to_owned(), vec![1, 2, 3]),
+                ("other".to_owned(), vec![4])
+            ]
+        );
+        storage.remove("bucket", "key").unwrap();
+        assert_eq!(storage.get::<Vec<u32>>("bucket", "key").unwrap(), None);
+    }
+
+    #[test]
+    fn updating_values() {
+        let storage = Storage::temporary().unwrap();
+        let increment = |count: Option<u32>| count.unwrap_or_default() + 1;
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        storage.update("bucket", "count", increment).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(storage.get::<u32>("bucket", "count").unwrap(), Some(100));
+    }
+}
@@ -0,0 +1,282 @@
+use super::{time_format::human_duration, ChatBotCommand, Messages};
+use crate::connect::{TwitchRequest, TwitchResponse};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StreamInfoConfig {
+    // how long answers of twitch are reused, changes of the channel are never cached
+    pub cache_seconds: u64,
+}
+
+impl Default for StreamInfoConfig {
+    fn default() -> Self {
+        Self { cache_seconds: 60 }
+    }
+}
+
+/// Answers questions about the stream and its viewers with the help of the Twitch API
+#[derive(Debug)]
+pub struct StreamInfo {
+    cache_duration: Duration,
+    cache: HashMap<TwitchRequest, (TwitchResponse, Instant)>,
+    messages: Messages,
+}
+
+impl StreamInfo {
+    pub fn new(config: &StreamInfoConfig, messages: Messages) -> Self {
+        Self {
+            cache_duration: Duration::from_secs(config.cache_seconds),
+            cache: HashMap::default(),
+            messages,
+        }
+    }
+
+    /// Answers right away from the cache or asks twitch
+    pub fn request(&self, request: TwitchRequest, now: Instant) -> ChatBotCommand {
+        match self.cache.get(&request) {
+            Some((response, fetched)) if now.duration_since(*fetched) < self.cache_duration => {
+                ChatBotCommand::SendMessage(message(&self.messages, &request, response, Utc::now()))
+            }
+            _ => ChatBotCommand::CallTwitch(request),
+        }
+    }
+
+    /// Caches the answer of twitch and turns it into a message for the chat
+    pub fn response(
+        &mut self,
+        request: TwitchRequest,
+        response: TwitchResponse,
+        now: Instant,
+    ) -> ChatBotCommand {
+        let message = message(&self.messages, &request, &response, Utc::now());
+        match (&request, &response) {
+            (_, TwitchResponse::Failed(_)) => {}
+            (TwitchRequest::SetTitle(_), _) | (TwitchRequest::SetGame(_), _) => {
+                // the cached title and game are outdated now
+                self.cache.remove(&TwitchRequest::StreamInfo);
+            }
+            _ => {
+                self.cache.insert(request, (response, now));
+            }
+        }
+        ChatBotCommand::SendMessage(message)
+    }
+}
+
+fn since(time: &DateTime<Utc>, now: DateTime<Utc>, messages: &Messages) -> String {
+    human_duration((now - *time).to_std().unwrap_or_default(), messages)
+}
+
+fn message(
+    messages: &Messages,
+    request: &TwitchRequest,
+    response: &TwitchResponse,
+    now: DateTime<Utc>,
+) -> String {
+    match (request, response) {
+        (_, TwitchResponse::Failed(_)) => messages.text("twitch.failed"),
+        (_, TwitchResponse::Live { started_at }) => messages.format(
+            "twitch.live",
+            &[("duration", &since(started_at, now, messages))],
+        ),
+        (_, TwitchResponse::Offline) => messages.text("twitch.offline"),
+        (_, TwitchResponse::ChannelInfo(info)) => messages.format(
+            "twitch.channel_info",
+            &[
+                ("title", &info.title),
+                (
+                    "game",
+                    &if info.game_name.is_empty() {
+                        messages.text("twitch.no_game")
+                    } else {
+                        info.game_name.to_owned()
+                    },
+                ),
+            ],
+        ),
+        (_, TwitchResponse::TitleChanged(title)) => {
+            messages.format("twitch.title_changed", &[("title", title)])
+        }
+        (_, TwitchResponse::GameChanged(game)) => {
+            messages.format("twitch.game_changed", &[("game", game)])
+        }
+        (TwitchRequest::FollowAge(user), TwitchResponse::FollowedAt(followed_at)) => messages
+            .format(
+                "twitch.follow_age",
+                &[
+                    ("user", user),
+                    ("duration", &since(followed_at, now, messages)),
+                    ("date", &followed_at.format("%Y-%m-%d")),
+                ],
+            ),
+        (TwitchRequest::FollowAge(user), TwitchResponse::NotFound) => {
+            messages.format("twitch.unknown_user", &[("user", user)])
+        }
+        (TwitchRequest::FollowAge(user), _) => {
+            messages.format("twitch.not_following", &[("user", user)])
+        }
+        (TwitchRequest::AccountAge(user), TwitchResponse::AccountCreatedAt(created_at)) => messages
+            .format(
+                "twitch.account_age",
+                &[
+                    ("user", user),
+                    ("duration", &since(created_at, now, messages)),
+                    ("date", &created_at.format("%Y-%m-%d")),
+                ],
+            ),
+        (TwitchRequest::SetGame(game), _) => {
+            messages.format("twitch.unknown_game", &[("game", game)])
+        }
+        (TwitchRequest::AccountAge(user), _) => {
+            messages.format("twitch.unknown_user", &[("user", user)])
+        }
+        (TwitchRequest::ChannelInfo(login), _) => {
+            messages.format("twitch.unknown_channel", &[("channel", login)])
+        }
+        _ => messages.text("twitch.no_channel"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::ChannelInfo;
+    use chrono::{Duration as ChronoDuration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn formatting_answers() {
+        let started_at = now() - ChronoDuration::minutes(90);
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::Uptime,
+                &TwitchResponse::Live { started_at },
+                now()
+            ),
+            "The stream has been live for 1 hour, 30 minutes."
+        );
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::Uptime,
+                &TwitchResponse::Offline,
+                now()
+            ),
+            "The stream is offline."
+        );
+        let followed_at = Utc.with_ymd_and_hms(2021, 3, 15, 8, 0, 0).unwrap();
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::FollowAge("carkhy".to_owned()),
+                &TwitchResponse::FollowedAt(followed_at),
+                now()
+            ),
+            "carkhy has been following for 2 years, 78 days (since 2021-03-15)."
+        );
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::FollowAge("carkhy".to_owned()),
+                &TwitchResponse::NotFollowing,
+                now()
+            ),
+            "carkhy is not following the channel."
+        );
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::FollowAge("nobody".to_owned()),
+                &TwitchResponse::NotFound,
+                now()
+            ),
+            "I could not find the user nobody."
+        );
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::AccountAge("nobody".to_owned()),
+                &TwitchResponse::NotFound,
+                now()
+            ),
+            "I could not find the user nobody."
+        );
+        assert_eq!(
+            message(
+                &Messages::default(),
+                &TwitchRequest::SetGame("Nothing".to_owned()),
+                &TwitchResponse::NotFound,
+                now()
+            ),
+            "I could not find the game Nothing."
+        );
+    }
+
+    #[test]
+    fn caching_answers() {
+        let mut stream_info = StreamInfo::new(&StreamInfoConfig::default(), Messages::default());
+        let now = Instant::now();
+        assert!(matches!(
+            stream_info.request(TwitchRequest::StreamInfo, now),
+            ChatBotCommand::CallTwitch(TwitchRequest::StreamInfo)
+        ));
+        let info = ChannelInfo {
+            login: "twitchdev".to_owned(),
+            display_name: "TwitchDev".to_owned(),
+            game_name: "Science & Technology".to_owned(),
+            title: "Monthly Update".to_owned(),
+        };
+        stream_info.response(
+            TwitchRequest::StreamInfo,
+            TwitchResponse::ChannelInfo(info),
+            now,
+        );
+        assert!(matches!(
+            stream_info.request(TwitchRequest::StreamInfo, now + Duration::from_secs(30)),
+            ChatBotCommand::SendMessage(message) if message == "Title: Monthly Update | Game: Science & Technology"
+        ));
+        assert!(matches!(
+            stream_info.request(TwitchRequest::StreamInfo, now + Duration::from_secs(60)),
+            ChatBotCommand::CallTwitch(_)
+        ));
+
+        // changing the title invalidates the cached title
+        stream_info.response(
+            TwitchRequest::SetTitle("New".to_owned()),
+            TwitchResponse::TitleChanged("New".to_owned()),
+            now,
+        );
+        assert!(matches!(
+            stream_info.request(TwitchRequest::StreamInfo, now),
+            ChatBotCommand::CallTwitch(_)
+        ));
+    }
+
+    #[test]
+    fn failures_are_not_cached() {
+        let mut stream_info = StreamInfo::new(&StreamInfoConfig::default(), Messages::default());
+        let now = Instant::now();
+        assert!(matches!(
+            stream_info.response(
+                TwitchRequest::Uptime,
+                TwitchResponse::Failed("timeout".to_owned()),
+                now
+            ),
+            ChatBotCommand::SendMessage(message) if message == "I could not reach Twitch, please try again later."
+        ));
+        assert!(matches!(
+            stream_info.request(TwitchRequest::Uptime, now),
+            ChatBotCommand::CallTwitch(TwitchRequest::Uptime)
+        ));
+    }
+}
@@ -0,0 +1,116 @@
+use super::Messages;
+use std::time::Duration;
+
+const UNITS: [(&str, u64); 5] = [
+    ("year", 365 * 86400),
+    ("day", 86400),
+    ("hour", 3600),
+    ("minute", 60),
+    ("second", 1),
+];
+
+/// Formats a duration for chat messages using its two largest units, e.g. "2 days, 3 hours"
+pub fn human_duration(duration: Duration, messages: &Messages) -> String {
+    let mut remaining = duration.as_secs();
+    let parts: Vec<String> = UNITS
+        .iter()
+        .filter_map(|(name, seconds)| {
+            let amount = remaining / seconds;
+            remaining %= seconds;
+            match amount {
+                0 => None,
+                1 => Some(messages.text(&format!("duration.{}", name))),
+                _ => Some(messages.format(&format!("duration.{}s", name), &[("count", &amount)])),
+            }
+        })
+        .take(2)
+        .collect();
+    if parts.is_empty() {
+        messages.format("duration.seconds", &[("count", &0)])
+    } else {
+        parts.join(&messages.text("duration.separator"))
+    }
+}
+
+/// Parses durations given in chat, e.g. "90", "90s", "10m", "1h30m" or "2d".
+/// Numbers without a unit are seconds. Durations too long to count are `None`.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_lowercase();
+    if text.is_empty() {
+        return None;
+    }
+    let mut seconds: u64 = 0;
+    let mut amount = String::new();
+    for character in text.chars() {
+        if character.is_ascii_digit() {
+            amount.push(character);
+            continue;
+        }
+        let unit = match character {
+            'w' => 7 * 86400,
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let unit_seconds = amount.parse::<u64>().ok()?.checked_mul(unit)?;
+        seconds = seconds.checked_add(unit_seconds)?;
+        amount.clear();
+    }
+    if !amount.is_empty() {
+        seconds = seconds.checked_add(amount.parse().ok()?)?;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_durations() {
+        let messages = Messages::default();
+        assert_eq!(
+            human_duration(Duration::from_secs(0), &messages),
+            "0 seconds"
+        );
+        assert_eq!(
+            human_duration(Duration::from_secs(1), &messages),
+            "1 second"
+        );
+        assert_eq!(
+            human_duration(Duration::from_secs(61), &messages),
+            "1 minute, 1 second"
+        );
+        assert_eq!(
+            human_duration(Duration::from_secs(7200), &messages),
+            "2 hours"
+        );
+        assert_eq!(
+            human_duration(Duration::from_secs(2 * 86400 + 3 * 3600 + 59), &messages),
+            "2 days, 3 hours"
+        );
+        assert_eq!(
+            human_duration(Duration::from_secs(800 * 86400), &messages),
+            "2 years, 70 days"
+        );
+    }
+
+    #[test]
+    fn parsing_durations() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10m"), Some(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("2D"), Some(Duration::from_secs(2 * 86400)));
+        assert_eq!(parse_duration("0m"), Some(Duration::from_secs(0)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("ten"), None);
+        // too long to count
+        assert_eq!(parse_duration("99999999999999999999w"), None);
+        assert_eq!(parse_duration("9999999999999999w"), None);
+        assert_eq!(parse_duration("18446744073709551615s1s"), None);
+    }
+}
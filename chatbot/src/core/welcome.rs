@@ -0,0 +1,274 @@
+use super::stats::UserStats;
+use crate::connect::TextMessage;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WelcomeConfig {
+    pub enabled: bool,
+    // templates support the variables {user} and {streak} (consecutive streams attended)
+    pub first_message: String,
+    pub welcome_back: String,
+    // a regular has attended at least this many streams
+    pub regular_streams: u64,
+    // regulars are welcomed back after being absent for this many days
+    pub absence_days: i64,
+    // minimum time between two welcomes, so a raid does not flood the chat
+    pub min_interval_seconds: u64,
+    // users who are never welcomed, e.g. other bots
+    pub ignored_users: Vec<String>,
+}
+
+impl Default for WelcomeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            first_message: "Welcome to the chat, {user}!".to_owned(),
+            welcome_back: "Welcome back, {user}! Good to see you again.".to_owned(),
+            regular_streams: 3,
+            absence_days: 14,
+            min_interval_seconds: 30,
+            ignored_users: Vec::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Greeting {
+    FirstTime,
+    WelcomeBack,
+}
+
+/// Greets first-time chatters and regulars returning after a long absence
+#[derive(Debug)]
+pub struct Greeter {
+    enabled: bool,
+    first_message: String,
+    welcome_back: String,
+    regular_streams: u64,
+    absence: ChronoDuration,
+    min_interval: Duration,
+    ignored_users: HashSet<String>,
+    // users greeted since the bot started, nobody is greeted twice
+    greeted: HashSet<String>,
+    last_greeting: Option<Instant>,
+}
+
+impl Greeter {
+    pub fn new(config: &WelcomeConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            first_message: config.first_message.to_owned(),
+            welcome_back: config.welcome_back.to_owned(),
+            regular_streams: config.regular_streams,
+            absence: ChronoDuration::days(config.absence_days),
+            min_interval: Duration::from_secs(config.min_interval_seconds),
+            ignored_users: config
+                .ignored_users
+                .iter()
+                .map(|user| user.to_lowercase())
+                .collect(),
+            greeted: HashSet::default(),
+            last_greeting: None,
+        }
+    }
+
+    /// The greeting the author of the message deserves, judged by the statistics from before
+    /// the bot started. Without tracked statistics only twitch's first message tag is considered.
+    pub fn greeting(
+        &self,
+        message: &TextMessage,
+        previous: Option<&UserStats>,
+        tracked: bool,
+        now: DateTime<Utc>,
+    ) -> Option<Greeting> {
+        let user_name = message.user.name.to_lowercase();
+        if !self.enabled
+            || self.ignored_users.contains(&user_name)
+            || self.greeted.contains(&user_name)
+        {
+            return None;
+        }
+        match previous {
+            _ if message.first_message => Some(Greeting::FirstTime),
+            None if tracked => Some(Greeting::FirstTime),
+            Some(stats)
+                if stats.streams_attended >= self.regular_streams
+                    && now - stats.last_seen >= self.absence =>
+            {
+                Some(Greeting::WelcomeBack)
+            }
+            _ => None,
+        }
+    }
+
+    /// The welcome message for the user, none if the last welcome was too recent
+    pub fn welcome(
+        &mut self,
+        greeting: Greeting,
+        user_name: &str,
+        streak: u64,
+        now: Instant,
+    ) -> Option<String> {
+        if self
+            .last_greeting
+            .is_some_and(|last| now.duration_since(last) < self.min_interval)
+        {
+            return None;
+        }
+        self.last_greeting = Some(now);
+        self.greeted.insert(user_name.to_lowercase());
+        let template = match greeting {
+            Greeting::FirstTime => &self.first_message,
+            Greeting::WelcomeBack => &self.welcome_back,
+        };
+        Some(
+            template
+                .replace("{user}", user_name)
+                .replace("{streak}", &streak.to_string()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect::UserInfo;
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+
+    fn greeter() -> Greeter {
+        Greeter::new(&WelcomeConfig {
+            enabled: true,
+            ignored_users: vec!["StreamElements".to_owned()],
+            ..Default::default()
+        })
+    }
+
+    fn message(user_name: &str, first_message: bool) -> TextMessage {
+        TextMessage {
+            text: "hello".to_owned(),
+            user: UserInfo {
+                name: user_name.to_owned(),
+                badges: HashSet::default(),
+            },
+            first_message,
+            ..Default::default()
+        }
+    }
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, 1, day, 12, 0, 0).unwrap()
+    }
+
+    fn stats(streams_attended: u64, last_seen: DateTime<Utc>) -> UserStats {
+        UserStats {
+            user: "carkhy".to_owned(),
+            first_seen: day(1),
+            last_seen,
+            watch_time_seconds: 0,
+            message_count: 1,
+            commands: BTreeMap::default(),
+            streams_attended,
+            last_stream: last_seen,
+            stream_streak: 1,
+        }
+    }
+
+    #[test]
+    fn choosing_greetings() {
+        let greeter = greeter();
+        assert_eq!(
+            greeter.greeting(&message("carkhy", true), None, false, day(20)),
+            Some(Greeting::FirstTime)
+        );
+        assert_eq!(
+            greeter.greeting(&message("carkhy", false), None, true, day(20)),
+            Some(Greeting::FirstTime)
+        );
+        assert_eq!(
+            greeter.greeting(&message("carkhy", false), None, false, day(20)),
+            None
+        );
+        assert_eq!(
+            greeter.greeting(
+                &message("carkhy", false),
+                Some(&stats(3, day(1))),
+                true,
+                day(20)
+            ),
+            Some(Greeting::WelcomeBack)
+        );
+        assert_eq!(
+            greeter.greeting(
+                &message("carkhy", false),
+                Some(&stats(3, day(19))),
+                true,
+                day(20)
+            ),
+            None
+        );
+        assert_eq!(
+            greeter.greeting(
+                &message("carkhy", false),
+                Some(&stats(1, day(1))),
+                true,
+                day(20)
+            ),
+            None
+        );
+        assert_eq!(
+            greeter.greeting(&message("streamelements", true), None, true, day(20)),
+            None
+        );
+    }
+
+    #[test]
+    fn throttling_welcomes() {
+        let mut greeter = greeter();
+        let now = Instant::now();
+        assert_eq!(
+            greeter.welcome(Greeting::FirstTime, "carkhy", 1, now),
+            Some("Welcome to the chat, carkhy!".to_owned())
+        );
+        assert_eq!(
+            greeter.greeting(&message("carkhy", true), None, true, day(20)),
+            None
+        );
+        assert_eq!(
+            greeter.welcome(
+                Greeting::FirstTime,
+                "raider",
+                1,
+                now + Duration::from_secs(5)
+            ),
+            None
+        );
+        assert!(greeter
+            .welcome(
+                Greeting::FirstTime,
+                "raider",
+                1,
+                now + Duration::from_secs(30)
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn filling_templates() {
+        let mut greeter = Greeter::new(&WelcomeConfig {
+            enabled: true,
+            welcome_back: "{user} is back for {streak} streams in a row".to_owned(),
+            ..Default::default()
+        });
+        assert_eq!(
+            greeter.welcome(Greeting::WelcomeBack, "Carkhy", 4, Instant::now()),
+            Some("Carkhy is back for 4 streams in a row".to_owned())
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! A Twitch chat bot. Besides its built-in commands, the bot runs the commands added
+//! with `ChatBot::register_command`, each of them a `CommandHandler`.
+
+pub mod api;
+pub mod app_config;
+pub mod connect;
+pub mod core;
+
+pub use crate::core::{ChatBot, CommandHandler, CommandRegistry};
@@ -1,23 +1,25 @@
-use crate::{
-    connect::ChatBotEvent,
+use chatbot::{
+    api,
+    app_config::{AppConfig, PathConfig},
+    connect::{
+        AccessTokenDispenser, ChatBotEvent, EventSubConnector, HelixClient, TwitchChatConnector,
+    },
     core::{
-        ChatBot,
+        retrain_classifier, stream_stats_csv, user_stats_csv, BotConfig,
         ChatBotCommand::{self, *},
+        ChatLog, Messages, Storage,
     },
+    ChatBot,
 };
-use app_config::AppConfig;
-use connect::TwitchChatConnector;
 use std::sync::mpsc;
-use std::{error::Error, sync::mpsc::Sender};
+use std::{env, error::Error, fs, sync::mpsc::Sender};
 use thread_timer::ThreadTimer;
 
-pub mod app_config;
-mod connect;
-mod core;
-
 fn process_command(
     command: ChatBotCommand,
     connector: &TwitchChatConnector,
+    helix: &HelixClient,
+    channel: &str,
     bot_event_sender: Sender<ChatBotEvent>,
 ) -> Result<(), Box<dyn Error>> {
     match command {
@@ -38,26 +40,106 @@ fn process_command(
         }
         MultipleCommands(new_commands) => {
             for command in new_commands {
-                process_command(command, connector, bot_event_sender.clone())?;
+                process_command(command, connector, helix, channel, bot_event_sender.clone())?;
             }
         }
+        Moderate(action) => {
+            println!("Moderating: {:?}", &action);
+            connector.moderate(&action)?;
+        }
+        CallTwitch(request) => {
+            // the answer comes back to the bot as an event, so the bot is not blocked meanwhile
+            let helix = helix.clone();
+            let channel = channel.to_owned();
+            tokio::spawn(async move {
+                let response = helix.answer(&channel, &request).await;
+                let _ = bot_event_sender.send(ChatBotEvent::TwitchResponse(request, response));
+            });
+        }
     }
     Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let paths = PathConfig::from_env();
+    let bot_config = BotConfig::load(paths.bot_config_file())?;
+    let storage = Storage::open(paths.bot_storage_path())?;
+
+    let chat_log = ChatLog::open(paths.bot_log_directory(), &bot_config.chat_log)?;
+
+    // `chatbot retrain-classifier [file]` replaces the spam model with one trained from the
+    // file's labelled lines ("spam<TAB>message" or "ham<TAB>message") or from the chat log
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("retrain-classifier") {
+        let labelled_messages = match args.get(2) {
+            Some(file) => fs::read_to_string(file)?,
+            None => chat_log.labelled_messages()?,
+        };
+        let (spam, ham) = retrain_classifier(&storage, &labelled_messages)?;
+        println!(
+            "Trained the spam classifier with {} spam and {} ham messages",
+            spam, ham
+        );
+        return Ok(());
+    }
+
+    // `chatbot export-stats <users.csv> <streams.csv>` writes the viewer statistics as CSV
+    if args.get(1).map(String::as_str) == Some("export-stats") {
+        let users_file = args.get(2).map_or("users.csv", String::as_str);
+        let streams_file = args.get(3).map_or("streams.csv", String::as_str);
+        fs::write(users_file, user_stats_csv(&storage)?)?;
+        fs::write(streams_file, stream_stats_csv(&storage)?)?;
+        println!(
+            "Exported the viewer statistics to {} and {}",
+            users_file, streams_file
+        );
+        return Ok(());
+    }
+
+    // only the bot itself needs the twitch credentials, the commands above run offline
     let app_config = AppConfig::new()?;
+    let messages = Messages::new(&bot_config.messages, app_config.channel_name())?;
+
+    if let Some(address) = &bot_config.stats.api_address {
+        api::start_stats_api(address, app_config.bot_api_token(), storage.clone())
+            .map_err(|err| format!("Could not start the statistics API [{}]", err))?;
+    }
 
     let (tx, rx) = mpsc::channel();
 
-    let connector = TwitchChatConnector::new(&app_config, tx.clone()).await;
-    connector.send_message("Hello, world!")?;
+    let mut access_token_dispenser = AccessTokenDispenser::new(&app_config).await?;
+    let connector = TwitchChatConnector::new(
+        &app_config,
+        &mut access_token_dispenser,
+        &bot_config.commands.prefix,
+        tx.clone(),
+    )
+    .await;
+    connector.send_message(&messages.text("connected"))?;
+    let helix = HelixClient::new(
+        app_config.twitch_helix_url(),
+        app_config.twitch_client_id(),
+        access_token_dispenser,
+    );
+    let _event_sub_connector = EventSubConnector::new(
+        app_config.twitch_eventsub_url(),
+        helix.clone(),
+        app_config.channel_name(),
+        app_config.bot_user_name(),
+        tx.clone(),
+    );
 
-    let mut chat_bot = ChatBot::new();
+    let mut chat_bot = ChatBot::with_config(&bot_config, messages, storage, chat_log)?;
     while let Ok(message) = rx.recv() {
         if let Some(bot_command) = chat_bot.handle_event(message) {
-            process_command(bot_command, &connector, tx.clone())?;
+            process_command(
+                bot_command,
+                &connector,
+                &helix,
+                app_config.channel_name(),
+                tx.clone(),
+            )?;
         }
     }
     Ok(())